@@ -9,7 +9,7 @@ use rusoto_s3::{GetObjectRequest, Object, S3Client, S3};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use std::{
-	convert::identity, io::{self}, time::Duration
+	convert::identity, fmt::Display, io::{self}, str::FromStr, time::Duration
 };
 
 use amadeus_core::{
@@ -93,7 +93,7 @@ FnMutNamed! {
 					})
 					.then(|x: Result<String, io::Error>| async {
 						if let Ok(x) = x {
-							Ok(CloudfrontRow::from_line(&x))
+							CloudfrontRow::from_line(&x)
 						} else {
 							Err(AwsError::from(x.err().unwrap()))
 						}
@@ -175,67 +175,93 @@ impl Data for CloudfrontRow {
 	}
 }
 impl CloudfrontRow {
-	fn from_line(line: &str) -> Self {
+	fn from_line(line: &str) -> Result<Self, AwsError> {
+		Self::parse(line).map_err(|err| AwsError::Record(line.to_owned(), err))
+	}
+	fn parse(line: &str) -> Result<Self, String> {
+		fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String>
+		where
+			T::Err: Display,
+		{
+			value
+				.parse()
+				.map_err(|err| format!("invalid {} {:?}: {}", name, value, err))
+		}
 		let mut values = line.split('\t');
-		let date = values.next().unwrap();
-		let time = values.next().unwrap();
-		let x_edge_location = values.next().unwrap();
-		let sc_bytes = values.next().unwrap();
-		let c_ip = values.next().unwrap();
-		let cs_method = values.next().unwrap();
-		let cs_host = values.next().unwrap();
-		let cs_uri_stem = values.next().unwrap();
-		let sc_status = values.next().unwrap();
-		let cs_referer = values.next().unwrap();
-		let cs_user_agent = values.next().unwrap();
-		let cs_uri_query = values.next().unwrap();
-		let cs_cookie = values.next().unwrap();
-		let x_edge_result_type = values.next().unwrap();
-		let x_edge_request_id = values.next().unwrap();
-		let x_host_header = values.next().unwrap();
-		let cs_protocol = values.next().unwrap();
-		let cs_bytes = values.next().unwrap();
-		let time_taken = values.next().unwrap();
-		let x_forwarded_for = values.next().unwrap();
-		let ssl_protocol = values.next().unwrap();
-		let ssl_cipher = values.next().unwrap();
-		let x_edge_response_result_type = values.next().unwrap();
-		let cs_protocol_version = values.next().unwrap();
-		let fle_status = values.next().unwrap();
-		let fle_encrypted_fields = values.next().unwrap();
-		assert_eq!(values.next(), None);
-		let time = DateTime::from_chrono(&Utc.from_utc_datetime(&NaiveDateTime::new(
-			NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap(),
-			NaiveTime::parse_from_str(&time, "%H:%M:%S").unwrap(),
-		)));
+		let mut next = |name: &str| {
+			values
+				.next()
+				.ok_or_else(|| format!("missing field {}", name))
+		};
+		let date = next("date")?;
+		let time = next("time")?;
+		let x_edge_location = next("x-edge-location")?;
+		let sc_bytes = next("sc-bytes")?;
+		let c_ip = next("c-ip")?;
+		let cs_method = next("cs-method")?;
+		let cs_host = next("cs(Host)")?;
+		let cs_uri_stem = next("cs-uri-stem")?;
+		let sc_status = next("sc-status")?;
+		let cs_referer = next("cs(Referer)")?;
+		let cs_user_agent = next("cs(User-Agent)")?;
+		let cs_uri_query = next("cs-uri-query")?;
+		let cs_cookie = next("cs(Cookie)")?;
+		let x_edge_result_type = next("x-edge-result-type")?;
+		let x_edge_request_id = next("x-edge-request-id")?;
+		let x_host_header = next("x-host-header")?;
+		let cs_protocol = next("cs-protocol")?;
+		let cs_bytes = next("cs-bytes")?;
+		let time_taken = next("time-taken")?;
+		let x_forwarded_for = next("x-forwarded-for")?;
+		let ssl_protocol = next("ssl-protocol")?;
+		let ssl_cipher = next("ssl-cipher")?;
+		let x_edge_response_result_type = next("x-edge-response-result-type")?;
+		let cs_protocol_version = next("cs-protocol-version")?;
+		let fle_status = next("fle-status")?;
+		let fle_encrypted_fields = next("fle-encrypted-fields")?;
+		if values.next().is_some() {
+			return Err(String::from("too many fields"));
+		}
+		let time = DateTime::from_chrono(
+			&Utc.from_utc_datetime(&NaiveDateTime::new(
+				NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+					.map_err(|err| format!("invalid date {:?}: {}", date, err))?,
+				NaiveTime::parse_from_str(&time, "%H:%M:%S")
+					.map_err(|err| format!("invalid time {:?}: {}", time, err))?,
+			)),
+		);
 		let status = if sc_status != "000" {
-			Some(StatusCode::from_bytes(sc_status.as_bytes()).unwrap())
+			Some(
+				StatusCode::from_bytes(sc_status.as_bytes())
+					.map_err(|err| format!("invalid sc-status {:?}: {}", sc_status, err))?,
+			)
 		} else {
 			None
 		};
 		#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-		let time_taken =
-			Duration::from_millis((time_taken.parse::<f64>().unwrap() * 1000.0).round() as u64);
-		CloudfrontRow {
+		let time_taken = Duration::from_millis(
+			(parse::<f64>("time-taken", time_taken)? * 1000.0).round() as u64,
+		);
+		let url = format!(
+			"{}://{}{}{}{}",
+			cs_protocol,
+			x_host_header,
+			cs_uri_stem,
+			if cs_uri_query == "-" { "" } else { "?" },
+			if cs_uri_query == "-" {
+				""
+			} else {
+				&cs_uri_query
+			}
+		);
+		Ok(CloudfrontRow {
 			time,
 			edge_location: x_edge_location.to_owned(),
-			response_bytes: sc_bytes.parse().unwrap(),
-			remote_ip: c_ip.parse().unwrap(),
-			method: cs_method.parse().unwrap(),
+			response_bytes: parse("sc-bytes", sc_bytes)?,
+			remote_ip: parse("c-ip", c_ip)?,
+			method: parse("cs-method", cs_method)?,
 			host: cs_host.to_owned(),
-			url: Url::parse(&format!(
-				"{}://{}{}{}{}",
-				cs_protocol,
-				x_host_header,
-				cs_uri_stem,
-				if cs_uri_query == "-" { "" } else { "?" },
-				if cs_uri_query == "-" {
-					""
-				} else {
-					&cs_uri_query
-				}
-			))
-			.unwrap(),
+			url: Url::parse(&url).map_err(|err| format!("invalid url {:?}: {}", url, err))?,
 			status,
 			user_agent: if cs_user_agent != "-" {
 				Some(cs_user_agent.to_owned())
@@ -254,7 +280,7 @@ impl CloudfrontRow {
 			},
 			result_type: x_edge_result_type.to_owned(),
 			request_id: x_edge_request_id.to_owned(),
-			request_bytes: cs_bytes.parse().unwrap(),
+			request_bytes: parse("cs-bytes", cs_bytes)?,
 			time_taken,
 			forwarded_for: if x_forwarded_for != "-" {
 				Some(x_forwarded_for.to_owned())
@@ -278,7 +304,7 @@ impl CloudfrontRow {
 			} else {
 				None
 			},
-		}
+		})
 	}
 }
 
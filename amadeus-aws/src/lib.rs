@@ -46,7 +46,9 @@ use std::{
 	error, fmt::{self, Display}, future::Future, io, ops::FnMut, time::Duration
 };
 
-use amadeus_core::util::{IoError, ResultExpand};
+use amadeus_core::{
+	par_sink::RecordError, util::{IoError, ResultExpand}
+};
 
 #[doc(inline)]
 pub use cloudfront::{Cloudfront, CloudfrontRow};
@@ -178,6 +180,8 @@ pub enum AwsError {
 	ParseError(String),
 	Unknown(rusoto_core::request::BufferedHttpResponse),
 	Io(IoError),
+	/// A record that couldn't be parsed, and why.
+	Record(String, String),
 }
 impl Clone for AwsError {
 	fn clone(&self) -> Self {
@@ -202,6 +206,7 @@ impl Clone for AwsError {
 				headers: headers.clone(),
 			}),
 			Self::Io(err) => Self::Io(err.clone()),
+			Self::Record(record, err) => Self::Record(record.clone(), err.clone()),
 		}
 	}
 }
@@ -216,6 +221,7 @@ impl PartialEq for AwsError {
 			(Self::Credentials(a), Self::Credentials(b)) => a == b,
 			(Self::Unknown(a), Self::Unknown(b)) => a == b,
 			(Self::Io(a), Self::Io(b)) => a == b,
+			(Self::Record(a, a_err), Self::Record(b, b_err)) => a == b && a_err == b_err,
 			_ => false,
 		}
 	}
@@ -232,6 +238,16 @@ impl Display for AwsError {
 			Self::Credentials(err) => err.fmt(f),
 			Self::Unknown(err) => fmt::Debug::fmt(err, f),
 			Self::Io(err) => err.fmt(f),
+			Self::Record(_record, err) => err.fmt(f),
+		}
+	}
+}
+impl RecordError for AwsError {
+	fn record(&self) -> Option<&str> {
+		if let Self::Record(record, _err) = self {
+			Some(record)
+		} else {
+			None
 		}
 	}
 }
@@ -2,22 +2,53 @@
 
 #![allow(clippy::type_complexity)]
 
+mod commit;
 mod local;
+mod split;
 
 use async_trait::async_trait;
-use futures::{future::LocalBoxFuture, ready};
+use futures::{future::LocalBoxFuture, ready, FutureExt, TryFutureExt};
 use pin_project::pin_project;
 use std::{
-	convert::TryFrom, error::Error, ffi, fmt, future::Future, io, pin::Pin, sync::Arc, task::{Context, Poll}
+	cell::Cell, convert::TryFrom, error::Error, ffi, fmt, future::Future, io, pin::Pin, sync::Arc, task::{Context, Poll}
 };
 use widestring::U16String;
 
 use crate::pool::ProcessSend;
 
+pub use commit::{
+	existing_file, CommitReducer, CommitReducerAsync, PartFile, PartFileFormat, PartFileReducer, PartFileReducerAsync, PartFileSink, WriteMode
+};
 pub use local::LocalFile;
+pub use split::{Split, SplitPage, Splits, SplitsError};
 
 const PAGE_SIZE: usize = 10 * 1024 * 1024; // `Reader` reads this many bytes at a time
 
+thread_local! {
+	static BYTES_READ: Cell<u64> = Cell::new(0);
+}
+
+/// The number of bytes read from [`Page`]s with [`read_counted`] on this thread, so
+/// they can be attributed to the task being polled.
+pub(crate) fn bytes_read() -> u64 {
+	BYTES_READ.with(Cell::get)
+}
+
+/// Reads `len` bytes of `page` from `offset` like [`Page::read`], counting them once
+/// they arrive towards the bytes reported by [`Progress`](crate::par_stream::Progress)
+/// for the task being polled. A [`Reader`] reads with this.
+pub fn read_counted<P: Page + ?Sized>(
+	page: &P, offset: u64, len: usize,
+) -> LocalBoxFuture<'static, Result<Box<[u8]>, P::Error>> {
+	page.read(offset, len)
+		.map_ok(|buf| {
+			let read = buf.len() as u64;
+			BYTES_READ.with(|bytes_read| bytes_read.set(bytes_read.get() + read));
+			buf
+		})
+		.boxed_local()
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct OsString {
 	buf: U16String,
@@ -207,6 +238,32 @@ pub trait Partition: Clone + fmt::Debug + ProcessSend + 'static {
 
 	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error>;
 }
+/// A directory that can be written to. Paths are relative to the directory.
+///
+/// This is what [`Destination`](crate::Destination)s write their output into; it's
+/// serializable so that it can be sent to the workers that do the writing.
+#[async_trait(?Send)]
+pub trait OutputDirectory: Clone + fmt::Debug + ProcessSend + 'static {
+	type Page: Page<Error = Self::Error>;
+	type Error: Error
+		+ Clone
+		+ PartialEq
+		+ From<io::Error>
+		+ Into<io::Error>
+		+ ProcessSend
+		+ 'static;
+
+	/// Lists the files in the directory, recursively.
+	async fn files(&self) -> Result<Vec<PathBuf>, Self::Error>;
+	/// Creates a file, truncating it if it already exists, along with any missing
+	/// parent directories.
+	async fn create(&self, path: &PathBuf) -> Result<Self::Page, Self::Error>;
+	/// Renames a file, replacing `to` if it already exists.
+	async fn rename(&self, from: &PathBuf, to: &PathBuf) -> Result<(), Self::Error>;
+	/// Removes a file.
+	async fn remove(&self, path: &PathBuf) -> Result<(), Self::Error>;
+}
+
 #[allow(clippy::len_without_is_empty)]
 pub trait Page {
 	type Error: Error + Clone + PartialEq + Into<io::Error> + ProcessSend + 'static;
@@ -304,7 +361,7 @@ where
 			let start = *self_.offset;
 			let len = buf.len();
 			let len = len.min(PAGE_SIZE);
-			let pending = self_.page.read(start, len);
+			let pending = read_counted(&*self_.page, start, len);
 			*self_.pending = Some(pending);
 		}
 		let ret = ready!(self_.pending.as_mut().as_pin_mut().unwrap().poll(cx));
@@ -315,7 +372,8 @@ where
 				buf_.len()
 			})
 			.map_err(Into::into);
-		*self_.offset += u64::try_from(ret.as_ref().ok().cloned().unwrap_or(0)).unwrap();
+		let read = u64::try_from(ret.as_ref().ok().cloned().unwrap_or(0)).unwrap();
+		*self_.offset += read;
 		Poll::Ready(ret)
 	}
 }
@@ -0,0 +1,543 @@
+use educe::Educe;
+use futures::{future::LocalBoxFuture, ready, Stream};
+use pin_project::pin_project;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::Debug, future::Future, io, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}
+};
+
+use super::{OutputDirectory, Page, PathBuf};
+use crate::{
+	par_sink::{
+		DistributedSink, ExtendReducer, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+	}, par_stream::Identity, pipe::Sink
+};
+
+/// The number of bytes each worker buffers before writing them out to its part file.
+const BUF_SIZE: usize = 1024 * 1024;
+
+/// Returns a new temporary name for a part file. It starts with `.` so that readers
+/// skip it while it's being written.
+fn part_file_temp_name() -> String {
+	format!(".part-{:016x}.tmp", thread_rng().gen::<u64>())
+}
+
+/// What to do if a [`Destination`](crate::Destination)'s output directory already
+/// contains files. In every mode the directory is created if it doesn't exist.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum WriteMode {
+	/// Fail, leaving the existing files untouched.
+	FailIfExists,
+	/// Replace the existing files.
+	Overwrite,
+	/// Add to the existing files.
+	Append,
+}
+impl Default for WriteMode {
+	fn default() -> Self {
+		Self::FailIfExists
+	}
+}
+
+/// How rows are encoded into the bytes of a part file by a [`PartFileReducer`].
+pub trait PartFileFormat<D, Row>:
+	Clone + Send + Serialize + for<'de> Deserialize<'de> + 'static
+where
+	D: OutputDirectory,
+{
+	/// The state kept while writing a file.
+	type Writer;
+	type Error: From<D::Error> + Send + 'static;
+
+	/// The extension of the committed files.
+	const EXTENSION: &'static str;
+
+	fn writer(&self) -> Result<Self::Writer, Self::Error>;
+	/// Encode `row`, appending whatever is ready to be written out to `buf`.
+	fn write(
+		&self, writer: &mut Self::Writer, row: Row, buf: &mut Vec<u8>,
+	) -> Result<(), Self::Error>;
+	/// Finish the file, appending the rest of it to `buf`.
+	fn close(&self, writer: Self::Writer, buf: &mut Vec<u8>) -> Result<(), Self::Error>;
+}
+
+/// A sink that writes rows to files in an [`OutputDirectory`], encoded with a
+/// [`PartFileFormat`].
+///
+/// Each worker writes its rows to its own part file with a [`PartFileReducer`]; once
+/// they've all finished, the part files are committed with a [`CommitReducer`].
+#[derive(Educe)]
+#[educe(Clone(bound = "D: Clone, F: Clone"), Debug(bound = "D: Debug"))]
+pub struct PartFileSink<D, Row, F> {
+	directory: D,
+	#[educe(Debug(ignore))]
+	format: F,
+	mode: WriteMode,
+	existing: Option<PathBuf>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row, F> PartFileSink<D, Row, F>
+where
+	D: OutputDirectory,
+{
+	/// `existing` is the file that [`existing_file`] found in the directory when the
+	/// destination was created, if any.
+	pub fn new(directory: D, format: F, mode: WriteMode, existing: Option<PathBuf>) -> Self {
+		Self {
+			directory,
+			format,
+			mode,
+			existing,
+			marker: PhantomData,
+		}
+	}
+	fn into_reducers<Item>(self) -> (PartFileReducer<D, Row, F>, CommitReducer<D, Item>)
+	where
+		F: PartFileFormat<D, Row>,
+	{
+		let reduce_c = CommitReducer::new(
+			self.directory.clone(),
+			F::EXTENSION,
+			self.mode,
+			self.existing,
+		);
+		let reduce_a = PartFileReducer {
+			directory: self.directory,
+			format: self.format,
+			abort: reduce_c.will_fail(),
+			marker: PhantomData,
+		};
+		(reduce_a, reduce_c)
+	}
+}
+impl<D, Row, F> ParallelSink<Row> for PartFileSink<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+{
+	type Done = Result<(), F::Error>;
+	type Pipe = Identity;
+	type ReduceA = PartFileReducer<D, Row, F>;
+	type ReduceC = CommitReducer<D, PartFile<F::Error>>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (reduce_a, reduce_c) = self.into_reducers();
+		(Identity, reduce_a, reduce_c)
+	}
+}
+impl<D, Row, F> DistributedSink<Row> for PartFileSink<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+	F::Error: Serialize + for<'de> Deserialize<'de>,
+{
+	type Done = Result<(), F::Error>;
+	type Pipe = Identity;
+	type ReduceA = PartFileReducer<D, Row, F>;
+	type ReduceB = ExtendReducer<PartFile<F::Error>, Vec<Result<String, F::Error>>>;
+	type ReduceC = CommitReducer<D, Vec<Result<String, F::Error>>>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (reduce_a, reduce_c) = self.into_reducers();
+		(Identity, reduce_a, ExtendReducer::default(), reduce_c)
+	}
+}
+
+/// The temporary name of the part file a worker wrote, or `None` if it had no rows.
+pub type PartFile<E> = Option<Result<String, E>>;
+
+/// Writes a worker's rows to a part file with a temporary name, yielding that name.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "D: Clone, F: Clone"))]
+#[serde(
+	bound(serialize = "D: Serialize, F: Serialize"),
+	bound(deserialize = "D: Deserialize<'de>, F: Deserialize<'de>")
+)]
+pub struct PartFileReducer<D, Row, F> {
+	directory: D,
+	format: F,
+	abort: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row, F> Reducer<Row> for PartFileReducer<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+{
+	type Done = PartFile<F::Error>;
+	type Async = PartFileReducerAsync<D, Row, F>;
+
+	fn into_async(self) -> Self::Async {
+		PartFileReducerAsync {
+			directory: self.directory,
+			format: self.format,
+			abort: self.abort,
+			name: part_file_temp_name(),
+			writer: None,
+			buf: Vec::new(),
+			offset: 0,
+			page: None,
+			closed: false,
+			pending: None,
+			failed: None,
+			marker: PhantomData,
+		}
+	}
+}
+impl<D, Row, F> ReducerSend<Row> for PartFileReducer<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+{
+	type Done = PartFile<F::Error>;
+}
+
+#[pin_project]
+pub struct PartFileReducerAsync<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+{
+	directory: D,
+	format: F,
+	/// Write nothing, as the commit is going to fail anyway.
+	abort: bool,
+	name: String,
+	writer: Option<F::Writer>,
+	buf: Vec<u8>,
+	offset: u64,
+	page: Option<D::Page>,
+	closed: bool,
+	#[pin]
+	pending: Option<LocalBoxFuture<'static, Result<D::Page, D::Error>>>,
+	#[pin]
+	failed: Option<LocalBoxFuture<'static, PartFile<F::Error>>>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row, F> PartFileReducerAsync<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+{
+	/// Write out the buffered bytes, creating the part file if this is the first write.
+	fn write(self: Pin<&mut Self>) {
+		let mut self_ = self.project();
+		let buf = mem::take(self_.buf);
+		let offset = *self_.offset;
+		*self_.offset += buf.len() as u64;
+		let directory = self_.directory.clone();
+		let page = self_.page.take();
+		let path = file_path(self_.name.clone());
+		self_.pending.set(Some(Box::pin(async move {
+			let page = match page {
+				Some(page) => page,
+				None => directory.create(&path).await?,
+			};
+			page.write(offset, buf.into_boxed_slice()).await?;
+			Ok(page)
+		})));
+	}
+	/// Give up on the part file, removing whatever has been written of it.
+	fn fail(self: Pin<&mut Self>, err: F::Error) {
+		let mut self_ = self.project();
+		let directory = self_.directory.clone();
+		let path = file_path(self_.name.clone());
+		self_.failed.set(Some(Box::pin(async move {
+			let _ = directory.remove(&path).await;
+			Some(Err(err))
+		})));
+	}
+}
+impl<D, Row, F> Sink<Row> for PartFileReducerAsync<D, Row, F>
+where
+	D: OutputDirectory,
+	F: PartFileFormat<D, Row>,
+{
+	type Done = PartFile<F::Error>;
+
+	fn poll_forward(
+		mut self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Row>>,
+	) -> Poll<Self::Done> {
+		if self.abort {
+			return Poll::Ready(None);
+		}
+		loop {
+			let mut self_ = self.as_mut().project();
+			if let Some(failed) = self_.failed.as_pin_mut() {
+				return failed.poll(cx);
+			}
+			if let Some(pending) = self_.pending.as_mut().as_pin_mut() {
+				let page = ready!(pending.poll(cx));
+				self_.pending.set(None);
+				match page {
+					Ok(page) => *self_.page = Some(page),
+					Err(err) => {
+						self.as_mut().fail(F::Error::from(err));
+						continue;
+					}
+				}
+			}
+			if *self_.closed {
+				return Poll::Ready(Some(Ok(self_.name.clone())));
+			}
+			let res = match ready!(stream.as_mut().poll_next(cx)) {
+				Some(row) => {
+					if self_.writer.is_none() {
+						match self_.format.writer() {
+							Ok(writer) => *self_.writer = Some(writer),
+							Err(err) => {
+								self.as_mut().fail(err);
+								continue;
+							}
+						}
+					}
+					let writer = self_.writer.as_mut().unwrap();
+					self_.format.write(writer, row, self_.buf)
+				}
+				None => match self_.writer.take() {
+					Some(writer) => {
+						*self_.closed = true;
+						self_.format.close(writer, self_.buf)
+					}
+					None => return Poll::Ready(None),
+				},
+			};
+			match res {
+				Ok(()) if *self_.closed || self_.buf.len() >= BUF_SIZE => self.as_mut().write(),
+				Ok(()) => (),
+				Err(err) => self.as_mut().fail(err),
+			}
+		}
+	}
+}
+
+/// Commits the part files written by a [`Destination`](crate::Destination)'s workers.
+///
+/// Each worker writes its output to a file with a temporary name (see
+/// [`part_file_temp_name`]) and yields that name, or `None` if it had nothing to write.
+/// Once all workers have finished, if they all succeeded this renames the files to
+/// `part-<index>-<job>.<extension>` and writes a `_SUCCESS` marker, dealing with any
+/// files already in the directory according to the [`WriteMode`]; otherwise it
+/// removes them and returns the first error.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "D: Clone"))]
+#[serde(
+	bound(serialize = "D: Serialize"),
+	bound(deserialize = "D: Deserialize<'de>")
+)]
+pub struct CommitReducer<D, Item> {
+	directory: D,
+	extension: String,
+	mode: WriteMode,
+	existing: Option<String>,
+	job: u64,
+	marker: PhantomData<fn() -> Item>,
+}
+impl<D, Item> CommitReducer<D, Item> {
+	/// `existing` is the file that [`existing_file`] found in the directory when the
+	/// destination was created, if any.
+	pub fn new(directory: D, extension: &str, mode: WriteMode, existing: Option<PathBuf>) -> Self {
+		Self {
+			directory,
+			extension: extension.to_owned(),
+			mode,
+			existing: existing.map(|existing| existing.display().to_string()),
+			job: thread_rng().gen(),
+			marker: PhantomData,
+		}
+	}
+	/// Whether the commit will fail whatever the workers write, as the directory
+	/// already contained files and the mode is [`WriteMode::FailIfExists`]. Workers
+	/// check this so that they don't write anything.
+	pub fn will_fail(&self) -> bool {
+		self.mode == WriteMode::FailIfExists && self.existing.is_some()
+	}
+}
+
+impl<D, Item, E> Reducer<Item> for CommitReducer<D, Item>
+where
+	D: OutputDirectory,
+	Item: IntoIterator<Item = Result<String, E>>,
+	E: From<D::Error> + 'static,
+{
+	type Done = Result<(), E>;
+	type Async = CommitReducerAsync<D, Item, E>;
+
+	fn into_async(self) -> Self::Async {
+		let error = match self.existing {
+			Some(existing) if self.mode == WriteMode::FailIfExists => {
+				Some(E::from(already_exists::<D>(&existing)))
+			}
+			_ => None,
+		};
+		CommitReducerAsync {
+			directory: self.directory,
+			extension: self.extension,
+			mode: self.mode,
+			job: self.job,
+			files: Vec::new(),
+			error,
+			pending: None,
+			marker: PhantomData,
+		}
+	}
+	fn ordered(&self) -> bool {
+		true
+	}
+}
+impl<D, Item, E> ReducerProcessSend<Item> for CommitReducer<D, Item>
+where
+	D: OutputDirectory,
+	Item: IntoIterator<Item = Result<String, E>>,
+	E: From<D::Error> + Send + 'static,
+	Result<(), E>: Serialize + for<'de> Deserialize<'de>,
+{
+	type Done = Result<(), E>;
+}
+impl<D, Item, E> ReducerSend<Item> for CommitReducer<D, Item>
+where
+	D: OutputDirectory,
+	Item: IntoIterator<Item = Result<String, E>>,
+	E: From<D::Error> + Send + 'static,
+{
+	type Done = Result<(), E>;
+}
+
+#[pin_project]
+pub struct CommitReducerAsync<D, Item, E> {
+	directory: D,
+	extension: String,
+	mode: WriteMode,
+	job: u64,
+	files: Vec<String>,
+	error: Option<E>,
+	#[pin]
+	pending: Option<LocalBoxFuture<'static, Result<(), E>>>,
+	marker: PhantomData<fn() -> Item>,
+}
+impl<D, Item, E> Sink<Item> for CommitReducerAsync<D, Item, E>
+where
+	D: OutputDirectory,
+	Item: IntoIterator<Item = Result<String, E>>,
+	E: From<D::Error> + 'static,
+{
+	type Done = Result<(), E>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let mut self_ = self.project();
+		if self_.pending.is_none() {
+			while let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+				for file in item {
+					match file {
+						Ok(file) => self_.files.push(file),
+						Err(err) => {
+							if self_.error.is_none() {
+								*self_.error = Some(err);
+							}
+						}
+					}
+				}
+			}
+			let directory = self_.directory.clone();
+			let extension = self_.extension.clone();
+			let mode = *self_.mode;
+			let job = *self_.job;
+			let files = mem::take(self_.files);
+			let error = self_.error.take();
+			*self_.pending = Some(Box::pin(async move {
+				let existing = match error {
+					None => existing_files(&directory, mode).await,
+					Some(err) => Err(err),
+				};
+				let existing = match existing {
+					Ok(existing) => existing,
+					Err(err) => {
+						for file in files {
+							let _ = directory.remove(&file_path(file)).await;
+						}
+						return Err(err);
+					}
+				};
+				// Remove the marker first so that the directory isn't taken to be
+				// complete if the commit fails part way through
+				let success = file_path(String::from("_SUCCESS"));
+				if existing.contains(&success) {
+					directory.remove(&success).await?;
+				}
+				if mode == WriteMode::Overwrite {
+					for path in existing.iter().filter(|&path| *path != success) {
+						directory.remove(path).await?;
+					}
+				}
+				for (i, file) in files.into_iter().enumerate() {
+					let to = file_path(format!("part-{:05}-{:016x}.{}", i, job, extension));
+					directory.rename(&file_path(file), &to).await?;
+				}
+				let _ = directory.create(&success).await?;
+				Ok(())
+			}));
+		}
+		self_.pending.as_mut().as_pin_mut().unwrap().poll(cx)
+	}
+}
+
+/// Returns a file already in `directory`, ignoring hidden ones like in-progress part
+/// files, or `None` if there aren't any.
+///
+/// [`Destination`](crate::Destination)s look this up when they're created, so that
+/// with [`WriteMode::FailIfExists`] a job fails before it starts rather than once its
+/// workers have written everything. The directory is checked again on commit.
+pub async fn existing_file<D>(directory: &D) -> Result<Option<PathBuf>, D::Error>
+where
+	D: OutputDirectory,
+{
+	Ok(visible_files(directory).await?.into_iter().next())
+}
+
+/// The files already in the directory, excluding hidden ones like in-progress part
+/// files. Errors if there are any and `mode` is [`WriteMode::FailIfExists`].
+async fn existing_files<D, E>(directory: &D, mode: WriteMode) -> Result<Vec<PathBuf>, E>
+where
+	D: OutputDirectory,
+	E: From<D::Error>,
+{
+	let existing = visible_files(directory).await?;
+	if mode == WriteMode::FailIfExists && !existing.is_empty() {
+		return Err(E::from(already_exists::<D>(
+			&existing[0].display().to_string(),
+		)));
+	}
+	Ok(existing)
+}
+
+async fn visible_files<D>(directory: &D) -> Result<Vec<PathBuf>, D::Error>
+where
+	D: OutputDirectory,
+{
+	Ok(directory
+		.files()
+		.await?
+		.into_iter()
+		.filter(|path| !path.file_name().unwrap().to_string_lossy().starts_with('.'))
+		.collect())
+}
+
+fn already_exists<D>(existing: &str) -> D::Error
+where
+	D: OutputDirectory,
+{
+	D::Error::from(io::Error::new(
+		io::ErrorKind::AlreadyExists,
+		format!("output directory already contains {}", existing),
+	))
+}
+
+fn file_path(file_name: String) -> PathBuf {
+	let mut path = PathBuf::new();
+	path.set_file_name(Some(file_name));
+	path
+}
@@ -20,7 +20,7 @@ use {
 	std::io::{Seek, SeekFrom}, tokio::task::spawn_blocking
 };
 
-use super::{Directory, File, Page, Partition};
+use super::{Directory, File, OutputDirectory, Page, Partition};
 #[cfg(target_arch = "wasm32")]
 use crate::util::{f64_to_u64, u64_to_f64};
 use crate::util::{IoError, ResultExpand};
@@ -89,6 +89,63 @@ impl Partition for PathBuf {
 	}
 }
 #[async_trait(?Send)]
+impl OutputDirectory for PathBuf {
+	type Page = LocalFile;
+	type Error = IoError;
+
+	async fn files(&self) -> Result<Vec<super::PathBuf>, Self::Error> {
+		if !self.exists() {
+			return Ok(Vec::new());
+		}
+		WalkDir::new(self)
+			.follow_links(true)
+			.sort_by(|a, b| a.file_name().cmp(b.file_name()))
+			.into_iter()
+			.filter_map(|e| match e {
+				Ok(ref e) if e.file_type().is_dir() => None,
+				Ok(e) => {
+					let path = e.path().strip_prefix(self).unwrap();
+					let mut path_buf = super::PathBuf::new();
+					for component in path.parent().unwrap() {
+						path_buf.push(component);
+					}
+					path_buf.set_file_name(path.file_name());
+					Some(Ok(path_buf))
+				}
+				Err(e) => Some(Err(if e.io_error().is_some() {
+					e.into_io_error().unwrap()
+				} else {
+					io::Error::new(io::ErrorKind::Other, e)
+				}
+				.into())),
+			})
+			.collect()
+	}
+	async fn create(&self, path: &super::PathBuf) -> Result<Self::Page, Self::Error> {
+		let path = local_path(self, path);
+		fs::create_dir_all(path.parent().unwrap())?;
+		Ok(LocalFile::create(path)?)
+	}
+	async fn rename(&self, from: &super::PathBuf, to: &super::PathBuf) -> Result<(), Self::Error> {
+		let to = local_path(self, to);
+		fs::create_dir_all(to.parent().unwrap())?;
+		Ok(fs::rename(local_path(self, from), to)?)
+	}
+	async fn remove(&self, path: &super::PathBuf) -> Result<(), Self::Error> {
+		Ok(fs::remove_file(local_path(self, path))?)
+	}
+}
+fn local_path(base: &Path, path: &super::PathBuf) -> PathBuf {
+	let mut ret = base.to_owned();
+	for component in path.iter() {
+		ret.push(component.to_string_lossy());
+	}
+	if let Some(file_name) = path.file_name() {
+		ret.push(file_name.to_string_lossy());
+	}
+	ret
+}
+#[async_trait(?Send)]
 impl Directory for &Path {
 	async fn partitions_filter<F>(
 		self, mut f: F,
@@ -251,6 +308,30 @@ impl LocalFile {
 		}
 	}
 
+	/// [Creates](https://doc.rust-lang.org/std/fs/struct.File.html#method.create)
+	/// a file for random access, truncating it if it already exists.
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		#[cfg(not(target_arch = "wasm32"))]
+		{
+			Self::from_file(
+				fs::OpenOptions::new()
+					.read(true)
+					.write(true)
+					.create(true)
+					.truncate(true)
+					.open(path)?,
+			)
+		}
+		#[cfg(target_arch = "wasm32")]
+		{
+			let _ = path;
+			Err(io::Error::new(
+				io::ErrorKind::Other,
+				"creating files is not supported on wasm",
+			))
+		}
+	}
+
 	fn clone(&self) -> Self {
 		Self {
 			inner: self.inner.clone(),
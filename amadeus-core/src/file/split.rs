@@ -0,0 +1,300 @@
+//! Splitting large files of newline-delimited records into byte ranges that can be
+//! processed in parallel.
+
+use async_trait::async_trait;
+use futures::{future, future::LocalBoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::{
+	cell::RefCell, convert::TryFrom, error, fmt::{self, Display}, io, rc::Rc
+};
+
+use super::{File, Page, Partition};
+use crate::util::IoError;
+
+/// The number of bytes read at a time while looking for the end of a line.
+const SCAN_SIZE: usize = 64 * 1024;
+
+/// Splits each partition of a [`File`] into byte ranges of `size` bytes, so that a large
+/// file of newline-delimited records can be read by many workers at once.
+///
+/// Like Hadoop's input splits, ranges are widened to whole lines: a range starts with
+/// the first line that begins within it, and ends with the last line that begins within
+/// it. Line-oriented formats like JSON Lines and CSV can then be read from each range as
+/// though it were a file of its own. CSV with newlines inside quoted fields isn't
+/// supported, as a range can begin part way through such a field.
+///
+/// The bytes of each file are split as they're stored, so compressed files can't be
+/// split: a range of a gzipped file, like the logs read by `Cloudfront`, can't be
+/// decompressed on its own. Sources of compressed files don't read through `Splits`, and
+/// it shouldn't be given files that a format decompresses itself.
+///
+/// ```ignore
+/// let rows = Json::<_, Value>::new(Splits::new(vec![PathBuf::from("big.jsonl")], 64 << 20)).await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Splits<F> {
+	file: F,
+	size: u64,
+	header: bool,
+}
+impl<F> Splits<F> {
+	pub fn new(file: F, size: u64) -> Self {
+		assert_ne!(size, 0, "split size must be greater than zero");
+		Self {
+			file,
+			size,
+			header: false,
+		}
+	}
+	/// Whether the first line of each file is a header, like that of a CSV file with
+	/// headers. If so it's prepended to every range of the file. Only a single line is
+	/// treated as the header, so a header spanning several lines, for example with a
+	/// newline in a quoted field, isn't supported. Defaults to `false`.
+	pub fn header(mut self, header: bool) -> Self {
+		self.header = header;
+		self
+	}
+}
+#[async_trait(?Send)]
+impl<F> File for Splits<F>
+where
+	F: File,
+	<F::Partition as Partition>::Page: 'static,
+{
+	type Partition = Split<F::Partition>;
+	type Error = SplitsError<
+		F::Error,
+		<F::Partition as Partition>::Error,
+		<<F::Partition as Partition>::Page as Page>::Error,
+	>;
+
+	async fn partitions(self) -> Result<Vec<Self::Partition>, Self::Error> {
+		let (size, header) = (self.size, self.header);
+		let mut splits = Vec::new();
+		for partition in self.file.partitions().await.map_err(SplitsError::File)? {
+			let mut len = 0;
+			for page in partition
+				.clone()
+				.pages()
+				.await
+				.map_err(SplitsError::Partition)?
+			{
+				len += page.len().await.map_err(SplitsError::Page)?;
+			}
+			let mut start = 0;
+			loop {
+				let end = len.min(start + size);
+				splits.push(Split {
+					partition: partition.clone(),
+					start,
+					end,
+					header,
+				});
+				if end == len {
+					break;
+				}
+				start = end;
+			}
+		}
+		Ok(splits)
+	}
+}
+
+/// A byte range of a [`Partition`], as produced by [`Splits`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Split<P> {
+	partition: P,
+	start: u64,
+	end: u64,
+	header: bool,
+}
+#[async_trait(?Send)]
+impl<P> Partition for Split<P>
+where
+	P: Partition,
+	P::Page: 'static,
+{
+	type Page = SplitPage<P::Page>;
+	type Error = P::Error;
+
+	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error> {
+		let pages = self.partition.pages().await?;
+		Ok(vec![SplitPage {
+			inner: Rc::new(SplitPageInner {
+				pages,
+				start: self.start,
+				end: self.end,
+				header: self.header,
+				bounds: RefCell::new(None),
+			}),
+		}])
+	}
+}
+
+/// The pages of a partition, concatenated and narrowed to the whole lines of a
+/// [`Split`]. It's read-only: writes fail with [`io::ErrorKind::PermissionDenied`].
+pub struct SplitPage<P> {
+	inner: Rc<SplitPageInner<P>>,
+}
+struct SplitPageInner<P> {
+	pages: Vec<P>,
+	start: u64,
+	end: u64,
+	header: bool,
+	bounds: RefCell<Option<Rc<Bounds>>>,
+}
+/// Where the lines of a split lie, found on first use as it takes reads to find them.
+struct Bounds {
+	/// The offset of each page within the partition, followed by the partition's length.
+	offsets: Vec<u64>,
+	/// The length of the header line, if one is prepended.
+	header: u64,
+	start: u64,
+	end: u64,
+}
+impl<P> SplitPageInner<P>
+where
+	P: Page,
+{
+	async fn bounds(&self) -> Result<Rc<Bounds>, P::Error> {
+		if let Some(bounds) = &*self.bounds.borrow() {
+			return Ok(bounds.clone());
+		}
+		let mut offsets = vec![0];
+		for page in &self.pages {
+			let len = page.len().await?;
+			offsets.push(offsets.last().unwrap() + len);
+		}
+		let mut bounds = Bounds {
+			offsets,
+			header: 0,
+			start: 0,
+			end: 0,
+		};
+		let len = *bounds.offsets.last().unwrap();
+		let start = if self.start == 0 {
+			0
+		} else {
+			self.line_end(&bounds, self.start - 1).await?
+		};
+		let end = if self.end >= len {
+			len
+		} else {
+			self.line_end(&bounds, self.end - 1).await?
+		};
+		if start < end {
+			if self.header && start > 0 {
+				bounds.header = self.line_end(&bounds, 0).await?;
+			}
+			bounds.start = start;
+			bounds.end = end;
+		}
+		let bounds = Rc::new(bounds);
+		*self.bounds.borrow_mut() = Some(bounds.clone());
+		Ok(bounds)
+	}
+	/// The offset just after the first newline at or after `offset`, or the end of the
+	/// partition if there isn't one.
+	async fn line_end(&self, bounds: &Bounds, mut offset: u64) -> Result<u64, P::Error> {
+		loop {
+			let buf = self.read_at(bounds, offset, SCAN_SIZE).await?;
+			if buf.is_empty() {
+				return Ok(offset);
+			}
+			if let Some(i) = buf.iter().position(|&byte| byte == b'\n') {
+				return Ok(offset + u64::try_from(i).unwrap() + 1);
+			}
+			offset += u64::try_from(buf.len()).unwrap();
+		}
+	}
+	/// Reads from the page that `offset` falls in. Reads don't cross pages, so may be
+	/// short.
+	async fn read_at(
+		&self, bounds: &Bounds, offset: u64, len: usize,
+	) -> Result<Box<[u8]>, P::Error> {
+		let page = bounds.offsets[1..].iter().position(|&end| offset < end);
+		let page = match page {
+			Some(page) => page,
+			None => return Ok(Box::new([])),
+		};
+		let (start, end) = (bounds.offsets[page], bounds.offsets[page + 1]);
+		let len = usize::try_from(end - offset).map_or(len, |remaining| remaining.min(len));
+		self.pages[page].read(offset - start, len).await
+	}
+}
+impl<P> Page for SplitPage<P>
+where
+	P: Page + 'static,
+{
+	type Error = IoError;
+
+	fn len(&self) -> LocalBoxFuture<'static, Result<u64, Self::Error>> {
+		let inner = self.inner.clone();
+		async move {
+			let bounds = inner.bounds().await.map_err(io_error)?;
+			Ok(bounds.header + (bounds.end - bounds.start))
+		}
+		.boxed_local()
+	}
+	fn read(
+		&self, offset: u64, len: usize,
+	) -> LocalBoxFuture<'static, Result<Box<[u8]>, Self::Error>> {
+		let inner = self.inner.clone();
+		async move {
+			let bounds = inner.bounds().await.map_err(io_error)?;
+			let (offset, end) = if offset < bounds.header {
+				(offset, bounds.header)
+			} else {
+				(bounds.start + (offset - bounds.header), bounds.end)
+			};
+			if offset >= end {
+				return Ok(Box::new([]) as Box<[u8]>);
+			}
+			let len = usize::try_from(end - offset).map_or(len, |remaining| remaining.min(len));
+			inner.read_at(&bounds, offset, len).await.map_err(io_error)
+		}
+		.boxed_local()
+	}
+	fn write(
+		&self, _offset: u64, _buf: Box<[u8]>,
+	) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+		future::ready(Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			"split pages are read-only",
+		)
+		.into()))
+		.boxed_local()
+	}
+}
+
+fn io_error<E: Into<io::Error>>(err: E) -> IoError {
+	err.into().into()
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SplitsError<A, B, C> {
+	File(A),
+	Partition(B),
+	Page(C),
+}
+impl<A, B, C> error::Error for SplitsError<A, B, C>
+where
+	A: error::Error,
+	B: error::Error,
+	C: error::Error,
+{
+}
+impl<A, B, C> Display for SplitsError<A, B, C>
+where
+	A: Display,
+	B: Display,
+	C: Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::File(err) => Display::fmt(err, f),
+			Self::Partition(err) => Display::fmt(err, f),
+			Self::Page(err) => Display::fmt(err, f),
+		}
+	}
+}
@@ -100,6 +100,7 @@ pub mod par_stream;
 pub mod pipe;
 pub mod pool;
 mod source;
+mod spill;
 pub mod util;
 
 pub use source::*;
@@ -3,7 +3,7 @@
 use either::Either;
 use futures::Stream;
 use serde_closure::traits;
-use std::{cmp::Ordering, hash::Hash, iter, ops};
+use std::{cmp::Ordering, hash::Hash, iter, ops, time::Duration};
 
 use super::{par_sink::*, par_stream::*};
 use crate::{pipe::Pipe, pool::ProcessSend};
@@ -134,6 +134,61 @@ macro_rules! pipe {
 				$assert_sink(Fork::new(self, sink, sink_ref))
 			}
 
+			/// Drops the errors of a stream of `Result`s, keeping the `Ok` rows.
+			#[inline]
+			fn skip_errors<T, E>(self) -> FilterMapSync<Self, SkipErrors<T, E>>
+			where
+				T: 'static,
+				E: 'static,
+				Self: $pipe<Input, Output = Result<T, E>> + Sized,
+			{
+				$assert_pipe(FilterMapSync::new(self, SkipErrors::new()))
+			}
+
+			/// Sends the `Ok` rows of a stream of `Result`s to `sink` and the errors to
+			/// `err_sink`, for example to write them to a dead-letter destination.
+			#[inline]
+			fn split_errors<A, B, T, E>(self, sink: A, err_sink: B) -> SplitErrors<Self, A, B>
+			where
+				A: $sink<T>,
+				B: $sink<E>,
+				T: 'static,
+				E: Clone + 'static,
+				Self: $pipe<Input, Output = Result<T, E>> + Sized,
+			{
+				$assert_sink(SplitErrors::new(self, sink, err_sink))
+			}
+
+			/// Sends the `Ok` rows of a stream of `Result`s to `sink`, alongside counting
+			/// the errors and keeping the first `limit` of them.
+			#[inline]
+			fn collect_errors<S, T, E>(
+				self, limit: usize, sink: S,
+			) -> SplitErrors<Self, S, CollectErrors<Identity>>
+			where
+				S: $sink<T>,
+				T: 'static,
+				E: Clone + $send + 'static,
+				Self: $pipe<Input, Output = Result<T, E>> + Sized,
+			{
+				$assert_sink(SplitErrors::new(self, sink, CollectErrors::new(Identity, limit)))
+			}
+
+			/// Sends the `Ok` rows of a stream of `Result`s to `sink`, failing with the
+			/// count and first `n + 1` errors if there are more than `n` errors. It fails
+			/// as soon as the tasks finished so far have more than `n` errors, so the
+			/// count is of those.
+			#[inline]
+			fn fail_after<S, T, E>(self, n: usize, sink: S) -> FailAfter<Self, S>
+			where
+				S: $sink<T>,
+				T: 'static,
+				E: Clone + $send + 'static,
+				Self: $pipe<Input, Output = Result<T, E>> + Sized,
+			{
+				$assert_sink(FailAfter::new(self, sink, n))
+			}
+
 			#[inline]
 			fn for_each<F>(self, f: F) -> ForEach<Self, F>
 			where
@@ -168,6 +223,73 @@ macro_rules! pipe {
 				$assert_sink(GroupBy::new(self, sink))
 			}
 
+			/// Aggregates rows with `sink` in consecutive, non-overlapping windows of `size`,
+			/// by the time `f` gives them. Returns each window's start with its aggregate.
+			///
+			/// # Panics
+			///
+			/// If `size` is zero, or longer than `i64::MAX` nanoseconds.
+			#[inline]
+			fn tumbling_window<F, T, S>(self, f: F, size: Duration, sink: S) -> Window<Self, F, S>
+			where
+				F: $fns::Fn(&Self::Output) -> T + Clone + $send + 'static,
+				T: Timestamp + Ord,
+				S: $sink<Self::Output>,
+				<S::Pipe as $pipe<Self::Output>>::Task: Clone + $send + 'static,
+				S::ReduceA: 'static,
+				S::ReduceC: Clone,
+				Self::Output: Clone,
+				Self: Sized,
+			{
+				$assert_sink(Window::tumbling(self, f, size, sink))
+			}
+
+			/// Aggregates rows with `sink` in windows of `size` starting every `slide`, by
+			/// the time `f` gives them. Rows are aggregated in each window they fall in.
+			/// Returns each window's start with its aggregate.
+			///
+			/// # Panics
+			///
+			/// If `size` or `slide` is zero, or longer than `i64::MAX` nanoseconds.
+			#[inline]
+			fn sliding_window<F, T, S>(
+				self, f: F, size: Duration, slide: Duration, sink: S,
+			) -> Window<Self, F, S>
+			where
+				F: $fns::Fn(&Self::Output) -> T + Clone + $send + 'static,
+				T: Timestamp + Ord,
+				S: $sink<Self::Output>,
+				<S::Pipe as $pipe<Self::Output>>::Task: Clone + $send + 'static,
+				S::ReduceA: 'static,
+				S::ReduceC: Clone,
+				Self::Output: Clone,
+				Self: Sized,
+			{
+				$assert_sink(Window::sliding(self, f, size, slide, sink))
+			}
+
+			/// Aggregates rows with `sink` in sessions, by the time `f` gives them. A
+			/// session ends when there's a `gap` or more before the next row. Returns each
+			/// session's first time with its aggregate.
+			///
+			/// # Panics
+			///
+			/// If `gap` is zero, or longer than `i64::MAX` nanoseconds.
+			#[inline]
+			fn session_window<F, T, S>(self, f: F, gap: Duration, sink: S) -> Window<Self, F, S>
+			where
+				F: $fns::Fn(&Self::Output) -> T + Clone + $send + 'static,
+				T: Timestamp + Ord,
+				S: $sink<Self::Output>,
+				<S::Pipe as $pipe<Self::Output>>::Task: Clone + $send + 'static,
+				S::ReduceA: 'static,
+				S::ReduceC: Clone,
+				Self::Output: Clone,
+				Self: Sized,
+			{
+				$assert_sink(Window::session(self, f, gap, sink))
+			}
+
 			#[inline]
 			fn histogram(self) -> Histogram<Self>
 			where
@@ -195,6 +317,17 @@ macro_rules! pipe {
 				$assert_sink(Count::new(self))
 			}
 
+			/// Estimates the number of distinct rows with a
+			/// [`HyperLogLog`](amadeus_streaming::HyperLogLog) of the given `error_rate`.
+			#[inline]
+			fn count_distinct_approx(self, error_rate: f64) -> CountDistinctApprox<Self>
+			where
+				Self::Output: Hash + 'static,
+				Self: Sized,
+			{
+				$assert_sink(CountDistinctApprox::new(self, error_rate))
+			}
+
 			#[inline]
 			fn sum<B>(self) -> Sum<Self, B>
 			where
@@ -220,6 +353,17 @@ macro_rules! pipe {
 				$assert_sink(StdDev::new(self))
 			}
 
+			/// Estimates the given quantiles, each between `0.0` and `1.0`, with a
+			/// [`TDigest`](amadeus_streaming::TDigest). Returns `None`s if there are no rows.
+			#[inline]
+			fn quantiles(self, quantiles: &[f64]) -> Quantiles<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				assert!(quantiles.iter().all(|quantile| (0.0..=1.0).contains(quantile)));
+				$assert_sink(Quantiles::new(self, quantiles.to_vec()))
+			}
+
 			#[inline]
 			fn combine<F>(self, f: F) -> Combine<Self, F>
 			where
@@ -344,6 +488,17 @@ macro_rules! pipe {
 				$assert_sink(Any::new(self, f))
 			}
 
+			/// Collects the first `n` rows, in the order [`collect`](Self::collect) would
+			/// return them. Work stops once they have arrived.
+			#[inline]
+			fn take(self, n: usize) -> Take<Self>
+			where
+				Self::Output: $send + 'static,
+				Self: Sized,
+			{
+				$assert_sink(Take::new(self, n))
+			}
+
 			#[inline]
 			fn collect<B>(self) -> Collect<Self, B>
 			where
@@ -4,25 +4,33 @@ mod collect;
 mod combine;
 mod combiner;
 mod count;
+mod count_distinct;
+mod errors;
 mod fold;
 mod folder;
 mod for_each;
 mod fork;
 mod group_by;
+mod group_by_spill;
 mod histogram;
 mod max;
 mod mean;
 mod pipe;
+mod quantiles;
 mod sample;
+mod shuffle;
+mod sort;
 mod stddev;
 mod sum;
+mod take;
 mod tuple;
+mod window;
 
 use super::par_pipe::*;
 use crate::{pipe::Sink, pool::ProcessSend};
 
 pub use self::{
-	all::*, any::*, collect::*, combine::*, combiner::*, count::*, fold::*, folder::*, for_each::*, fork::*, group_by::*, histogram::*, max::*, mean::*, pipe::*, sample::*, stddev::*, sum::*, tuple::*
+	all::*, any::*, collect::*, combine::*, combiner::*, count::*, count_distinct::*, errors::*, fold::*, folder::*, for_each::*, fork::*, group_by::*, group_by_spill::*, histogram::*, max::*, mean::*, pipe::*, quantiles::*, sample::*, shuffle::*, sort::*, stddev::*, sum::*, take::*, tuple::*, window::*
 };
 
 #[must_use]
@@ -31,6 +39,11 @@ pub trait Reducer<Item> {
 	type Async: Sink<Item, Done = Self::Done>;
 
 	fn into_async(self) -> Self::Async;
+	/// Whether the results of tasks need to be reduced in the order the tasks were handed
+	/// out, rather than as they finish. Defaults to `false`.
+	fn ordered(&self) -> bool {
+		false
+	}
 }
 pub trait ReducerSend<Item>: Reducer<Item, Done = <Self as ReducerSend<Item>>::Done> {
 	type Done: Send + 'static;
@@ -78,6 +78,9 @@ impl<Item, T: Default + Extend<Item>> Reducer<Item> for PushReducer<Item, T> {
 	fn into_async(self) -> Self::Async {
 		PushReducerAsync(Some(Default::default()), PhantomData)
 	}
+	fn ordered(&self) -> bool {
+		true
+	}
 }
 impl<Item, T: Default + Extend<Item>> ReducerProcessSend<Item> for PushReducer<Item, T>
 where
@@ -122,6 +125,9 @@ impl<Item: IntoIterator<Item = B>, T: Default + Extend<B>, B> Reducer<Item>
 	fn into_async(self) -> Self::Async {
 		ExtendReducerAsync(Some(T::default()), PhantomData)
 	}
+	fn ordered(&self) -> bool {
+		true
+	}
 }
 impl<Item: IntoIterator<Item = B>, T: Default + Extend<B>, B> ReducerProcessSend<Item>
 	for ExtendReducer<Item, T>
@@ -173,6 +179,9 @@ where
 	fn into_async(self) -> Self::Async {
 		IntoReducerAsync(self.0.into_async(), PhantomData)
 	}
+	fn ordered(&self) -> bool {
+		self.0.ordered()
+	}
 }
 
 #[pin_project]
@@ -203,6 +212,9 @@ impl<R: Reducer<Item>, Item> Reducer<Option<Item>> for OptionReducer<R> {
 	fn into_async(self) -> Self::Async {
 		OptionReducerAsync(Some(self.0.into_async()))
 	}
+	fn ordered(&self) -> bool {
+		self.0.ordered()
+	}
 }
 impl<R: Reducer<Item>, Item> ReducerProcessSend<Option<Item>> for OptionReducer<R>
 where
@@ -251,6 +263,9 @@ impl<R: Reducer<Item>, E, Item> Reducer<Result<Item, E>> for ResultReducer<R, E>
 	fn into_async(self) -> Self::Async {
 		ResultReducerAsync::Ok(self.0.into_async())
 	}
+	fn ordered(&self) -> bool {
+		self.0.ordered()
+	}
 }
 impl<R: Reducer<Item>, E, Item> ReducerProcessSend<Result<Item, E>> for ResultReducer<R, E>
 where
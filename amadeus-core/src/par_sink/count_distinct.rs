@@ -0,0 +1,86 @@
+use amadeus_streaming::HyperLogLog;
+use derive_new::new;
+use educe::Educe;
+use serde::{Deserialize, Serialize};
+use std::{hash::Hash, marker::PhantomData};
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+
+#[derive(new)]
+#[must_use]
+pub struct CountDistinctApprox<P> {
+	pipe: P,
+	error_rate: f64,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for CountDistinctApprox<P>
+	where
+		P::Output: Hash + 'static,
+	{
+		folder_par_sink!(
+			CountDistinctApproxFolder<StepA>,
+			CountDistinctApproxFolder<StepB>,
+			self,
+			CountDistinctApproxFolder::new(self.error_rate),
+			CountDistinctApproxFolder::new(self.error_rate)
+		);
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct CountDistinctApproxFolder<Step> {
+	error_rate: f64,
+	marker: PhantomData<fn() -> Step>,
+}
+
+pub struct StepA;
+pub struct StepB;
+
+impl<Item> FolderSync<Item> for CountDistinctApproxFolder<StepA>
+where
+	Item: Hash,
+{
+	type State = HyperLogLog<Item>;
+	type Done = HyperLogLog<Item>;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		HyperLogLog::new(self.error_rate)
+	}
+
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: Item) {
+		state.push(&item);
+	}
+
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+impl<Item> FolderSync<HyperLogLog<Item>> for CountDistinctApproxFolder<StepB>
+where
+	Item: Hash,
+{
+	type State = HyperLogLog<Item>;
+	type Done = f64;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		HyperLogLog::new(self.error_rate)
+	}
+
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: HyperLogLog<Item>) {
+		state.union(&item);
+	}
+
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.len()
+	}
+}
@@ -0,0 +1,345 @@
+use derive_new::new;
+use educe::Educe;
+use futures::{pin_mut, ready, stream, Stream, StreamExt};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use serde_closure::FnNamed;
+use std::{
+	fmt, marker::PhantomData, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	folder_par_sink, DistributedPipe, DistributedSink, FolderSync, FolderSyncReducer, Fork, ParallelPipe, ParallelSink, Pipe, Reducer
+};
+use crate::{
+	par_stream::{FilterMapSync, Identity}, pipe::Sink, pool::ProcessSend
+};
+
+/// An error for a row that couldn't be parsed, which may carry the raw record it was
+/// parsed from, so that it can be written somewhere to be inspected or reprocessed.
+pub trait RecordError: fmt::Display {
+	/// The raw record that couldn't be parsed, if known.
+	fn record(&self) -> Option<&str> {
+		None
+	}
+}
+
+FnNamed! {
+	pub type SkipErrors<T, E> = |self|row=> Result<T, E>| -> Option<T> where ; where {
+		row.ok()
+	}
+}
+FnNamed! {
+	pub type ErrorRef<T, E> = |self|row=> &Result<T, E>| -> Option<E> where ; where E: (Clone) {
+		row.as_ref().err().cloned()
+	}
+}
+
+type SplitFork<P, A, B, T, E> = Fork<
+	P,
+	Pipe<FilterMapSync<Identity, SkipErrors<T, E>>, A>,
+	Pipe<FilterMapSync<Identity, ErrorRef<T, E>>, B>,
+	&'static Result<T, E>,
+>;
+
+/// Sends the `Ok` rows of a stream of `Result`s to one sink and the errors to another.
+#[derive(new)]
+#[must_use]
+pub struct SplitErrors<P, A, B> {
+	pipe: P,
+	ok: A,
+	err: B,
+}
+
+impl<P, A, B> SplitErrors<P, A, B> {
+	fn fork<T, E>(self) -> SplitFork<P, A, B, T, E> {
+		Fork::new(
+			self.pipe,
+			Pipe::new(FilterMapSync::new(Identity, SkipErrors::new()), self.ok),
+			Pipe::new(FilterMapSync::new(Identity, ErrorRef::new()), self.err),
+		)
+	}
+}
+
+impl<P, A, B, Item, T, E> ParallelSink<Item> for SplitErrors<P, A, B>
+where
+	P: ParallelPipe<Item, Output = Result<T, E>>,
+	A: ParallelSink<T>,
+	B: ParallelSink<E>,
+	T: 'static,
+	E: Clone + 'static,
+{
+	type Done = (A::Done, B::Done);
+	type Pipe = <SplitFork<P, A, B, T, E> as ParallelSink<Item>>::Pipe;
+	type ReduceA = <SplitFork<P, A, B, T, E> as ParallelSink<Item>>::ReduceA;
+	type ReduceC = <SplitFork<P, A, B, T, E> as ParallelSink<Item>>::ReduceC;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		ParallelSink::reducers(self.fork())
+	}
+}
+impl<P, A, B, Item, T, E> DistributedSink<Item> for SplitErrors<P, A, B>
+where
+	P: DistributedPipe<Item, Output = Result<T, E>>,
+	A: DistributedSink<T>,
+	B: DistributedSink<E>,
+	T: 'static,
+	E: Clone + 'static,
+{
+	type Done = (A::Done, B::Done);
+	type Pipe = <SplitFork<P, A, B, T, E> as DistributedSink<Item>>::Pipe;
+	type ReduceA = <SplitFork<P, A, B, T, E> as DistributedSink<Item>>::ReduceA;
+	type ReduceB = <SplitFork<P, A, B, T, E> as DistributedSink<Item>>::ReduceB;
+	type ReduceC = <SplitFork<P, A, B, T, E> as DistributedSink<Item>>::ReduceC;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		DistributedSink::reducers(self.fork())
+	}
+}
+
+/// The errors of a stream: how many there were, and the first of them.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Errors<E> {
+	/// The number of errors.
+	pub count: usize,
+	/// The first errors, in the order [`Collect`](super::Collect) would return them.
+	pub errors: Vec<E>,
+}
+impl<E> Errors<E> {
+	fn new() -> Self {
+		Self {
+			count: 0,
+			errors: Vec::new(),
+		}
+	}
+}
+impl<E> fmt::Display for Errors<E>
+where
+	E: fmt::Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let plural = if self.count == 1 { "" } else { "s" };
+		write!(f, "{} error{}", self.count, plural)?;
+		if let Some(error) = self.errors.first() {
+			write!(f, ", the first: {}", error)?;
+		}
+		Ok(())
+	}
+}
+impl<E> std::error::Error for Errors<E> where E: fmt::Debug + fmt::Display {}
+
+/// Counts errors, keeping the first `limit` of them.
+#[derive(new)]
+#[must_use]
+pub struct CollectErrors<P> {
+	pipe: P,
+	limit: usize,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for CollectErrors<P>
+	where
+		P::Output: Send + 'static,
+	{
+		folder_par_sink!(
+			CollectErrorsFolder<StepA>,
+			CollectErrorsFolder<StepB>,
+			self,
+			CollectErrorsFolder::new(self.limit),
+			CollectErrorsFolder::new(self.limit)
+		);
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct CollectErrorsFolder<Step> {
+	limit: usize,
+	marker: PhantomData<fn() -> Step>,
+}
+
+pub struct StepA;
+pub struct StepB;
+
+impl<E> FolderSync<E> for CollectErrorsFolder<StepA> {
+	type State = Errors<E>;
+	type Done = Errors<E>;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		Errors::new()
+	}
+
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: E) {
+		state.count += 1;
+		if state.errors.len() < self.limit {
+			state.errors.push(item);
+		}
+	}
+
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+impl<E> FolderSync<Errors<E>> for CollectErrorsFolder<StepB> {
+	type State = Errors<E>;
+	type Done = Errors<E>;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		Errors::new()
+	}
+
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: Errors<E>) {
+		state.count += item.count;
+		let limit = self.limit.saturating_sub(state.errors.len());
+		state.errors.extend(item.errors.into_iter().take(limit));
+	}
+
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+
+	fn ordered(&self) -> bool {
+		true
+	}
+}
+
+/// Sends the `Ok` rows of a stream of `Result`s to a sink, failing with the [`Errors`]
+/// as soon as there are more than `n` errors.
+#[derive(new)]
+#[must_use]
+pub struct FailAfter<P, S> {
+	pipe: P,
+	sink: S,
+	n: usize,
+}
+
+impl<P, S> FailAfter<P, S> {
+	fn split(self) -> (SplitErrors<P, S, CollectErrors<Identity>>, usize) {
+		let limit = self.n.saturating_add(1);
+		(
+			SplitErrors::new(self.pipe, self.sink, CollectErrors::new(Identity, limit)),
+			self.n,
+		)
+	}
+}
+
+impl<P, S, Item, T, E> ParallelSink<Item> for FailAfter<P, S>
+where
+	P: ParallelPipe<Item, Output = Result<T, E>>,
+	S: ParallelSink<T>,
+	T: 'static,
+	E: Clone + Send + 'static,
+{
+	type Done = Result<S::Done, Errors<E>>;
+	type Pipe = <SplitErrors<P, S, CollectErrors<Identity>> as ParallelSink<Item>>::Pipe;
+	type ReduceA = <SplitErrors<P, S, CollectErrors<Identity>> as ParallelSink<Item>>::ReduceA;
+	type ReduceC = FailAfterReducer<
+		<SplitErrors<P, S, CollectErrors<Identity>> as ParallelSink<Item>>::ReduceC,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (split, n) = self.split();
+		let (pipe, reduce_a, reduce_c) = ParallelSink::reducers(split);
+		(pipe, reduce_a, FailAfterReducer::new(reduce_c, n))
+	}
+}
+impl<P, S, Item, T, E> DistributedSink<Item> for FailAfter<P, S>
+where
+	P: DistributedPipe<Item, Output = Result<T, E>>,
+	S: DistributedSink<T>,
+	T: 'static,
+	E: Clone + ProcessSend + 'static,
+{
+	type Done = Result<S::Done, Errors<E>>;
+	type Pipe = <SplitErrors<P, S, CollectErrors<Identity>> as DistributedSink<Item>>::Pipe;
+	type ReduceA = <SplitErrors<P, S, CollectErrors<Identity>> as DistributedSink<Item>>::ReduceA;
+	type ReduceB = <SplitErrors<P, S, CollectErrors<Identity>> as DistributedSink<Item>>::ReduceB;
+	type ReduceC = FailAfterReducer<
+		<SplitErrors<P, S, CollectErrors<Identity>> as DistributedSink<Item>>::ReduceC,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (split, n) = self.split();
+		let (pipe, reduce_a, reduce_b, reduce_c) = DistributedSink::reducers(split);
+		(pipe, reduce_a, reduce_b, FailAfterReducer::new(reduce_c, n))
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct FailAfterReducer<R> {
+	reducer: R,
+	n: usize,
+}
+
+impl<R, T, A, E> Reducer<(T, Errors<E>)> for FailAfterReducer<R>
+where
+	R: Reducer<(T, Errors<E>), Done = (A, Errors<E>)>,
+{
+	type Done = Result<A, Errors<E>>;
+	type Async = FailAfterReducerAsync<R::Async>;
+
+	fn into_async(self) -> Self::Async {
+		FailAfterReducerAsync {
+			reducer: self.reducer.into_async(),
+			n: self.n,
+			count: 0,
+		}
+	}
+	fn ordered(&self) -> bool {
+		self.reducer.ordered()
+	}
+}
+
+#[pin_project]
+pub struct FailAfterReducerAsync<R> {
+	#[pin]
+	reducer: R,
+	n: usize,
+	count: usize,
+}
+impl<R, T, A, E> Sink<(T, Errors<E>)> for FailAfterReducerAsync<R>
+where
+	R: Sink<(T, Errors<E>), Done = (A, Errors<E>)>,
+{
+	type Done = Result<A, Errors<E>>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = (T, Errors<E>)>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let (n, count) = (*self_.n, self_.count);
+		// Once more than `n` errors have arrived the outcome is known, so the stream is
+		// ended rather than the remaining tasks awaited.
+		let stream = stream::poll_fn(|cx| {
+			if *count > n {
+				return Poll::Ready(None);
+			}
+			let item = ready!(stream.poll_next_unpin(cx));
+			if let Some((_, errors)) = &item {
+				*count += errors.count;
+			}
+			Poll::Ready(item)
+		});
+		pin_mut!(stream);
+		self_
+			.reducer
+			.poll_forward(cx, stream)
+			.map(|(done, errors)| {
+				if errors.count <= n {
+					Ok(done)
+				} else {
+					Err(errors)
+				}
+			})
+	}
+}
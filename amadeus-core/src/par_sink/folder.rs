@@ -66,6 +66,11 @@ pub trait FolderSync<Item> {
 	fn zero(&mut self) -> Self::State;
 	fn push(&mut self, state: &mut Self::State, item: Item);
 	fn done(&mut self, state: Self::State) -> Self::Done;
+	/// Whether items need to be pushed in the order of the tasks they came from. See
+	/// [`Reducer::ordered`].
+	fn ordered(&self) -> bool {
+		false
+	}
 }
 
 pub struct Inter;
@@ -96,6 +101,9 @@ where
 			marker: PhantomData,
 		}
 	}
+	fn ordered(&self) -> bool {
+		self.folder.ordered()
+	}
 }
 impl<Item, F> ReducerProcessSend<Item> for FolderSyncReducer<Item, F, Inter>
 where
@@ -126,6 +134,9 @@ where
 			marker: PhantomData,
 		}
 	}
+	fn ordered(&self) -> bool {
+		self.folder.ordered()
+	}
 }
 impl<Item, F> ReducerProcessSend<Item> for FolderSyncReducer<Item, F, Final>
 where
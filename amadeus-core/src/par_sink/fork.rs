@@ -242,7 +242,7 @@ where
 			{
 				let waker = cx.waker();
 				let stream = stream::poll_fn(|cx| {
-					if *ref_given {
+					if *ref_given && !item_given {
 						item_given = true;
 						return Poll::Ready(Some(pending.take().unwrap()));
 					}
@@ -0,0 +1,327 @@
+#![allow(clippy::type_complexity, clippy::too_many_lines)]
+
+use derive_new::new;
+use educe::Educe;
+use futures::{pin_mut, ready, stream, Stream, StreamExt};
+use indexmap::IndexMap;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	hash::Hash, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{
+	DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, Partitions, PipeTask, Reducer, ReducerSend, ShuffleFile, ShuffleFilesReducer
+};
+use crate::{
+	pipe::{Pipe, Sink, StreamExt as _}, pool::ProcessSend, util::IoError
+};
+
+/// Groups `(key, value)` rows by key like [`GroupBy`](super::GroupBy), except that each
+/// worker holds the partial aggregates of at most `max_keys` distinct keys in memory,
+/// however large each aggregate is. Beyond that it finishes them, converts them with
+/// `convert`, and spills them to disk hash-partitioned by key, to be merged a partition
+/// at a time by [`Grouped`](crate::par_stream::Grouped). Like
+/// [`Shuffle`](super::Shuffle), the workers need to share a temporary directory, and a
+/// failure to spill returns the error.
+#[derive(new)]
+#[must_use]
+pub struct GroupBySpill<A, P, R, C> {
+	a: A,
+	task: P,
+	reduce_a: R,
+	convert: C,
+	max_keys: usize,
+	partitions: usize,
+}
+
+impl<A: ParallelPipe<Item, Output = (T, U)>, P, R, C, Item, T, U> ParallelSink<Item>
+	for GroupBySpill<A, P, R, C>
+where
+	P: PipeTask<U> + Clone + Send + 'static,
+	R: Reducer<P::Output> + Clone + Send + 'static,
+	C: Reducer<R::Done> + Clone + Send + 'static,
+	C::Done: Serialize,
+	T: Eq + Hash + Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = A;
+	type ReduceA = GroupBySpillReducer<P, R, C, T, U>;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.a,
+			GroupBySpillReducer::new(
+				self.task,
+				self.reduce_a,
+				self.convert,
+				self.max_keys,
+				self.partitions,
+			),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+impl<A: DistributedPipe<Item, Output = (T, U)>, P, R, C, Item, T, U> DistributedSink<Item>
+	for GroupBySpill<A, P, R, C>
+where
+	P: PipeTask<U> + Clone + ProcessSend + 'static,
+	R: Reducer<P::Output> + Clone + ProcessSend + 'static,
+	C: Reducer<R::Done> + Clone + ProcessSend + 'static,
+	C::Done: Serialize,
+	T: Eq + Hash + Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = A;
+	type ReduceA = GroupBySpillReducer<P, R, C, T, U>;
+	type ReduceB = ShuffleFilesReducer;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.a,
+			GroupBySpillReducer::new(
+				self.task,
+				self.reduce_a,
+				self.convert,
+				self.max_keys,
+				self.partitions,
+			),
+			ShuffleFilesReducer::new(),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "P: Clone, R: Clone, C: Clone"))]
+#[serde(
+	bound(serialize = "P: Serialize, R: Serialize, C: Serialize"),
+	bound(deserialize = "P: Deserialize<'de>, R: Deserialize<'de>, C: Deserialize<'de>")
+)]
+pub struct GroupBySpillReducer<P, R, C, T, U> {
+	task: P,
+	factory: R,
+	convert: C,
+	max_keys: usize,
+	partitions: usize,
+	marker: PhantomData<fn() -> (T, U)>,
+}
+
+impl<P, R, C, T, U> Reducer<(T, U)> for GroupBySpillReducer<P, R, C, T, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	C: Reducer<R::Done> + Clone,
+	C::Done: Serialize,
+	T: Eq + Hash + Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Async = GroupBySpillReducerAsync<P::Async, R, C, T, U>;
+
+	fn into_async(self) -> Self::Async {
+		GroupBySpillReducerAsync {
+			pipe: self.task.into_async(),
+			factory: self.factory,
+			convert: self.convert,
+			max_keys: self.max_keys,
+			files: Partitions::new(self.partitions),
+			state: State::Reading,
+			ended: false,
+			pending: None,
+			map: IndexMap::new(),
+		}
+	}
+}
+impl<P, R, C, T, U> ReducerSend<(T, U)> for GroupBySpillReducer<P, R, C, T, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	C: Reducer<R::Done> + Clone,
+	C::Done: Serialize,
+	T: Eq + Hash + Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+}
+
+enum State<T, D, C> {
+	Reading,
+	Finishing(Vec<Option<D>>),
+	Spilling(vec::IntoIter<(T, D)>, Option<(T, Option<D>, Pin<Box<C>>)>),
+}
+
+#[pin_project]
+pub struct GroupBySpillReducerAsync<P, R, C, T, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output>,
+	C: Reducer<R::Done>,
+{
+	#[pin]
+	pipe: P,
+	factory: R,
+	convert: C,
+	max_keys: usize,
+	files: Partitions,
+	state: State<T, R::Done, C::Async>,
+	ended: bool,
+	pending: Option<(T, Option<U>, Option<Pin<Box<R::Async>>>)>,
+	map: IndexMap<T, Pin<Box<R::Async>>>,
+}
+
+impl<P, R, C, T, U> Sink<(T, U)> for GroupBySpillReducerAsync<P, R, C, T, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output> + Clone,
+	C: Reducer<R::Done> + Clone,
+	C::Done: Serialize,
+	T: Eq + Hash + Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = (T, U)>>,
+	) -> Poll<Self::Done> {
+		let mut self_ = self.project();
+		loop {
+			match self_.state {
+				State::Reading => {
+					if self_.pending.is_none() {
+						if !self_.map.is_empty() && self_.map.len() >= *self_.max_keys {
+							*self_.state =
+								State::Finishing(self_.map.iter().map(|_| None).collect());
+							continue;
+						}
+						if let Some((k, u)) = ready!(stream.as_mut().poll_next(cx)) {
+							let r = if !self_.map.contains_key(&k) {
+								Some(Box::pin(self_.factory.clone().into_async()))
+							} else {
+								None
+							};
+							*self_.pending = Some((k, Some(u), r));
+						} else {
+							*self_.ended = true;
+							*self_.state =
+								State::Finishing(self_.map.iter().map(|_| None).collect());
+							continue;
+						}
+					}
+					let (k, u, r) = self_.pending.as_mut().unwrap();
+					let waker = cx.waker();
+					let stream = stream::poll_fn(|cx| {
+						u.take().map_or_else(
+							|| {
+								let waker_ = cx.waker();
+								if !waker.will_wake(waker_) {
+									waker_.wake_by_ref();
+								}
+								Poll::Pending
+							},
+							|u| Poll::Ready(Some(u)),
+						)
+					})
+					.fuse()
+					.pipe(self_.pipe.as_mut());
+					pin_mut!(stream);
+					let map = &mut *self_.map;
+					let r_ = r.as_mut().unwrap_or_else(|| map.get_mut(k).unwrap());
+					if r_.as_mut().poll_forward(cx, stream).is_ready() {
+						let _ = u.take();
+					}
+					if u.is_some() {
+						return Poll::Pending;
+					}
+					let (k, _u, r) = self_.pending.take().unwrap();
+					if let Some(r) = r {
+						let _ = self_.map.insert(k, r);
+					}
+				}
+				State::Finishing(done) => {
+					let mut done_ = true;
+					self_
+						.map
+						.values_mut()
+						.zip(done.iter_mut())
+						.for_each(|(r, done)| {
+							if done.is_none() {
+								let stream = stream::empty();
+								pin_mut!(stream);
+								if let Poll::Ready(done_) = r.as_mut().poll_forward(cx, stream) {
+									*done = Some(done_);
+								} else {
+									done_ = false;
+								}
+							}
+						});
+					if !done_ {
+						return Poll::Pending;
+					}
+					let rows = mem::take(self_.map)
+						.into_iter()
+						.zip(done.iter_mut())
+						.map(|((k, _), v)| (k, v.take().unwrap()))
+						.collect::<Vec<_>>();
+					*self_.state = State::Spilling(rows.into_iter(), None);
+				}
+				State::Spilling(rows, current) => {
+					if current.is_none() {
+						if let Some((k, v)) = rows.next() {
+							let c = Box::pin(self_.convert.clone().into_async());
+							*current = Some((k, Some(v), c));
+						} else {
+							*self_.state = State::Reading;
+							if *self_.ended {
+								return Poll::Ready(self_.files.finish());
+							}
+							continue;
+						}
+					}
+					let (_, v, c) = current.as_mut().unwrap();
+					let stream = stream::poll_fn(|_| Poll::Ready(v.take()));
+					pin_mut!(stream);
+					let partial = ready!(c.as_mut().poll_forward(cx, stream));
+					let (k, _, _) = current.take().unwrap();
+					let row = (k, partial);
+					if let Err(err) = self_.files.write(&row.0, &row) {
+						return Poll::Ready(Err(err.into()));
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Reduces a single row to itself, for spilling partial aggregates that need no
+/// converting.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone, Default)]
+#[serde(bound = "")]
+pub struct OnceReducer<T>(PhantomData<fn() -> T>);
+
+impl<T> Reducer<T> for OnceReducer<T> {
+	type Done = T;
+	type Async = OnceReducerAsync<T>;
+
+	fn into_async(self) -> Self::Async {
+		OnceReducerAsync(None)
+	}
+}
+
+#[pin_project]
+pub struct OnceReducerAsync<T>(Option<T>);
+impl<T> Sink<T> for OnceReducerAsync<T> {
+	type Done = T;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = T>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+			*self_.0 = Some(item);
+		}
+		Poll::Ready(self_.0.take().expect("OnceReducer given no rows"))
+	}
+}
@@ -0,0 +1,83 @@
+use amadeus_streaming::TDigest;
+use derive_new::new;
+use educe::Educe;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+
+/// The compression of the [`TDigest`] used to estimate quantiles.
+const COMPRESSION: f64 = 100.0;
+
+#[derive(new)]
+#[must_use]
+pub struct Quantiles<P> {
+	pipe: P,
+	quantiles: Vec<f64>,
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Quantiles<P> {
+		folder_par_sink!(
+			QuantilesFolder<StepA>,
+			QuantilesFolder<StepB>,
+			self,
+			QuantilesFolder::new(self.quantiles.clone()),
+			QuantilesFolder::new(self.quantiles)
+		);
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct QuantilesFolder<Step> {
+	quantiles: Vec<f64>,
+	marker: PhantomData<fn() -> Step>,
+}
+
+pub struct StepA;
+pub struct StepB;
+
+impl FolderSync<f64> for QuantilesFolder<StepA> {
+	type State = TDigest;
+	type Done = TDigest;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(COMPRESSION)
+	}
+
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: f64) {
+		state.push(item);
+	}
+
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+impl FolderSync<TDigest> for QuantilesFolder<StepB> {
+	type State = TDigest;
+	type Done = Vec<Option<f64>>;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(COMPRESSION)
+	}
+
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: TDigest) {
+		state.union(&item);
+	}
+
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		self.quantiles
+			.iter()
+			.map(|&quantile| state.quantile(quantile))
+			.collect()
+	}
+}
@@ -0,0 +1,404 @@
+use derive_new::new;
+use educe::Educe;
+use futures::{ready, Stream};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use serde_closure::traits;
+use std::{
+	collections::hash_map::DefaultHasher, convert::TryFrom, hash::{Hash, Hasher}, io, marker::PhantomData, mem, path::PathBuf, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+};
+use crate::{
+	pipe::Sink, pool::ProcessSend, spill::{temp_dir_id, SpillFile, SpillReader, SpillWriter}, util::IoError
+};
+
+/// Hash-partitions `(key, value)` rows by key, with each worker spilling the rows of
+/// each partition to a file. This is the first half of a shuffle: the rows of a
+/// partition can then be read together by a single worker.
+///
+/// # Spilling
+///
+/// The files are written to each worker's temporary directory, so the workers need
+/// to be on a single host, or share a temporary directory; reading a file written
+/// elsewhere fails with an error. If writing any file fails, they're all removed and
+/// the error is returned. The same holds for every operation that spills rows to
+/// disk, such as [`hash_join`](crate::par_stream::ParallelStream::hash_join).
+#[derive(new)]
+#[must_use]
+pub struct Shuffle<P> {
+	pipe: P,
+	partitions: usize,
+}
+
+impl<P: ParallelPipe<Item, Output = (K, V)>, Item, K, V> ParallelSink<Item> for Shuffle<P>
+where
+	K: Hash + Serialize + Send + 'static,
+	V: Serialize + Send + 'static,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = P;
+	type ReduceA = ShuffleReducer<(K, V)>;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			ShuffleReducer::new(self.partitions),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+impl<P: DistributedPipe<Item, Output = (K, V)>, Item, K, V> DistributedSink<Item> for Shuffle<P>
+where
+	K: Hash + ProcessSend + 'static,
+	V: ProcessSend + 'static,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = P;
+	type ReduceA = ShuffleReducer<(K, V)>;
+	type ReduceB = ShuffleFilesReducer;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			ShuffleReducer::new(self.partitions),
+			ShuffleFilesReducer::new(),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+
+/// Hash-partitions rows by the key `f` gives them, with each worker spilling the rows
+/// of each partition to a file. Unlike [`Shuffle`] only the rows are spilled, not
+/// their keys.
+#[derive(new)]
+#[must_use]
+pub struct ShuffleBy<P, F> {
+	pipe: P,
+	f: F,
+	partitions: usize,
+}
+
+impl<P: ParallelPipe<Item>, Item, F, K> ParallelSink<Item> for ShuffleBy<P, F>
+where
+	F: for<'a> traits::FnMut<(&'a P::Output,), Output = K> + Clone + Send + 'static,
+	K: Hash,
+	P::Output: Serialize + Send + 'static,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = P;
+	type ReduceA = ShuffleByReducer<P::Output, F>;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			ShuffleByReducer::new(self.f, self.partitions),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+impl<P: DistributedPipe<Item>, Item, F, K> DistributedSink<Item> for ShuffleBy<P, F>
+where
+	F: for<'a> traits::FnMut<(&'a P::Output,), Output = K> + Clone + ProcessSend + 'static,
+	K: Hash,
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = P;
+	type ReduceA = ShuffleByReducer<P::Output, F>;
+	type ReduceB = ShuffleFilesReducer;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			ShuffleByReducer::new(self.f, self.partitions),
+			ShuffleFilesReducer::new(),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+
+/// A file of rows spilled by a worker of a [`Shuffle`] or [`ShuffleBy`], all in the
+/// same partition.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShuffleFile {
+	pub partition: usize,
+	pub path: PathBuf,
+	pub len: usize,
+	temp_dir: u64,
+}
+impl ShuffleFile {
+	/// Opens the file to read its rows, failing if it was written to a temporary
+	/// directory other than this worker's.
+	pub(crate) fn open<T>(&self) -> io::Result<SpillReader<T>> {
+		if temp_dir_id()? != self.temp_dir {
+			return Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!(
+					"spill file {} was written on another host; shuffles need the workers to be on a single host or share a temporary directory",
+					self.path.display()
+				),
+			));
+		}
+		SpillReader::open(&self.path, 0, self.len)
+	}
+}
+
+/// The partition that rows with `key` are shuffled to.
+pub(crate) fn partition_of<K: Hash + ?Sized>(key: &K, partitions: usize) -> usize {
+	// `DefaultHasher::new` uses fixed keys, so this is the same on every worker
+	let mut hasher = DefaultHasher::new();
+	key.hash(&mut hasher);
+	usize::try_from(hasher.finish() % partitions as u64).unwrap()
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct ShuffleReducer<Item> {
+	partitions: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+impl<K, V> Reducer<(K, V)> for ShuffleReducer<(K, V)>
+where
+	K: Hash + Serialize,
+	V: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Async = ShuffleReducerAsync<(K, V)>;
+
+	fn into_async(self) -> Self::Async {
+		ShuffleReducerAsync {
+			files: Partitions::new(self.partitions),
+			marker: PhantomData,
+		}
+	}
+}
+impl<K, V> ReducerSend<(K, V)> for ShuffleReducer<(K, V)>
+where
+	K: Hash + Serialize,
+	V: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+}
+
+#[pin_project]
+pub struct ShuffleReducerAsync<Item> {
+	files: Partitions,
+	marker: PhantomData<fn() -> Item>,
+}
+impl<K, V> Sink<(K, V)> for ShuffleReducerAsync<(K, V)>
+where
+	K: Hash + Serialize,
+	V: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = (K, V)>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(row) = ready!(stream.as_mut().poll_next(cx)) {
+			if let Err(err) = self_.files.write(&row.0, &row) {
+				return Poll::Ready(Err(err.into()));
+			}
+		}
+		Poll::Ready(self_.files.finish())
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "F: Clone"))]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct ShuffleByReducer<Item, F> {
+	f: F,
+	partitions: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+impl<Item, F, K> Reducer<Item> for ShuffleByReducer<Item, F>
+where
+	F: for<'a> traits::FnMut<(&'a Item,), Output = K>,
+	K: Hash,
+	Item: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Async = ShuffleByReducerAsync<Item, F>;
+
+	fn into_async(self) -> Self::Async {
+		ShuffleByReducerAsync {
+			f: self.f,
+			files: Partitions::new(self.partitions),
+			marker: PhantomData,
+		}
+	}
+}
+impl<Item, F, K> ReducerSend<Item> for ShuffleByReducer<Item, F>
+where
+	F: for<'a> traits::FnMut<(&'a Item,), Output = K>,
+	K: Hash,
+	Item: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+}
+
+#[pin_project]
+pub struct ShuffleByReducerAsync<Item, F> {
+	f: F,
+	files: Partitions,
+	marker: PhantomData<fn() -> Item>,
+}
+impl<Item, F, K> Sink<Item> for ShuffleByReducerAsync<Item, F>
+where
+	F: for<'a> traits::FnMut<(&'a Item,), Output = K>,
+	K: Hash,
+	Item: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(row) = ready!(stream.as_mut().poll_next(cx)) {
+			let key = self_.f.call_mut((&row,));
+			if let Err(err) = self_.files.write(&key, &row) {
+				return Poll::Ready(Err(err.into()));
+			}
+		}
+		Poll::Ready(self_.files.finish())
+	}
+}
+
+/// Gathers the spill files of the workers of a [`Shuffle`] or [`ShuffleBy`], or fails
+/// with the first error, removing the files of the others.
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct ShuffleFilesReducer;
+impl Reducer<Result<Vec<ShuffleFile>, IoError>> for ShuffleFilesReducer {
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Async = ShuffleFilesReducerAsync;
+
+	fn into_async(self) -> Self::Async {
+		ShuffleFilesReducerAsync {
+			files: Vec::new(),
+			error: None,
+		}
+	}
+}
+impl ReducerProcessSend<Result<Vec<ShuffleFile>, IoError>> for ShuffleFilesReducer {
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+}
+impl ReducerSend<Result<Vec<ShuffleFile>, IoError>> for ShuffleFilesReducer {
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+}
+
+#[pin_project]
+pub struct ShuffleFilesReducerAsync {
+	files: Vec<ShuffleFile>,
+	error: Option<IoError>,
+}
+impl Sink<Result<Vec<ShuffleFile>, IoError>> for ShuffleFilesReducerAsync {
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = Result<Vec<ShuffleFile>, IoError>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(files) = ready!(stream.as_mut().poll_next(cx)) {
+			match (files, &self_.error) {
+				(Ok(files), None) => self_.files.extend(files),
+				(Ok(files), Some(_)) => remove(files),
+				(Err(err), _) => {
+					remove(mem::take(self_.files));
+					let _ = self_.error.get_or_insert(err);
+				}
+			}
+		}
+		Poll::Ready(match self_.error.take() {
+			Some(err) => Err(err),
+			None => Ok(mem::take(self_.files)),
+		})
+	}
+}
+
+/// Removes the spill files `files`.
+pub(crate) fn remove(files: Vec<ShuffleFile>) {
+	for file in files {
+		drop(SpillFile(file.path));
+	}
+}
+
+/// A worker's spill files, one for each partition it has seen rows of.
+pub(crate) struct Partitions {
+	files: Vec<Option<SpillWriter>>,
+}
+impl Partitions {
+	pub(crate) fn new(partitions: usize) -> Self {
+		Self {
+			files: (0..partitions).map(|_| None).collect(),
+		}
+	}
+	/// Writes `row` to the file of the partition of `key`. On error, the files written
+	/// so far are removed.
+	pub(crate) fn write<K: Hash + ?Sized, T: Serialize>(
+		&mut self, key: &K, row: &T,
+	) -> io::Result<()> {
+		let partition = partition_of(key, self.files.len());
+		self.write_to(partition, row)
+	}
+	/// Writes `row` to the file of `partition`. On error, the files written so far are
+	/// removed.
+	pub(crate) fn write_to<T: Serialize>(&mut self, partition: usize, row: &T) -> io::Result<()> {
+		let file = &mut self.files[partition];
+		let res = match file {
+			Some(file) => file.write(row),
+			None => {
+				SpillWriter::new("shuffle").and_then(|file_| file.get_or_insert(file_).write(row))
+			}
+		};
+		if res.is_err() {
+			self.clear();
+		}
+		res
+	}
+	/// Removes the files written so far.
+	pub(crate) fn clear(&mut self) {
+		// Dropping unfinished spill files removes them
+		self.files.iter_mut().for_each(|file| *file = None);
+	}
+	/// Finishes the files, or removes them on error.
+	pub(crate) fn finish(&mut self) -> Result<Vec<ShuffleFile>, IoError> {
+		let temp_dir = temp_dir_id()?;
+		let mut files = Vec::new();
+		for (partition, file) in self.files.iter_mut().enumerate() {
+			if let Some(file) = file.take() {
+				match file.finish() {
+					Ok((path, len)) => files.push(ShuffleFile {
+						partition,
+						path,
+						len,
+						temp_dir,
+					}),
+					Err(err) => {
+						remove(files);
+						self.clear();
+						return Err(err.into());
+					}
+				}
+			}
+		}
+		Ok(files)
+	}
+}
@@ -0,0 +1,707 @@
+use derive_new::new;
+use educe::Educe;
+use futures::{ready, Stream};
+use itertools::Itertools;
+use pin_project::pin_project;
+use rand::{thread_rng, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_closure::traits;
+use std::{
+	cmp::Ordering, iter, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{
+	remove, DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, Partitions, Reducer, ReducerProcessSend, ReducerSend, ShuffleFile, ShuffleFilesReducer
+};
+use crate::{
+	into_par_stream::{IntoDistributedStream, IntoParallelStream}, par_stream::{DistributedStream, ParallelStream, StreamTask}, pipe::Sink, pool::ProcessSend, spill::{SpillFile, SpillReader, SpillWriter}, util::IoError
+};
+
+/// The number of rows each worker samples to choose the boundaries of the ranges.
+const SAMPLES: usize = 1024;
+
+/// The first step of a [`sort_by`](crate::par_stream::ParallelStream::sort_by): each
+/// worker spills the rows it's given to a file as they arrive, and samples them.
+#[derive(new)]
+#[must_use]
+pub struct SortSample<P> {
+	pipe: P,
+}
+
+impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for SortSample<P>
+where
+	P::Output: Serialize + Send + 'static,
+{
+	type Done = Result<SortSamples<P::Output>, IoError>;
+	type Pipe = P;
+	type ReduceA = SortSampleReducer<P::Output>;
+	type ReduceC = SortSamplesReducer<P::Output>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			SortSampleReducer::new(),
+			SortSamplesReducer::new(),
+		)
+	}
+}
+impl<P: DistributedPipe<Item>, Item> DistributedSink<Item> for SortSample<P>
+where
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Result<SortSamples<P::Output>, IoError>;
+	type Pipe = P;
+	type ReduceA = SortSampleReducer<P::Output>;
+	type ReduceB = SortSamplesReducer<P::Output>;
+	type ReduceC = SortSamplesReducer<P::Output>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			SortSampleReducer::new(),
+			SortSamplesReducer::new(),
+			SortSamplesReducer::new(),
+		)
+	}
+}
+
+/// The rows spilled by the workers of a [`SortSample`], along with each worker's
+/// sample of them.
+#[derive(Serialize, Deserialize)]
+pub struct SortSamples<T> {
+	files: Vec<ShuffleFile>,
+	samples: Vec<Sample<T>>,
+}
+/// Up to [`SAMPLES`] rows chosen uniformly at random from the `len` rows of a worker.
+#[derive(Serialize, Deserialize)]
+struct Sample<T> {
+	rows: Vec<T>,
+	len: usize,
+}
+impl<T> SortSamples<T> {
+	/// The number of rows sampled from.
+	pub(crate) fn len(&self) -> usize {
+		self.samples.iter().map(|sample| sample.len).sum()
+	}
+	/// Chooses the rows at which each range after the first starts, so that the ranges
+	/// hold about `buffer` rows each, and returns them along with the spill files, each
+	/// in a partition of its own so that they can be read by separate tasks.
+	#[allow(clippy::cast_precision_loss)]
+	pub(crate) fn into_bounds<F>(self, buffer: usize, f: &F) -> (Vec<ShuffleFile>, Vec<T>)
+	where
+		F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering>,
+	{
+		let ranges = self.len() / buffer + 1;
+		let mut samples = self
+			.samples
+			.into_iter()
+			.flat_map(|Sample { rows, len }| {
+				let weight = len as f64 / rows.len() as f64;
+				rows.into_iter().map(move |row| (row, weight))
+			})
+			.collect::<Vec<_>>();
+		samples.sort_by(|a, b| f.call((&a.0, &b.0)));
+		let total = samples.iter().map(|&(_, weight)| weight).sum::<f64>();
+		let mut samples = samples.into_iter();
+		let mut bounds: Vec<T> = Vec::with_capacity(ranges - 1);
+		if let Some((first, weight)) = samples.next() {
+			// Bounds are strictly increasing, so that no range is empty
+			let mut seen = weight;
+			let mut next = 1;
+			for (row, weight) in samples {
+				if next < ranges && seen >= total * next as f64 / ranges as f64 {
+					if f.call((bounds.last().unwrap_or(&first), &row)) == Ordering::Less {
+						bounds.push(row);
+					}
+					while next < ranges && seen >= total * next as f64 / ranges as f64 {
+						next += 1;
+					}
+				}
+				seen += weight;
+			}
+		}
+		let files = self
+			.files
+			.into_iter()
+			.enumerate()
+			.map(|(partition, mut file)| {
+				file.partition = partition;
+				file
+			})
+			.collect();
+		(files, bounds)
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct SortSampleReducer<T> {
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> Reducer<T> for SortSampleReducer<T>
+where
+	T: Serialize,
+{
+	type Done = Result<SortSamples<T>, IoError>;
+	type Async = SortSampleReducerAsync<T>;
+
+	fn into_async(self) -> Self::Async {
+		SortSampleReducerAsync {
+			files: Partitions::new(1),
+			rows: Vec::new(),
+			len: 0,
+		}
+	}
+}
+impl<T> ReducerSend<T> for SortSampleReducer<T>
+where
+	T: Serialize + Send + 'static,
+{
+	type Done = Result<SortSamples<T>, IoError>;
+}
+
+#[pin_project]
+pub struct SortSampleReducerAsync<T> {
+	files: Partitions,
+	rows: Vec<T>,
+	len: usize,
+}
+impl<T> Sink<T> for SortSampleReducerAsync<T>
+where
+	T: Serialize,
+{
+	type Done = Result<SortSamples<T>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = T>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(row) = ready!(stream.as_mut().poll_next(cx)) {
+			if let Err(err) = self_.files.write_to(0, &row) {
+				return Poll::Ready(Err(err.into()));
+			}
+			// Reservoir sampling
+			*self_.len += 1;
+			if self_.rows.len() < SAMPLES {
+				self_.rows.push(row);
+			} else {
+				let i = thread_rng().gen_range(0, *self_.len);
+				if i < SAMPLES {
+					self_.rows[i] = row;
+				}
+			}
+		}
+		Poll::Ready(self_.files.finish().map(|files| SortSamples {
+			files,
+			samples: vec![Sample {
+				rows: mem::take(self_.rows),
+				len: *self_.len,
+			}],
+		}))
+	}
+}
+
+/// Gathers the spill files and samples of the workers of a [`SortSample`], or fails
+/// with the first error, removing the files of the others.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct SortSamplesReducer<T> {
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> Reducer<Result<SortSamples<T>, IoError>> for SortSamplesReducer<T> {
+	type Done = Result<SortSamples<T>, IoError>;
+	type Async = SortSamplesReducerAsync<T>;
+
+	fn into_async(self) -> Self::Async {
+		SortSamplesReducerAsync {
+			files: Vec::new(),
+			samples: Vec::new(),
+			error: None,
+		}
+	}
+}
+impl<T> ReducerProcessSend<Result<SortSamples<T>, IoError>> for SortSamplesReducer<T>
+where
+	T: ProcessSend + 'static,
+{
+	type Done = Result<SortSamples<T>, IoError>;
+}
+impl<T> ReducerSend<Result<SortSamples<T>, IoError>> for SortSamplesReducer<T>
+where
+	T: Send + 'static,
+{
+	type Done = Result<SortSamples<T>, IoError>;
+}
+
+#[pin_project]
+pub struct SortSamplesReducerAsync<T> {
+	files: Vec<ShuffleFile>,
+	samples: Vec<Sample<T>>,
+	error: Option<IoError>,
+}
+impl<T> Sink<Result<SortSamples<T>, IoError>> for SortSamplesReducerAsync<T> {
+	type Done = Result<SortSamples<T>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = Result<SortSamples<T>, IoError>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(samples) = ready!(stream.as_mut().poll_next(cx)) {
+			match (samples, &self_.error) {
+				(Ok(samples), None) => {
+					self_.files.extend(samples.files);
+					self_.samples.extend(samples.samples);
+				}
+				(Ok(samples), Some(_)) => remove(samples.files),
+				(Err(err), _) => {
+					remove(mem::take(self_.files));
+					let _ = self_.error.get_or_insert(err);
+				}
+			}
+		}
+		Poll::Ready(match self_.error.take() {
+			Some(err) => Err(err),
+			None => Ok(SortSamples {
+				files: mem::take(self_.files),
+				samples: mem::take(self_.samples),
+			}),
+		})
+	}
+}
+
+/// The second step of a [`sort_by`](crate::par_stream::ParallelStream::sort_by): the
+/// spilled rows are range-partitioned by `bounds` like a [`Shuffle`](super::Shuffle),
+/// so that each range can then be sorted by a single worker. Fails with the first
+/// error reading or writing a spill file.
+#[derive(new)]
+#[must_use]
+pub struct SortShuffle<P, T, F> {
+	pipe: P,
+	bounds: Vec<T>,
+	f: F,
+}
+
+impl<P: ParallelPipe<Item, Output = Result<T, IoError>>, Item, T, F> ParallelSink<Item>
+	for SortShuffle<P, T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + Send + 'static,
+	T: Clone + Serialize + Send + 'static,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = P;
+	type ReduceA = SortShuffleReducer<T, F>;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			SortShuffleReducer::new(self.bounds, self.f),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+impl<P: DistributedPipe<Item, Output = Result<T, IoError>>, Item, T, F> DistributedSink<Item>
+	for SortShuffle<P, T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + ProcessSend + 'static,
+	T: Clone + ProcessSend + 'static,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Pipe = P;
+	type ReduceA = SortShuffleReducer<T, F>;
+	type ReduceB = ShuffleFilesReducer;
+	type ReduceC = ShuffleFilesReducer;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			SortShuffleReducer::new(self.bounds, self.f),
+			ShuffleFilesReducer::new(),
+			ShuffleFilesReducer::new(),
+		)
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone(bound = "T: Clone, F: Clone"))]
+#[serde(
+	bound(serialize = "T: Serialize, F: Serialize"),
+	bound(deserialize = "T: Deserialize<'de>, F: Deserialize<'de>")
+)]
+pub struct SortShuffleReducer<T, F> {
+	bounds: Vec<T>,
+	f: F,
+}
+impl<T, F> Reducer<Result<T, IoError>> for SortShuffleReducer<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering>,
+	T: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+	type Async = SortShuffleReducerAsync<T, F>;
+
+	fn into_async(self) -> Self::Async {
+		SortShuffleReducerAsync {
+			files: Partitions::new(self.bounds.len() + 1),
+			bounds: self.bounds,
+			f: self.f,
+		}
+	}
+}
+impl<T, F> ReducerSend<Result<T, IoError>> for SortShuffleReducer<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering>,
+	T: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+}
+
+#[pin_project]
+pub struct SortShuffleReducerAsync<T, F> {
+	files: Partitions,
+	bounds: Vec<T>,
+	f: F,
+}
+impl<T, F> Sink<Result<T, IoError>> for SortShuffleReducerAsync<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering>,
+	T: Serialize,
+{
+	type Done = Result<Vec<ShuffleFile>, IoError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = Result<T, IoError>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while let Some(row) = ready!(stream.as_mut().poll_next(cx)) {
+			let row = match row {
+				Ok(row) => row,
+				Err(err) => {
+					self_.files.clear();
+					return Poll::Ready(Err(err));
+				}
+			};
+			// The range is the number of bounds at or before the row
+			let range = self_
+				.bounds
+				.binary_search_by(|bound| match self_.f.call((bound, &row)) {
+					Ordering::Greater => Ordering::Greater,
+					_ => Ordering::Less,
+				})
+				.unwrap_err();
+			if let Err(err) = self_.files.write_to(range, &row) {
+				return Poll::Ready(Err(err.into()));
+			}
+		}
+		Poll::Ready(self_.files.finish())
+	}
+}
+
+/// The rows of a stream, as sorted by
+/// [`sort_by`](crate::par_stream::ParallelStream::sort_by).
+///
+/// The rows are sorted in three steps, each spread across the workers. First each
+/// worker spills the rows it's given and samples them; the samples are used to choose
+/// boundaries that split the rows into ranges of about `buffer` rows each. Then the
+/// spilled rows are shuffled into the ranges. Finally each range is sorted by a worker,
+/// with runs of up to `buffer` rows sorted in memory, spilled, and then merged if the
+/// range holds more.
+///
+/// Iterating over this sorts the ranges one after another into a single ordered
+/// iterator. Alternatively it can be turned into a parallel or distributed stream, with
+/// each range sorted by a worker. Workers are allocated consecutive ranges and results
+/// are reduced in order, so piping it to a [`Destination`](crate::Destination) writes
+/// part files that are themselves in order.
+///
+/// The rows are `Result`s, as reading a spill file can fail. The spill files are
+/// deleted when this, or the stream or iterator it's turned into, is dropped. Like
+/// those of a [`Shuffle`](super::Shuffle), they're read by whichever worker sorts a
+/// range.
+pub struct Sorted<T, F> {
+	ranges: Vec<Vec<ShuffleFile>>,
+	len: usize,
+	f: F,
+	buffer: usize,
+	files: Vec<SpillFile>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T, F> Sorted<T, F> {
+	pub(crate) fn new(
+		files: Vec<ShuffleFile>, ranges: usize, len: usize, f: F, buffer: usize,
+	) -> Self {
+		let files_ = files
+			.iter()
+			.map(|file| SpillFile(file.path.clone()))
+			.collect();
+		let mut ranges = (0..ranges).map(|_| Vec::new()).collect::<Vec<_>>();
+		for file in files {
+			ranges[file.partition].push(file);
+		}
+		Self {
+			ranges,
+			len,
+			f,
+			buffer,
+			files: files_,
+			marker: PhantomData,
+		}
+	}
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	/// The number of ranges the rows are split into.
+	pub fn ranges(&self) -> usize {
+		self.ranges.len()
+	}
+}
+
+/// Sorts the rows of a range, holding up to `buffer` of them in memory at once. Errors
+/// are yielded as soon as they're hit.
+fn sort<T, F>(
+	files: Vec<ShuffleFile>, f: F, buffer: usize,
+) -> Box<dyn Iterator<Item = Result<T, IoError>>>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + 'static,
+	T: Serialize + DeserializeOwned + 'static,
+{
+	let mut rows = Vec::new();
+	let mut runs = Vec::new();
+	for file in files {
+		let file = match file.open() {
+			Ok(file) => file,
+			Err(err) => return Box::new(iter::once(Err(err.into()))),
+		};
+		for row in file {
+			match row {
+				Ok(row) => rows.push(row),
+				Err(err) => return Box::new(iter::once(Err(err.into()))),
+			}
+			if rows.len() >= buffer {
+				match spill(mem::take(&mut rows), &f) {
+					Ok(run) => runs.push(run),
+					Err(err) => return Box::new(iter::once(Err(err))),
+				}
+			}
+		}
+	}
+	rows.sort_by(|a, b| f.call((a, b)));
+	if runs.is_empty() {
+		return Box::new(rows.into_iter().map(Ok));
+	}
+	let mut iters = Vec::with_capacity(runs.len() + 1);
+	let mut files = Vec::with_capacity(runs.len());
+	for (path, len) in runs {
+		match SpillReader::open(&path, 0, len) {
+			Ok(run) => iters.push(Box::new(run.map(|row| row.map_err(Into::into)))
+				as Box<dyn Iterator<Item = Result<T, IoError>>>),
+			Err(err) => return Box::new(iter::once(Err(err.into()))),
+		}
+		files.push(SpillFile(path));
+	}
+	iters.push(Box::new(rows.into_iter().map(Ok)));
+	Box::new(Merge {
+		rows: iters.into_iter().kmerge_by(move |a, b| match (a, b) {
+			(Ok(a), Ok(b)) => f.call((a, b)) == Ordering::Less,
+			(Err(_), _) => true,
+			(Ok(_), Err(_)) => false,
+		}),
+		_files: files,
+	})
+}
+
+/// Sorts `rows` and spills them to a file, returning its path and the number of rows.
+fn spill<T, F>(mut rows: Vec<T>, f: &F) -> Result<(std::path::PathBuf, usize), IoError>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering>,
+	T: Serialize,
+{
+	rows.sort_by(|a, b| f.call((a, b)));
+	let mut file = SpillWriter::new("sort")?;
+	for row in &rows {
+		file.write(row)?;
+	}
+	Ok(file.finish()?)
+}
+
+/// Merged runs, whose spill files are removed once it's dropped.
+struct Merge<I> {
+	rows: I,
+	_files: Vec<SpillFile>,
+}
+impl<I> Iterator for Merge<I>
+where
+	I: Iterator,
+{
+	type Item = I::Item;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.rows.next()
+	}
+}
+
+/// Iterates over the rows of a [`Sorted`] in order.
+pub struct SortedIter<T, F> {
+	ranges: vec::IntoIter<Vec<ShuffleFile>>,
+	range: Box<dyn Iterator<Item = Result<T, IoError>>>,
+	f: F,
+	buffer: usize,
+	_files: Vec<SpillFile>,
+}
+impl<T, F> Iterator for SortedIter<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + 'static,
+	T: Serialize + DeserializeOwned + 'static,
+{
+	type Item = Result<T, IoError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(row) = self.range.next() {
+				return Some(row);
+			}
+			let range = self.ranges.next()?;
+			self.range = sort(range, self.f.clone(), self.buffer);
+		}
+	}
+}
+impl<T, F> IntoIterator for Sorted<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + 'static,
+	T: Serialize + DeserializeOwned + 'static,
+{
+	type Item = Result<T, IoError>;
+	type IntoIter = SortedIter<T, F>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		SortedIter {
+			ranges: self.ranges.into_iter(),
+			range: Box::new(iter::empty()),
+			f: self.f,
+			buffer: self.buffer,
+			_files: self.files,
+		}
+	}
+}
+
+/// A [`Sorted`] as a stream, with a task for each range.
+#[pin_project]
+pub struct SortedStream<T, F> {
+	ranges: vec::IntoIter<Vec<ShuffleFile>>,
+	f: F,
+	buffer: usize,
+	_files: Vec<SpillFile>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T, F> SortedStream<T, F> {
+	fn new(sorted: Sorted<T, F>) -> Self {
+		Self {
+			ranges: sorted.ranges.into_iter(),
+			f: sorted.f,
+			buffer: sorted.buffer,
+			_files: sorted.files,
+			marker: PhantomData,
+		}
+	}
+}
+impl_par_dist_rename! {
+	impl<T, F> IntoParallelStream for Sorted<T, F>
+	where
+		F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + Send + 'static,
+		T: Send + DeserializeOwned + Serialize + 'static,
+	{
+		type ParStream = SortedStream<T, F>;
+		type Item = Result<T, IoError>;
+
+		#[inline]
+		fn into_par_stream(self) -> Self::ParStream
+		where
+			Self: Sized,
+		{
+			SortedStream::new(self)
+		}
+	}
+
+	impl<T, F> ParallelStream for SortedStream<T, F>
+	where
+		F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + Send + 'static,
+		T: Send + DeserializeOwned + Serialize + 'static,
+	{
+		type Item = Result<T, IoError>;
+		type Task = SortedTask<T, F>;
+
+		#[inline]
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.ranges.size_hint()
+		}
+		#[inline]
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let (f, buffer) = (self_.f, *self_.buffer);
+			Poll::Ready(self_.ranges.next().map(|files| SortedTask {
+				files,
+				f: f.clone(),
+				buffer,
+				marker: PhantomData,
+			}))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct SortedTask<T, F> {
+	files: Vec<ShuffleFile>,
+	f: F,
+	buffer: usize,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T, F> StreamTask for SortedTask<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + 'static,
+	T: Serialize + DeserializeOwned + 'static,
+{
+	type Item = Result<T, IoError>;
+	type Async = SortedTaskAsync<T, F>;
+
+	fn into_async(self) -> Self::Async {
+		SortedTaskAsync {
+			task: Some(self),
+			rows: Box::new(iter::empty()),
+		}
+	}
+}
+
+/// Sorts the range of a [`SortedTask`] when it's first polled.
+#[pin_project]
+pub struct SortedTaskAsync<T, F> {
+	task: Option<SortedTask<T, F>>,
+	rows: Box<dyn Iterator<Item = Result<T, IoError>>>,
+}
+impl<T, F> Stream for SortedTaskAsync<T, F>
+where
+	F: for<'a, 'b> traits::Fn<(&'a T, &'b T), Output = Ordering> + Clone + 'static,
+	T: Serialize + DeserializeOwned + 'static,
+{
+	type Item = Result<T, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if let Some(task) = self_.task.take() {
+			*self_.rows = sort(task.files, task.f, task.buffer);
+		}
+		Poll::Ready(self_.rows.next())
+	}
+}
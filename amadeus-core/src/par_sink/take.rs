@@ -0,0 +1,167 @@
+use derive_new::new;
+use educe::Educe;
+use futures::{ready, Stream};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	marker::PhantomData, mem, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+};
+use crate::{pipe::Sink, pool::ProcessSend};
+
+/// Collects the first `n` rows, in the order [`Collect`](super::Collect) would return
+/// them.
+///
+/// Each worker stops once it has `n` rows, and the rest of the work is dropped once
+/// the first `n` rows have arrived, which cancels the tasks yet to finish.
+#[derive(new)]
+#[must_use]
+pub struct Take<P> {
+	pipe: P,
+	n: usize,
+}
+
+impl<P: ParallelPipe<Item>, Item> ParallelSink<Item> for Take<P>
+where
+	P::Output: Send + 'static,
+{
+	type Done = Vec<P::Output>;
+	type Pipe = P;
+	type ReduceA = TakeReducer<P::Output>;
+	type ReduceC = TakeVecReducer<P::Output>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		(
+			self.pipe,
+			TakeReducer::new(self.n),
+			TakeVecReducer::new(self.n),
+		)
+	}
+}
+impl<P: DistributedPipe<Item>, Item> DistributedSink<Item> for Take<P>
+where
+	P::Output: ProcessSend + 'static,
+{
+	type Done = Vec<P::Output>;
+	type Pipe = P;
+	type ReduceA = TakeReducer<P::Output>;
+	type ReduceB = TakeVecReducer<P::Output>;
+	type ReduceC = TakeVecReducer<P::Output>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		(
+			self.pipe,
+			TakeReducer::new(self.n),
+			TakeVecReducer::new(self.n),
+			TakeVecReducer::new(self.n),
+		)
+	}
+}
+
+/// Collects the first `n` rows, finishing as soon as it has them.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct TakeReducer<Item> {
+	n: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> Reducer<Item> for TakeReducer<Item> {
+	type Done = Vec<Item>;
+	type Async = TakeReducerAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		TakeReducerAsync(Vec::new(), self.n)
+	}
+}
+impl<Item> ReducerProcessSend<Item> for TakeReducer<Item>
+where
+	Item: ProcessSend + 'static,
+{
+	type Done = Vec<Item>;
+}
+impl<Item> ReducerSend<Item> for TakeReducer<Item>
+where
+	Item: Send + 'static,
+{
+	type Done = Vec<Item>;
+}
+
+/// Collects the first `n` rows of a stream of `Vec`s of rows, finishing as soon as it
+/// has them.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct TakeVecReducer<Item> {
+	n: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> Reducer<Vec<Item>> for TakeVecReducer<Item> {
+	type Done = Vec<Item>;
+	type Async = TakeReducerAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		TakeReducerAsync(Vec::new(), self.n)
+	}
+	fn ordered(&self) -> bool {
+		true
+	}
+}
+impl<Item> ReducerProcessSend<Vec<Item>> for TakeVecReducer<Item>
+where
+	Item: ProcessSend + 'static,
+{
+	type Done = Vec<Item>;
+}
+impl<Item> ReducerSend<Vec<Item>> for TakeVecReducer<Item>
+where
+	Item: Send + 'static,
+{
+	type Done = Vec<Item>;
+}
+
+#[pin_project]
+pub struct TakeReducerAsync<Item>(Vec<Item>, usize);
+
+impl<Item> Sink<Item> for TakeReducerAsync<Item> {
+	type Done = Vec<Item>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while self_.0.len() < *self_.1 {
+			if let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+				self_.0.push(item);
+			} else {
+				break;
+			}
+		}
+		Poll::Ready(mem::take(self_.0))
+	}
+}
+impl<Item> Sink<Vec<Item>> for TakeReducerAsync<Item> {
+	type Done = Vec<Item>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Vec<Item>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		while self_.0.len() < *self_.1 {
+			if let Some(items) = ready!(stream.as_mut().poll_next(cx)) {
+				self_.0.extend(items);
+			} else {
+				break;
+			}
+		}
+		self_.0.truncate(*self_.1);
+		Poll::Ready(mem::take(self_.0))
+	}
+}
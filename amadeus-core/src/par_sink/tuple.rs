@@ -212,6 +212,9 @@ macro_rules! impl_tuple {
 					ready: ($(None::<$t::Done>,)*),
 				}
 			}
+			fn ordered(&self) -> bool {
+				$(self.$t.ordered() ||)* false
+			}
 		}
 		impl<$($t: Reducer<$s>,)* $($s,)*> ReducerProcessSend<$enum<$($s,)*>> for $reducea<$($t,)*> where $($t::Done: ProcessSend + 'static,)* {
 			type Done = ($($t::Done,)*);
@@ -269,6 +272,9 @@ macro_rules! impl_tuple {
 					ready: ($(None::<$t::Done>,)*),
 				}
 			}
+			fn ordered(&self) -> bool {
+				$(self.$t.ordered() ||)* false
+			}
 		}
 		impl<$($t: ReducerProcessSend<$s>,)* $($s,)*> ReducerProcessSend<($($s,)*)> for $reduceb<$($t,)*> {
 			type Done = ($(<$t as ReducerProcessSend<$s>>::Done,)*);
@@ -0,0 +1,517 @@
+#![allow(clippy::type_complexity)]
+
+use educe::Educe;
+use futures::{pin_mut, ready, stream, Stream, StreamExt};
+use indexmap::IndexMap;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use serde_closure::traits;
+use std::{
+	collections::{BTreeMap, HashMap}, convert::TryFrom, marker::PhantomData, mem, ops, pin::Pin, task::{Context, Poll}, time::Duration, vec
+};
+
+use super::{
+	DistributedPipe, DistributedSink, GroupByReducerA, GroupByReducerAAsync, GroupByReducerB, ParallelPipe, ParallelSink, PipeTask, Reducer, ReducerProcessSend, ReducerSend
+};
+use crate::{
+	pipe::{Pipe, Sink}, pool::ProcessSend
+};
+
+/// A point in time that rows can be windowed by, like `amadeus_types::DateTime`.
+pub trait Timestamp {
+	/// The number of nanoseconds since the Unix epoch.
+	fn as_nanos(&self) -> i64;
+	/// The point in time `nanos` nanoseconds after the Unix epoch.
+	fn from_nanos(nanos: i64) -> Self;
+}
+
+/// Aggregates rows into windows of event time, as returned by
+/// [`tumbling_window`](ParallelPipe::tumbling_window),
+/// [`sliding_window`](ParallelPipe::sliding_window) and
+/// [`session_window`](ParallelPipe::session_window).
+///
+/// Each worker aggregates its rows into buckets of time, which are merged into windows
+/// at the end by the inner sink's reducers. For sliding windows a bucket is a window,
+/// and rows are cloned into each window they fall in.
+#[must_use]
+pub struct Window<P, F, S> {
+	pipe: P,
+	f: F,
+	windows: Windows,
+	sink: S,
+}
+impl<P, F, S> Window<P, F, S> {
+	pub(crate) fn tumbling(pipe: P, f: F, size: Duration, sink: S) -> Self {
+		Self::sliding(pipe, f, size, size, sink)
+	}
+	pub(crate) fn sliding(pipe: P, f: F, size: Duration, slide: Duration, sink: S) -> Self {
+		let (size, slide) = (nanos(size, "size"), nanos(slide, "slide"));
+		Self {
+			pipe,
+			f,
+			windows: Windows::Sliding { size, slide },
+			sink,
+		}
+	}
+	pub(crate) fn session(pipe: P, f: F, gap: Duration, sink: S) -> Self {
+		Self {
+			pipe,
+			f,
+			windows: Windows::Session {
+				gap: nanos(gap, "gap"),
+			},
+			sink,
+		}
+	}
+}
+/// Converts a window's `duration` to nanoseconds, panicking if it's zero or doesn't
+/// fit in an `i64`.
+fn nanos(duration: Duration, name: &str) -> i64 {
+	let nanos = i64::try_from(duration.as_nanos()).unwrap_or_else(|_| {
+		panic!(
+			"window {} of {:?} is too long, the most is i64::MAX nanoseconds (about 292 years)",
+			name, duration
+		)
+	});
+	assert_ne!(nanos, 0, "window {} must be greater than zero", name);
+	nanos
+}
+
+impl<P: ParallelPipe<Item, Output = U>, F, S: ParallelSink<U>, Item, T, U> ParallelSink<Item>
+	for Window<P, F, S>
+where
+	F: for<'a> traits::Fn<(&'a U,), Output = T> + Clone + Send + 'static,
+	T: Timestamp + Ord,
+	<S::Pipe as ParallelPipe<U>>::Task: Clone + Send + 'static,
+	S::ReduceA: Clone + Send + 'static,
+	S::ReduceC: Clone,
+	U: Clone,
+{
+	type Done = BTreeMap<T, S::Done>;
+	type Pipe = P;
+	type ReduceA = WindowReducerA<<S::Pipe as ParallelPipe<U>>::Task, S::ReduceA, F, U>;
+	type ReduceC = WindowReducerC<
+		S::ReduceC,
+		T,
+		<S::ReduceA as ReducerSend<<S::Pipe as ParallelPipe<U>>::Output>>::Done,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (a, b, c) = self.sink.reducers();
+		(
+			self.pipe,
+			WindowReducerA::new(a.task(), b, self.f, self.windows),
+			WindowReducerC::new(c, self.windows),
+		)
+	}
+}
+
+impl<P: DistributedPipe<Item, Output = U>, F, S: DistributedSink<U>, Item, T, U>
+	DistributedSink<Item> for Window<P, F, S>
+where
+	F: for<'a> traits::Fn<(&'a U,), Output = T> + Clone + ProcessSend + 'static,
+	T: Timestamp + Ord,
+	<S::Pipe as DistributedPipe<U>>::Task: Clone + ProcessSend + 'static,
+	S::ReduceA: Clone + ProcessSend + 'static,
+	S::ReduceB: Clone,
+	S::ReduceC: Clone,
+	U: Clone,
+{
+	type Done = BTreeMap<T, S::Done>;
+	type Pipe = P;
+	type ReduceA = WindowReducerA<<S::Pipe as DistributedPipe<U>>::Task, S::ReduceA, F, U>;
+	type ReduceB = GroupByReducerB<
+		BucketReducer<S::ReduceB>,
+		i64,
+		Bucket<<S::ReduceA as ReducerSend<<S::Pipe as DistributedPipe<U>>::Output>>::Done>,
+	>;
+	type ReduceC = WindowReducerC<
+		S::ReduceC,
+		T,
+		<S::ReduceB as ReducerProcessSend<
+			<S::ReduceA as Reducer<<S::Pipe as DistributedPipe<U>>::Output>>::Done,
+		>>::Done,
+	>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (a, b, c, d) = self.sink.reducers();
+		(
+			self.pipe,
+			WindowReducerA::new(a.task(), b, self.f, self.windows),
+			GroupByReducerB::new(BucketReducer(c)),
+			WindowReducerC::new(d, self.windows),
+		)
+	}
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+enum Windows {
+	/// Windows of `size` nanoseconds starting every `slide` nanoseconds.
+	Sliding { size: i64, slide: i64 },
+	/// Runs of rows with less than `gap` nanoseconds between each.
+	Session { gap: i64 },
+}
+impl Windows {
+	/// The buckets a row at `time` is aggregated into. Rows in the same bucket are
+	/// always in the same window or session.
+	fn buckets(self, time: i64) -> ops::RangeInclusive<i64> {
+		match self {
+			// The windows [start, start + size) that contain time, with start a multiple of
+			// slide
+			Self::Sliding { size, slide } => {
+				time.saturating_sub(size).div_euclid(slide) + 1..=time.div_euclid(slide)
+			}
+			Self::Session { gap } => {
+				let bucket = time.div_euclid(gap);
+				bucket..=bucket
+			}
+		}
+	}
+	/// Assigns buckets to windows, returning the start of each window along with the
+	/// aggregates of its buckets.
+	fn assign<A>(self, buckets: BTreeMap<i64, Bucket<Vec<A>>>) -> Vec<(i64, Vec<A>)> {
+		match self {
+			Self::Sliding { slide, .. } => buckets
+				.into_iter()
+				.map(|(bucket, Bucket { value, .. })| (bucket * slide, value))
+				.collect(),
+			Self::Session { gap } => {
+				let mut windows = Vec::<(i64, i64, Vec<A>)>::new();
+				for (_, Bucket { min, max, value }) in buckets {
+					match windows.last_mut() {
+						Some((_, end, values)) if min - *end < gap => {
+							*end = max;
+							values.extend(value);
+						}
+						_ => windows.push((min, max, value)),
+					}
+				}
+				windows
+					.into_iter()
+					.map(|(start, _, values)| (start, values))
+					.collect()
+			}
+		}
+	}
+}
+
+/// The aggregate of the rows in a bucket of time, along with the earliest and latest
+/// of them in nanoseconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bucket<A> {
+	min: i64,
+	max: i64,
+	value: A,
+}
+
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "P: Clone, R: Clone, F: Clone"))]
+#[serde(
+	bound(serialize = "P: Serialize, R: Serialize, F: Serialize"),
+	bound(deserialize = "P: Deserialize<'de>, R: Deserialize<'de>, F: Deserialize<'de>")
+)]
+pub struct WindowReducerA<P, R, F, U> {
+	inner: GroupByReducerA<P, R, i64, U>,
+	f: F,
+	windows: Windows,
+}
+impl<P, R, F, U> WindowReducerA<P, R, F, U> {
+	fn new(pipe: P, reducer: R, f: F, windows: Windows) -> Self {
+		Self {
+			inner: GroupByReducerA::new(pipe, reducer),
+			f,
+			windows,
+		}
+	}
+}
+impl<P, R, F, T, U> Reducer<U> for WindowReducerA<P, R, F, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	F: for<'a> traits::Fn<(&'a U,), Output = T>,
+	T: Timestamp,
+	U: Clone,
+{
+	type Done = IndexMap<i64, Bucket<R::Done>>;
+	type Async = WindowReducerAAsync<P::Async, R, F, U>;
+
+	fn into_async(self) -> Self::Async {
+		WindowReducerAAsync {
+			inner: self.inner.into_async(),
+			f: self.f,
+			windows: self.windows,
+			bounds: HashMap::new(),
+		}
+	}
+}
+impl<P, R, F, T, U> ReducerProcessSend<U> for WindowReducerA<P, R, F, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	F: for<'a> traits::Fn<(&'a U,), Output = T>,
+	T: Timestamp,
+	U: Clone,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = IndexMap<i64, Bucket<R::Done>>;
+}
+impl<P, R, F, T, U> ReducerSend<U> for WindowReducerA<P, R, F, U>
+where
+	P: PipeTask<U>,
+	R: Reducer<P::Output> + Clone,
+	F: for<'a> traits::Fn<(&'a U,), Output = T>,
+	T: Timestamp,
+	U: Clone,
+	R::Done: Send + 'static,
+{
+	type Done = IndexMap<i64, Bucket<R::Done>>;
+}
+
+#[pin_project]
+pub struct WindowReducerAAsync<P, R, F, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output>,
+{
+	#[pin]
+	inner: GroupByReducerAAsync<P, R, i64, U>,
+	f: F,
+	windows: Windows,
+	bounds: HashMap<i64, (i64, i64)>,
+}
+impl<P, R, F, T, U> Sink<U> for WindowReducerAAsync<P, R, F, U>
+where
+	P: Pipe<U>,
+	R: Reducer<P::Output> + Clone,
+	F: for<'a> traits::Fn<(&'a U,), Output = T>,
+	T: Timestamp,
+	U: Clone,
+{
+	type Done = IndexMap<i64, Bucket<R::Done>>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = U>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let done = {
+			let (f, windows, bounds) = (&*self_.f, *self_.windows, &mut *self_.bounds);
+			let stream = stream.as_mut().flat_map(|row| {
+				let time = f.call((&row,)).as_nanos();
+				let buckets = windows.buckets(time);
+				let mut rows = Vec::with_capacity(buckets.size_hint().0);
+				for bucket in buckets {
+					let (min, max) = bounds.entry(bucket).or_insert((time, time));
+					*min = (*min).min(time);
+					*max = (*max).max(time);
+					rows.push(bucket);
+				}
+				let last = rows.pop().map(|bucket| (bucket, row.clone()));
+				stream::iter(
+					rows.into_iter()
+						.map(move |bucket| (bucket, row.clone()))
+						.chain(last),
+				)
+			});
+			pin_mut!(stream);
+			ready!(self_.inner.poll_forward(cx, stream))
+		};
+		let bounds = &*self_.bounds;
+		Poll::Ready(
+			done.into_iter()
+				.map(|(bucket, value)| {
+					let (min, max) = bounds[&bucket];
+					(bucket, Bucket { min, max, value })
+				})
+				.collect(),
+		)
+	}
+}
+
+/// Reduces the aggregates of a bucket from several workers, keeping track of the
+/// earliest and latest rows.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BucketReducer<R>(R);
+
+impl<R, A> Reducer<Bucket<A>> for BucketReducer<R>
+where
+	R: Reducer<A>,
+{
+	type Done = Bucket<R::Done>;
+	type Async = BucketReducerAsync<R::Async>;
+
+	fn into_async(self) -> Self::Async {
+		BucketReducerAsync {
+			inner: self.0.into_async(),
+			bounds: None,
+		}
+	}
+}
+impl<R, A> ReducerProcessSend<Bucket<A>> for BucketReducer<R>
+where
+	R: Reducer<A>,
+	R::Done: ProcessSend + 'static,
+{
+	type Done = Bucket<R::Done>;
+}
+impl<R, A> ReducerSend<Bucket<A>> for BucketReducer<R>
+where
+	R: Reducer<A>,
+	R::Done: Send + 'static,
+{
+	type Done = Bucket<R::Done>;
+}
+
+#[pin_project]
+pub struct BucketReducerAsync<R> {
+	#[pin]
+	inner: R,
+	bounds: Option<(i64, i64)>,
+}
+impl<R, A> Sink<Bucket<A>> for BucketReducerAsync<R>
+where
+	R: Sink<A>,
+{
+	type Done = Bucket<R::Done>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Bucket<A>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let value = {
+			let bounds = &mut *self_.bounds;
+			let stream = stream.as_mut().map(|Bucket { min, max, value }| {
+				let (min_, max_) = bounds.get_or_insert((min, max));
+				*min_ = (*min_).min(min);
+				*max_ = (*max_).max(max);
+				value
+			});
+			pin_mut!(stream);
+			ready!(self_.inner.poll_forward(cx, stream))
+		};
+		let (min, max) = self_.bounds.unwrap();
+		Poll::Ready(Bucket { min, max, value })
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "R: Clone"))]
+#[serde(
+	bound(serialize = "R: Serialize"),
+	bound(deserialize = "R: Deserialize<'de>")
+)]
+pub struct WindowReducerC<R, T, A> {
+	reducer: R,
+	windows: Windows,
+	marker: PhantomData<fn() -> (T, A)>,
+}
+impl<R, T, A> WindowReducerC<R, T, A> {
+	fn new(reducer: R, windows: Windows) -> Self {
+		Self {
+			reducer,
+			windows,
+			marker: PhantomData,
+		}
+	}
+}
+impl<R, T, A> Reducer<IndexMap<i64, Bucket<A>>> for WindowReducerC<R, T, A>
+where
+	R: Reducer<A> + Clone,
+	T: Timestamp + Ord,
+{
+	type Done = BTreeMap<T, R::Done>;
+	type Async = WindowReducerCAsync<R, T, A>;
+
+	fn into_async(self) -> Self::Async {
+		WindowReducerCAsync {
+			reducer: self.reducer,
+			windows: self.windows,
+			buckets: BTreeMap::new(),
+			pending: None,
+			marker: PhantomData,
+		}
+	}
+}
+
+#[pin_project]
+pub struct WindowReducerCAsync<R, T, A>
+where
+	R: Reducer<A>,
+{
+	reducer: R,
+	windows: Windows,
+	buckets: BTreeMap<i64, Bucket<Vec<A>>>,
+	pending: Option<
+		Vec<(
+			i64,
+			Pin<Box<R::Async>>,
+			stream::Iter<vec::IntoIter<A>>,
+			Option<R::Done>,
+		)>,
+	>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<R, T, A> Sink<IndexMap<i64, Bucket<A>>> for WindowReducerCAsync<R, T, A>
+where
+	R: Reducer<A> + Clone,
+	T: Timestamp + Ord,
+{
+	type Done = BTreeMap<T, R::Done>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = IndexMap<i64, Bucket<A>>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		if self_.pending.is_none() {
+			while let Some(buckets) = ready!(stream.as_mut().poll_next(cx)) {
+				for (bucket, Bucket { min, max, value }) in buckets {
+					let bucket = self_.buckets.entry(bucket).or_insert_with(|| Bucket {
+						min,
+						max,
+						value: Vec::new(),
+					});
+					bucket.min = bucket.min.min(min);
+					bucket.max = bucket.max.max(max);
+					bucket.value.push(value);
+				}
+			}
+			let reducer = &*self_.reducer;
+			*self_.pending = Some(
+				self_
+					.windows
+					.assign(mem::take(self_.buckets))
+					.into_iter()
+					.map(|(start, values)| {
+						let reducer = Box::pin(reducer.clone().into_async());
+						(start, reducer, stream::iter(values), None)
+					})
+					.collect(),
+			);
+		}
+		let mut done = true;
+		for (_, reducer, values, done_) in self_.pending.as_mut().unwrap() {
+			if done_.is_none() {
+				if let Poll::Ready(done__) = reducer.as_mut().poll_forward(cx, Pin::new(values)) {
+					*done_ = Some(done__);
+				} else {
+					done = false;
+				}
+			}
+		}
+		if !done {
+			return Poll::Pending;
+		}
+		Poll::Ready(
+			self_
+				.pending
+				.take()
+				.unwrap()
+				.into_iter()
+				.map(|(start, _, _, done)| (T::from_nanos(start), done.unwrap()))
+				.collect(),
+		)
+	}
+}
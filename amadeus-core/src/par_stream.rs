@@ -3,24 +3,32 @@
 
 #![allow(clippy::too_many_lines, unused_qualifications)]
 
+mod cancel;
 mod chain;
+mod checkpoint;
 mod cloned;
+mod dedup;
 mod filter;
 mod filter_map_sync;
 mod flat_map;
 mod flat_map_sync;
+mod grouped;
+mod hash_join;
 mod identity;
 mod inspect;
 mod join;
 mod map;
 mod map_sync;
+mod progress;
+mod repartition;
 mod sum_type;
 mod update;
 
 use async_trait::async_trait;
 use either::Either;
-use futures::{future, pin_mut, stream::StreamExt as _, Stream};
+use futures::{future, pin_mut, stream::StreamExt as _, FutureExt as _, Stream};
 use indexmap::IndexMap;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_closure::{traits, FnOnce};
 use std::{
 	cmp::Ordering, hash::Hash, iter, ops, pin::Pin, task::{Context, Poll}, vec
@@ -28,11 +36,11 @@ use std::{
 
 use super::{par_pipe::*, par_sink::*};
 use crate::{
-	into_par_stream::{IntoDistributedStream, IntoParallelStream}, pipe::{Sink, StreamExt}, pool::{ProcessPool, ProcessSend, ThreadPool}
+	into_par_stream::{IntoDistributedStream, IntoParallelStream}, pipe::{Sink, StreamExt}, pool::{self, Job, ProcessPool, ProcessSend, ThreadPool}, util::{InOrder, IoError}
 };
 
 pub use self::{
-	chain::*, cloned::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, identity::*, inspect::*, join::*, map::*, map_sync::*, update::*
+	cancel::*, chain::*, cloned::*, dedup::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, grouped::*, hash_join::*, identity::*, inspect::*, join::*, map::*, map_sync::*, progress::*, repartition::*, update::*
 };
 
 #[must_use]
@@ -103,6 +111,17 @@ macro_rules! stream {
 				$assert_stream(Filter::new(self, f))
 			}
 
+			/// Drops the errors of a stream of `Result`s, keeping the `Ok` rows.
+			#[inline]
+			fn skip_errors<T, E>(self) -> FilterMapSync<Self, SkipErrors<T, E>>
+			where
+				T: 'static,
+				E: 'static,
+				Self: $stream<Item = Result<T, E>> + Sized,
+			{
+				$assert_stream(FilterMapSync::new(self, SkipErrors::new()))
+			}
+
 			#[inline]
 			fn left_join<K, V1, V2>(self, right: impl IntoIterator<Item = (K, V2)>) -> LeftJoin<Self, K, V1, V2>
 			where
@@ -125,6 +144,129 @@ macro_rules! stream {
 				$assert_stream(InnerJoin::new(self, right.into_iter().collect()))
 			}
 
+			/// Joins this stream of `(key, value)` rows with another, by hash-partitioning
+			/// both into `partitions` partitions and joining each partition on a worker.
+			/// Unlike [`left_join`](Self::left_join) and [`inner_join`](Self::inner_join),
+			/// neither side needs to fit in memory, only each partition.
+			///
+			/// See [`Shuffle`] for where rows are spilled, and how spilling fails.
+			///
+			/// # Panics
+			///
+			/// If `partitions` is 0.
+			#[inline]
+			async fn hash_join<P, R, K, V1, V2>(
+				self, pool: &P, right: R, kind: JoinKind, partitions: usize,
+			) -> Result<HashJoin<K, V1, V2>, IoError>
+			where
+				P: $pool,
+				R: $into_stream<Item = (K, V2)>,
+				<R::$xxx as $stream>::Task: 'static,
+				K: Eq + Hash + Serialize + DeserializeOwned + $send + 'static,
+				V1: Serialize + DeserializeOwned + $send + 'static,
+				V2: Serialize + DeserializeOwned + $send + 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = (K, V1)> + Sized,
+			{
+				assert_ne!(partitions, 0, "hash_join needs at least 1 partition");
+				let (left, right) = future::join(
+					self.pipe(pool, Shuffle::new(Identity, partitions)),
+					right
+						.$into_stream_fn()
+						.pipe(pool, Shuffle::new(Identity, partitions)),
+				)
+				.await;
+				HashJoin::new(left, right, partitions, kind)
+			}
+
+			/// Hash-partitions the rows of this stream into `partitions` partitions by the
+			/// key `f` gives them, returning a stream with a task for each partition. All
+			/// the rows with a given key are then streamed by the same worker. Rows are
+			/// spilled to disk in between, so needn't fit in memory.
+			///
+			/// See [`Shuffle`] for where rows are spilled, and how spilling fails.
+			///
+			/// # Panics
+			///
+			/// If `partitions` is 0.
+			#[inline]
+			async fn repartition_by<P, F, K>(
+				self, pool: &P, f: F, partitions: usize,
+			) -> Result<Repartition<Self::Item>, IoError>
+			where
+				P: $pool,
+				F: $fns::FnMut(&Self::Item) -> K + Clone + $send + 'static,
+				K: Hash,
+				Self::Item: Serialize + DeserializeOwned + $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(partitions, 0, "repartition_by needs at least 1 partition");
+				let files = self
+					.pipe(pool, ShuffleBy::new(Identity, f, partitions))
+					.await?;
+				Ok(Repartition::new(files, partitions))
+			}
+
+			/// Removes duplicate rows, by hash-partitioning the rows into `partitions`
+			/// partitions and removing the duplicates within each on a worker. Rows are
+			/// spilled to disk in between, so only the distinct rows of each partition
+			/// need fit in memory.
+			///
+			/// See [`Shuffle`] for where rows are spilled, and how spilling fails.
+			///
+			/// # Panics
+			///
+			/// If `partitions` is 0.
+			#[inline]
+			async fn distinct<P>(
+				self, pool: &P, partitions: usize,
+			) -> Result<Dedup<Self::Item, DistinctKey<Self::Item>>, IoError>
+			where
+				P: $pool,
+				Self::Item: Eq + Hash + Clone + Serialize + DeserializeOwned + $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(partitions, 0, "distinct needs at least 1 partition");
+				let files = self
+					.pipe(pool, ShuffleBy::new(Identity, DistinctKey::new(), partitions))
+					.await?;
+				Ok(Dedup::new(
+					Repartition::new(files, partitions),
+					DistinctKey::new(),
+				))
+			}
+
+			/// Removes rows with duplicate keys, by hash-partitioning the rows into
+			/// `partitions` partitions by the key `f` gives them and removing the
+			/// duplicates within each on a worker. Rows are spilled to disk in between, so
+			/// only the distinct keys of each partition need fit in memory.
+			///
+			/// See [`Shuffle`] for where rows are spilled, and how spilling fails.
+			///
+			/// # Panics
+			///
+			/// If `partitions` is 0.
+			#[inline]
+			async fn dedup_by_key<P, F, K>(
+				self, pool: &P, f: F, partitions: usize,
+			) -> Result<Dedup<Self::Item, F>, IoError>
+			where
+				P: $pool,
+				F: $fns::FnMut(&Self::Item) -> K + Clone + $send + 'static,
+				K: Eq + Hash,
+				Self::Item: Serialize + DeserializeOwned + $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(partitions, 0, "dedup_by_key needs at least 1 partition");
+				let files = self
+					.pipe(pool, ShuffleBy::new(Identity, f.clone(), partitions))
+					.await?;
+				Ok(Dedup::new(Repartition::new(files, partitions), f))
+			}
+
 			#[inline]
 			fn chain<C>(self, chain: C) -> Chain<Self, C::$xxx>
 			where
@@ -134,6 +276,29 @@ macro_rules! stream {
 				$assert_stream(Chain::new(self, chain.$into_stream_fn()))
 			}
 
+			/// Stops this stream once `cancel` is cancelled: no more tasks are handed out,
+			/// and the tasks in flight stop streaming rows. Await the result of `pipe`,
+			/// `reduce` or `for_each` with [`Cancel::run`] to find whether it was
+			/// cancelled.
+			#[inline]
+			fn with_cancel(self, cancel: &Cancel) -> WithCancel<Self>
+			where
+				Self: Sized,
+			{
+				$assert_stream(WithCancel::new(self, cancel.clone()))
+			}
+
+			/// Reports the progress of this stream to `progress`: the tasks dispatched,
+			/// completed and failed, the rows and bytes each processed, and an estimate of
+			/// the time remaining.
+			#[inline]
+			fn with_progress(self, progress: &Progress) -> WithProgress<Self>
+			where
+				Self: Sized,
+			{
+				$assert_stream(WithProgress::new(self, progress.clone()))
+			}
+
 			#[inline]
 			async fn for_each<P, F>(self, pool: &P, f: F)
 			where
@@ -190,6 +355,40 @@ macro_rules! stream {
 					.await
 			}
 
+			/// Sorts the stream into ranges of about `buffer` rows, each sorted by a
+			/// worker. Rows are spilled to disk in between, so only `buffer` rows per
+			/// worker need fit in memory. See [`Sorted`] for how the rows are sorted, and
+			/// [`Shuffle`] for where rows are spilled, and how spilling fails.
+			///
+			/// # Panics
+			///
+			/// If `buffer` is 0.
+			#[inline]
+			async fn sort_by<P, F>(
+				self, pool: &P, buffer: usize, cmp: F,
+			) -> Result<Sorted<Self::Item, F>, IoError>
+			where
+				P: $pool,
+				F: $fns::Fn(&Self::Item, &Self::Item) -> Ordering + Clone + $send + 'static,
+				Self::Item: Clone + Serialize + DeserializeOwned + $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				assert_ne!(buffer, 0, "sort_by needs a buffer of at least 1 row");
+				let samples = self.pipe(pool, SortSample::new(Identity)).await?;
+				let len = samples.len();
+				let (files, bounds) = samples.into_bounds(buffer, &cmp);
+				let ranges = bounds.len() + 1;
+				let partitions = files.len();
+				let files = $stream::pipe(
+					Repartition::new(files, partitions),
+					pool,
+					SortShuffle::new(Identity, bounds, cmp.clone()),
+				)
+				.await?;
+				Ok(Sorted::new(files, ranges, len, cmp, buffer))
+			}
+
 			#[inline]
 			async fn count<P>(self, pool: &P) -> usize
 			where
@@ -202,6 +401,20 @@ macro_rules! stream {
 					.await
 			}
 
+			/// Estimates the number of distinct items with a
+			/// [`HyperLogLog`](amadeus_streaming::HyperLogLog) of the given `error_rate`.
+			#[inline]
+			async fn count_distinct_approx<P>(self, pool: &P, error_rate: f64) -> f64
+			where
+				P: $pool,
+				Self::Item: Hash + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::count_distinct_approx(Identity, error_rate))
+					.await
+			}
+
 			#[inline]
 			async fn sum<P, S>(self, pool: &P) -> S
 			where
@@ -239,6 +452,21 @@ macro_rules! stream {
 				.await
 			}
 
+			/// Estimates the given quantiles, each between `0.0` and `1.0`, with a
+			/// [`TDigest`](amadeus_streaming::TDigest). Returns `None`s if there are no
+			/// rows.
+			#[inline]
+			async fn quantiles<P>(self, pool: &P, quantiles: &[f64]) -> Vec<Option<f64>>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::quantiles(Identity, quantiles))
+				.await
+			}
+
 			#[inline]
 			async fn combine<P, F>(self, pool: &P, f: F) -> Option<Self::Item>
 			where
@@ -413,6 +641,79 @@ macro_rules! stream {
 				self.pipe(pool, $pipe::<Self::Item>::any(Identity, f))
 					.await
 			}
+
+			/// Collects the first `n` items, in the order [`collect`](Self::collect) would
+			/// return them. Each worker stops once it has `n` items, and tasks yet to
+			/// finish are cancelled once the first `n` items have arrived.
+			#[inline]
+			async fn take<P>(self, pool: &P, n: usize) -> Vec<Self::Item>
+			where
+				P: $pool,
+				Self::Item: $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::take(Identity, n))
+					.await
+			}
+
+			/// Like [`take`](Self::take), but returns the items as a stream so they can be
+			/// fed to further sinks.
+			#[inline]
+			async fn limit<P>(
+				self, pool: &P, n: usize,
+			) -> <Vec<Self::Item> as $into_stream>::$xxx
+			where
+				P: $pool,
+				Self::Item: $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.take(pool, n).await.$into_stream_fn()
+			}
+
+			/// Returns the first item, in the order [`collect`](Self::collect) would return
+			/// them. Work stops once it has arrived.
+			#[inline]
+			async fn first<P>(self, pool: &P) -> Option<Self::Item>
+			where
+				P: $pool,
+				Self::Item: $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.take(pool, 1).await.pop()
+			}
+
+			/// Returns the first item that `f` returns `true` for, in the order
+			/// [`collect`](Self::collect) would return them. Work stops once it has
+			/// arrived.
+			#[inline]
+			async fn find<P, F>(self, pool: &P, f: F) -> Option<Self::Item>
+			where
+				P: $pool,
+				F: $fns::FnMut(&Self::Item) -> bool + Clone + $send + 'static,
+				Self::Item: $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				self.filter(f).first(pool).await
+			}
+
+			/// Returns the first `Some` that `f` returns, in the order
+			/// [`collect`](Self::collect) would return them. Work stops once it has
+			/// arrived.
+			#[inline]
+			async fn find_map<P, F, B>(self, pool: &P, f: F) -> Option<B>
+			where
+				P: $pool,
+				F: $fns::FnMut(Self::Item) -> Option<B> + Clone + $send + 'static,
+				B: $send + 'static,
+				Self::Task: 'static,
+				Self: Sized,
+			{
+				$assert_stream(FilterMapSync::new(self, f)).first(pool).await
+			}
 		}
 
 		#[inline(always)]
@@ -475,10 +776,12 @@ stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallel
 			);
 		}
 
+		let ordered = reduce_c.ordered();
 		let handles = tasks
 			.into_iter()
 			.filter(|tasks| !tasks.is_empty())
-			.map(|tasks| {
+			.enumerate()
+			.map(|(i, tasks)| {
 				let reduce_a = reduce_a.clone();
 				pool.spawn(move || async move {
 					let sink = reduce_a.into_async();
@@ -492,9 +795,10 @@ stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallel
 					}
 					sink.done().await
 				})
+				.map(move |item| (i, item))
 			})
 			.collect::<futures::stream::FuturesUnordered<_>>();
-		let stream = handles.map(|item| {
+		let stream = InOrder::new(handles, ordered).map(|item| {
 			item.unwrap_or_else(|err| panic!("Amadeus: task '<unnamed>' panicked at '{}'", err))
 		});
 		let reduce_c = reduce_c.into_async();
@@ -563,6 +867,92 @@ stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallel
 			.await
 	}
 
+	/// Groups the `(key, value)` rows of this stream by key like `group_by`, except that
+	/// each worker holds the partial aggregates of at most `max_keys` distinct keys in
+	/// memory, however large each is, beyond which it spills them to disk,
+	/// hash-partitioned by key into `partitions` partitions. The groups are then merged
+	/// and streamed a partition at a time, rather than collected into one `IndexMap`.
+	///
+	/// See [`Shuffle`] for where partial aggregates are spilled, and how spilling
+	/// fails.
+	///
+	/// # Panics
+	///
+	/// If `partitions` is 0.
+	async fn group_by_spilling<P, S, A, B>(
+		self, pool: &P, sink: S, max_keys: usize, partitions: usize,
+	) -> Result<
+		Grouped<A, <S::ReduceA as ReducerSend<<S::Pipe as ParallelPipe<B>>::Output>>::Done, S::ReduceC>,
+		IoError,
+	>
+	where
+		P: ThreadPool,
+		A: Eq + Hash + Serialize + DeserializeOwned + Send + 'static,
+		B: 'static,
+		S: ParallelSink<B>,
+		<S::Pipe as ParallelPipe<B>>::Task: Clone + Send + 'static,
+		S::ReduceA: 'static,
+		<S::ReduceA as ReducerSend<<S::Pipe as ParallelPipe<B>>::Output>>::Done:
+			Serialize + DeserializeOwned,
+		S::ReduceC: Clone + Send + 'static,
+		Self::Task: 'static,
+		Self: ParallelStream<Item = (A, B)> + Sized,
+	{
+		assert_ne!(partitions, 0, "group_by_spilling needs at least 1 partition");
+		let (pipe, reduce_a, reduce_c) = sink.reducers();
+		let files = self
+			.pipe(
+				pool,
+				GroupBySpill::new(
+					Identity,
+					pipe.task(),
+					reduce_a,
+					OnceReducer::new(),
+					max_keys,
+					partitions,
+				),
+			)
+			.await?;
+		Ok(Grouped::new(Repartition::new(files, partitions), reduce_c))
+	}
+
+	/// Counts the errors of a stream of `Result`s, keeping the first `limit` of them,
+	/// and sends the `Ok` rows to `sink`.
+	async fn collect_errors<P, S, T, E>(
+		self, pool: &P, limit: usize, sink: S,
+	) -> (S::Done, Errors<E>)
+	where
+		P: ThreadPool,
+		S: ParallelSink<T>,
+		<S::Pipe as ParallelPipe<T>>::Task: 'static,
+		S::ReduceA: 'static,
+		T: 'static,
+		E: Clone + Send + 'static,
+		Self::Task: 'static,
+		Self: ParallelStream<Item = Result<T, E>> + Sized,
+	{
+		self.pipe(pool, ParallelPipe::<Self::Item>::collect_errors(Identity, limit, sink))
+			.await
+	}
+
+	/// Sends the `Ok` rows of a stream of `Result`s to `sink`, failing with the count
+	/// and first `n + 1` errors if there are more than `n` errors. It fails as soon as
+	/// the tasks finished so far have more than `n` errors, so the count is of those.
+	async fn fail_after<P, S, T, E>(self, pool: &P, n: usize, sink: S) -> Result<S::Done, Errors<E>>
+	where
+		P: ThreadPool,
+		S: ParallelSink<T>,
+		<S::Pipe as ParallelPipe<T>>::Task: 'static,
+		S::ReduceA: 'static,
+		T: 'static,
+		E: Clone + Send + 'static,
+		Self::Task: 'static,
+		Self: ParallelStream<Item = Result<T, E>> + Sized,
+	{
+		self.pipe(pool, ParallelPipe::<Self::Item>::fail_after(Identity, n, sink))
+			.await
+	}
+
 	async fn collect<P, B>(self, pool: &P) -> B
 	where
 		P: ThreadPool,
@@ -596,6 +986,13 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 	{
 		let self_ = self;
 		pin_mut!(self_);
+		if let Some(checkpoint) = pool.checkpoint() {
+			let mut tasks = Vec::new();
+			while let Some(task) = future::poll_fn(|cx| self_.as_mut().next_task(cx)).await {
+				tasks.push(task);
+			}
+			return checkpoint::reduce(pool, checkpoint, tasks, reduce_a, reduce_b, reduce_c).await;
+		}
 		// TODO: don't buffer tasks before sending. requires changes to ProcessPool
 		let mut tasks = (0..pool.processes()).map(|_| vec![]).collect::<Vec<_>>();
 		let mut allocated = 0;
@@ -638,13 +1035,17 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 			);
 		}
 
+		let policy = pool.retry_policy();
+		let job = Job::new(tasks.iter().filter(|tasks| !tasks.is_empty()).count());
+		let ordered = reduce_c.ordered();
 		let handles = tasks
 			.into_iter()
 			.filter(|tasks| !tasks.is_empty())
-			.map(|tasks| {
+			.enumerate()
+			.map(|(i, tasks)| {
 				let reduce_b = reduce_b.clone();
 				let reduce_a = reduce_a.clone();
-				pool.spawn(FnOnce!(move |pool: &P::ThreadPool| {
+				let work = FnOnce!(move |pool: &P::ThreadPool| {
 					let mut process_tasks = tasks.into_iter();
 
 					let mut tasks = (0..pool.threads()).map(|_| vec![]).collect::<Vec<_>>();
@@ -687,27 +1088,35 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 							tasks.iter().map(Vec::len).collect::<Vec<_>>()
 						);
 					}
+					let policy = pool.retry_policy();
+					let job = Job::new(tasks.iter().filter(|tasks| !tasks.is_empty()).count());
+					let ordered = reduce_b.ordered();
 					let handles = tasks
 						.into_iter()
 						.filter(|tasks| !tasks.is_empty())
-						.map(|tasks| {
+						.enumerate()
+						.map(|(i, tasks)| {
 							let reduce_a = reduce_a.clone();
-							pool.spawn(move || async move {
-								let sink = reduce_a.into_async();
-								pin_mut!(sink);
-								// this is faster than stream::iter(tasks.into_iter().map(StreamTask::into_async)).flatten().sink(sink).await
-								for task in tasks.into_iter().map(StreamTask::into_async) {
-										pin_mut!(task);
-										if let Some(ret) = sink.send_all(&mut task).await {
-												return ret;
-										}
-								}
-								sink.done().await
+							let pool = pool.clone();
+							pool::spawn_retrying(policy.clone(), job.clone(), (tasks, reduce_a), move |(tasks, reduce_a)| {
+								pool.spawn(move || async move {
+									let sink = reduce_a.into_async();
+									pin_mut!(sink);
+									// this is faster than stream::iter(tasks.into_iter().map(StreamTask::into_async)).flatten().sink(sink).await
+									for task in tasks.into_iter().map(StreamTask::into_async) {
+											pin_mut!(task);
+											if let Some(ret) = sink.send_all(&mut task).await {
+													return ret;
+											}
+									}
+									sink.done().await
+								})
 							})
+							.map(move |item| (i, item))
 						})
 						.collect::<futures::stream::FuturesUnordered<_>>();
 
-					let stream = handles.map(|item| {
+					let stream = InOrder::new(handles, ordered).map(|item| {
 						item.unwrap_or_else(|err| {
 							panic!("Amadeus: task '<unnamed>' panicked at '{}'", err)
 						})
@@ -717,10 +1126,12 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 						pin_mut!(reduce_b);
 						stream.sink(reduce_b).await
 					}
-				}))
+				});
+				pool::spawn_retrying(policy.clone(), job.clone(), work, |work| pool.spawn(work))
+					.map(move |item| (i, item))
 			})
 			.collect::<futures::stream::FuturesUnordered<_>>();
-		let stream = handles.map(|item| {
+		let stream = InOrder::new(handles, ordered).map(|item| {
 			item.unwrap_or_else(|err| panic!("Amadeus: task '<unnamed>' panicked at '{}'", err))
 		});
 		let reduce_c = reduce_c.into_async();
@@ -797,6 +1208,93 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 		.await
 	}
 
+	/// Groups the `(key, value)` rows of this stream by key like `group_by`, except that
+	/// each worker holds the partial aggregates of at most `max_keys` distinct keys in
+	/// memory, however large each is, beyond which it spills them to disk,
+	/// hash-partitioned by key into `partitions` partitions. The groups are then merged
+	/// and streamed a partition at a time, rather than collected into one `IndexMap` on
+	/// this process.
+	///
+	/// See [`Shuffle`] for where partial aggregates are spilled, and how spilling
+	/// fails.
+	///
+	/// # Panics
+	///
+	/// If `partitions` is 0.
+	async fn group_by_spilling<P, S, A, B>(
+		self, pool: &P, sink: S, max_keys: usize, partitions: usize,
+	) -> Result<
+		Grouped<
+			A,
+			<S::ReduceB as ReducerProcessSend<
+				<S::ReduceA as ReducerSend<<S::Pipe as DistributedPipe<B>>::Output>>::Done,
+			>>::Done,
+			S::ReduceC,
+		>,
+		IoError,
+	>
+	where
+		P: ProcessPool,
+		A: Eq + Hash + ProcessSend + 'static,
+		B: 'static,
+		S: DistributedSink<B>,
+		<S::Pipe as DistributedPipe<B>>::Task: Clone + ProcessSend + 'static,
+		S::ReduceA: 'static,
+		S::ReduceB: 'static,
+		S::ReduceC: Clone + ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: DistributedStream<Item = (A, B)> + Sized,
+	{
+		assert_ne!(partitions, 0, "group_by_spilling needs at least 1 partition");
+		let (pipe, reduce_a, reduce_b, reduce_c) = sink.reducers();
+		let files = self
+			.pipe(
+				pool,
+				GroupBySpill::new(Identity, pipe.task(), reduce_a, reduce_b, max_keys, partitions),
+			)
+			.await?;
+		Ok(Grouped::new(Repartition::new(files, partitions), reduce_c))
+	}
+
+	/// Counts the errors of a stream of `Result`s, keeping the first `limit` of them,
+	/// and sends the `Ok` rows to `sink`.
+	async fn collect_errors<P, S, T, E>(
+		self, pool: &P, limit: usize, sink: S,
+	) -> (S::Done, Errors<E>)
+	where
+		P: ProcessPool,
+		S: DistributedSink<T>,
+		<S::Pipe as DistributedPipe<T>>::Task: 'static,
+		S::ReduceA: 'static,
+		S::ReduceB: 'static,
+		T: 'static,
+		E: Clone + ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: DistributedStream<Item = Result<T, E>> + Sized,
+	{
+		self.pipe(pool, DistributedPipe::<Self::Item>::collect_errors(Identity, limit, sink))
+			.await
+	}
+
+	/// Sends the `Ok` rows of a stream of `Result`s to `sink`, failing with the count
+	/// and first `n + 1` errors if there are more than `n` errors. It fails as soon as
+	/// the tasks finished so far have more than `n` errors, so the count is of those.
+	async fn fail_after<P, S, T, E>(self, pool: &P, n: usize, sink: S) -> Result<S::Done, Errors<E>>
+	where
+		P: ProcessPool,
+		S: DistributedSink<T>,
+		<S::Pipe as DistributedPipe<T>>::Task: 'static,
+		S::ReduceA: 'static,
+		S::ReduceB: 'static,
+		T: 'static,
+		E: Clone + ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: DistributedStream<Item = Result<T, E>> + Sized,
+	{
+		self.pipe(pool, DistributedPipe::<Self::Item>::fail_after(Identity, n, sink))
+			.await
+	}
+
 	async fn collect<P, B>(self, pool: &P) -> B
 	where
 		P: ProcessPool,
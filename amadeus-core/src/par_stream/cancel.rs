@@ -0,0 +1,309 @@
+use derive_new::new;
+use futures::{future, pin_mut, task::AtomicWaker, Stream};
+use once_cell::sync::Lazy;
+use pin_project::pin_project;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	collections::HashMap, error, fmt, future::Future, pin::Pin, sync::{
+		atomic::{AtomicBool, Ordering}, Arc, Mutex, Weak
+	}, task::{Context, Poll}, time::{Duration, Instant, SystemTime}
+};
+use tokio::time::{delay_until, Delay};
+
+use super::{ParallelStream, StreamTask};
+
+/// A handle with which to cancel a stream, either explicitly with
+/// [`cancel`](Cancel::cancel) or once a deadline passes.
+///
+/// A stream wrapped with [`with_cancel`](ParallelStream::with_cancel) stops handing out
+/// tasks once cancelled, and its tasks already in flight stop streaming rows, so
+/// awaiting the stream's `pipe`, `reduce` or `for_each` finishes soon after. Wrap that
+/// future with [`run`](Cancel::run) to return as soon as the handle is cancelled.
+///
+/// Tasks sent to the processes of a [`ProcessPool`](crate::pool::ProcessPool) observe
+/// the deadline, and a later call to [`cancel`](Cancel::cancel) is forwarded to them by
+/// the pool.
+#[derive(Clone, Debug)]
+pub struct Cancel {
+	inner: Arc<Inner>,
+	deadline: Option<SystemTime>,
+}
+
+#[derive(Debug)]
+struct Inner {
+	id: u64,
+	cancelled: AtomicBool,
+	wakers: Mutex<Vec<Weak<AtomicWaker>>>,
+}
+
+/// The handles in this process by id, so that the tasks deserialized here share one,
+/// and cancellations forwarded from another process reach them.
+static HANDLES: Lazy<Mutex<HashMap<u64, Weak<Inner>>>> = Lazy::new(Default::default);
+
+/// The pools that cancellations are forwarded to.
+static FORWARD: Lazy<Mutex<Vec<Weak<dyn ForwardCancel>>>> = Lazy::new(Default::default);
+
+impl Inner {
+	fn get(id: u64, cancelled: bool) -> Arc<Self> {
+		let mut handles = HANDLES.lock().unwrap();
+		if let Some(inner) = handles.get(&id).and_then(Weak::upgrade) {
+			if cancelled {
+				inner.cancel();
+			}
+			return inner;
+		}
+		handles.retain(|_, inner| inner.strong_count() != 0);
+		let inner = Arc::new(Self {
+			id,
+			cancelled: AtomicBool::new(cancelled),
+			wakers: Mutex::new(Vec::new()),
+		});
+		let _ = handles.insert(id, Arc::downgrade(&inner));
+		inner
+	}
+	fn cancel(&self) {
+		self.cancelled.store(true, Ordering::SeqCst);
+		for waker in self.wakers.lock().unwrap().drain(..) {
+			if let Some(waker) = waker.upgrade() {
+				waker.wake();
+			}
+		}
+	}
+}
+
+impl Cancel {
+	/// Create a handle that is cancelled only by calling [`cancel`](Cancel::cancel).
+	pub fn new() -> Self {
+		Self {
+			inner: Inner::get(thread_rng().gen(), false),
+			deadline: None,
+		}
+	}
+
+	/// Create a handle that is cancelled once `timeout` has elapsed, or by calling
+	/// [`cancel`](Cancel::cancel).
+	pub fn with_timeout(timeout: Duration) -> Self {
+		Self {
+			inner: Inner::get(thread_rng().gen(), false),
+			deadline: Some(SystemTime::now() + timeout),
+		}
+	}
+
+	/// Cancel the streams this handle, or any clone of it, was given to.
+	pub fn cancel(&self) {
+		self.inner.cancel();
+		let pools = {
+			let mut forward = FORWARD.lock().unwrap();
+			forward.retain(|pool| pool.strong_count() != 0);
+			forward.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+		};
+		// Forwarded without holding the lock, so pools can register meanwhile
+		for pool in pools {
+			pool.forward_cancel(self.inner.id);
+		}
+	}
+
+	/// Returns `true` if [`cancel`](Cancel::cancel) has been called or the deadline has
+	/// passed.
+	pub fn is_cancelled(&self) -> bool {
+		if self.inner.cancelled.load(Ordering::SeqCst) {
+			return true;
+		}
+		match self.deadline {
+			Some(deadline) => SystemTime::now() >= deadline,
+			None => false,
+		}
+	}
+
+	/// Await `f`, returning [`Cancelled`] rather than its output if this handle is
+	/// cancelled first, or was cancelled by the time it finished, as its output is then
+	/// likely incomplete. `f` is dropped once the handle is cancelled.
+	pub async fn run<F: Future>(&self, f: F) -> Result<F::Output, Cancelled> {
+		let mut signal = Signal::new(self);
+		let cancelled = future::poll_fn(|cx| signal.poll(self, cx));
+		pin_mut!(f);
+		pin_mut!(cancelled);
+		match future::select(f, cancelled).await {
+			future::Either::Left((ret, _)) if !self.is_cancelled() => Ok(ret),
+			_ => Err(Cancelled),
+		}
+	}
+}
+
+impl Default for Cancel {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Serialize for Cancel {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		(
+			self.inner.id,
+			self.inner.cancelled.load(Ordering::SeqCst),
+			self.deadline,
+		)
+			.serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Cancel {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		<(u64, bool, Option<SystemTime>)>::deserialize(deserializer).map(
+			|(id, cancelled, deadline)| Self {
+				inner: Inner::get(id, cancelled),
+				deadline,
+			},
+		)
+	}
+}
+
+/// A pool that forwards cancellations to the processes that may be running the tasks
+/// of a cancelled stream. Implemented by `ProcessPool`.
+#[doc(hidden)]
+pub trait ForwardCancel: Send + Sync {
+	/// Cancel the handle with id `id` on each of this pool's processes, by calling
+	/// [`cancel_forwarded`] there. This is called by [`Cancel::cancel`], so shouldn't
+	/// block on the processes.
+	fn forward_cancel(self: Arc<Self>, id: u64);
+}
+
+/// Forward cancellations in this process to `pool`, for as long as it's alive.
+#[doc(hidden)]
+pub fn forward_cancels(pool: Weak<dyn ForwardCancel>) {
+	FORWARD.lock().unwrap().push(pool);
+}
+
+/// Cancel the handle with id `id` in this process, if there is one, as forwarded by a
+/// [`ForwardCancel`] pool.
+#[doc(hidden)]
+pub fn cancel_forwarded(id: u64) {
+	let inner = HANDLES.lock().unwrap().get(&id).and_then(Weak::upgrade);
+	if let Some(inner) = inner {
+		inner.cancel();
+	}
+}
+
+/// Wakes a task once a handle is cancelled, either by [`cancel`](Cancel::cancel) or by
+/// its deadline passing.
+struct Signal {
+	waker: Arc<AtomicWaker>,
+	delay: Option<Delay>,
+}
+impl Signal {
+	fn new(cancel: &Cancel) -> Self {
+		let waker = Arc::new(AtomicWaker::new());
+		let mut wakers = cancel.inner.wakers.lock().unwrap();
+		wakers.retain(|waker| waker.strong_count() != 0);
+		wakers.push(Arc::downgrade(&waker));
+		Self { waker, delay: None }
+	}
+	fn poll(&mut self, cancel: &Cancel, cx: &mut Context) -> Poll<()> {
+		self.waker.register(cx.waker());
+		if cancel.is_cancelled() {
+			return Poll::Ready(());
+		}
+		if let Some(deadline) = cancel.deadline {
+			let delay = self.delay.get_or_insert_with(|| {
+				let timeout = deadline
+					.duration_since(SystemTime::now())
+					.unwrap_or_default();
+				delay_until((Instant::now() + timeout).into())
+			});
+			if Pin::new(delay).poll(cx).is_ready() {
+				return Poll::Ready(());
+			}
+		}
+		Poll::Pending
+	}
+}
+
+/// The error returned by [`Cancel::run`] when the stream was cancelled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cancelled;
+impl error::Error for Cancelled {}
+impl fmt::Display for Cancelled {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("cancelled")
+	}
+}
+
+#[pin_project]
+#[derive(new)]
+#[must_use]
+pub struct WithCancel<S> {
+	#[pin]
+	stream: S,
+	cancel: Cancel,
+}
+
+impl_par_dist! {
+	impl<S: ParallelStream> ParallelStream for WithCancel<S> {
+		type Item = S::Item;
+		type Task = WithCancelTask<S::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			if !self.cancel.is_cancelled() {
+				self.stream.size_hint()
+			} else {
+				(0, Some(0))
+			}
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			if self_.cancel.is_cancelled() {
+				return Poll::Ready(None);
+			}
+			let cancel = self_.cancel;
+			self_.stream.next_task(cx).map(|task| {
+				task.map(|task| {
+					let cancel = cancel.clone();
+					WithCancelTask { task, cancel }
+				})
+			})
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WithCancelTask<T> {
+	task: T,
+	cancel: Cancel,
+}
+impl<T: StreamTask> StreamTask for WithCancelTask<T> {
+	type Item = T::Item;
+	type Async = WithCancelTaskAsync<T::Async>;
+
+	fn into_async(self) -> Self::Async {
+		WithCancelTaskAsync {
+			task: self.task.into_async(),
+			signal: Signal::new(&self.cancel),
+			cancel: self.cancel,
+		}
+	}
+}
+
+#[pin_project]
+pub struct WithCancelTaskAsync<T> {
+	#[pin]
+	task: T,
+	cancel: Cancel,
+	signal: Signal,
+}
+impl<T: Stream> Stream for WithCancelTaskAsync<T> {
+	type Item = T::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if self_.signal.poll(self_.cancel, cx).is_ready() {
+			return Poll::Ready(None);
+		}
+		self_.task.poll_next(cx)
+	}
+}
@@ -0,0 +1,154 @@
+use futures::{future, pin_mut, ready, stream, FutureExt, Stream, StreamExt as _};
+use serde_closure::FnOnce;
+use std::{any::type_name, iter, task::Poll};
+
+use super::StreamTask;
+use crate::{
+	par_sink::{Reducer, ReducerProcessSend, ReducerSend}, pipe::{Sink, StreamExt}, pool::{self, Checkpoint, Job, ProcessPool, ProcessSend, ThreadPool}
+};
+
+/// How many spawns a job is split into per process, and so roughly how much of it is
+/// lost if it fails.
+const SPAWNS_PER_PROCESS: usize = 8;
+
+/// Reduce `tasks` like [`DistributedStream::reduce`](super::DistributedStream::reduce),
+/// except that each task is reduced separately by `reduce_a` and `reduce_b`, so that its
+/// partial result can be recorded in `checkpoint`, and tasks with a recorded partial
+/// result aren't run again.
+#[cfg_attr(not(nightly), serde_closure::desugar)]
+pub(crate) async fn reduce<P, T, B, R1, R2, R3>(
+	pool: &P, checkpoint: Checkpoint, tasks: Vec<T>, reduce_a: R1, reduce_b: R2, reduce_c: R3,
+) -> B
+where
+	P: ProcessPool,
+	T: StreamTask + ProcessSend + 'static,
+	R1: ReducerSend<T::Item> + Clone + ProcessSend + 'static,
+	R2: ReducerProcessSend<<R1 as ReducerSend<T::Item>>::Done> + Clone + ProcessSend + 'static,
+	R3: Reducer<<R2 as ReducerProcessSend<<R1 as ReducerSend<T::Item>>::Done>>::Done, Done = B>,
+{
+	// A task whose key can't be serialized is run without being checkpointed
+	let job = bincode::serialize(&(
+		(type_name::<T>(), type_name::<R1>(), type_name::<R2>()),
+		&reduce_a,
+		&reduce_b,
+	))
+	.ok();
+	let mut partials = Vec::with_capacity(tasks.len());
+	let mut pending = Vec::new();
+	for task in tasks {
+		let key = job.as_ref().and_then(|job| {
+			let mut key = job.clone();
+			bincode::serialize_into(&mut key, &task).ok().map(|()| key)
+		});
+		let partial = key.as_ref().and_then(|key| checkpoint.load(key));
+		if partial.is_none() {
+			pending.push((key, task));
+		}
+		partials.push(partial);
+	}
+
+	let spawns = pool.processes() * SPAWNS_PER_PROCESS;
+	let chunk = ((pending.len() + spawns - 1) / spawns).max(1);
+	let mut pending = pending.into_iter();
+	let chunks = iter::from_fn(|| {
+		let (keys, tasks): (Vec<_>, Vec<_>) = pending.by_ref().take(chunk).unzip();
+		if !tasks.is_empty() {
+			Some((keys, tasks))
+		} else {
+			None
+		}
+	})
+	.collect::<Vec<_>>();
+
+	let policy = pool.retry_policy();
+	let job = Job::new(chunks.len());
+	let handles = chunks
+		.into_iter()
+		.map(|(keys, tasks)| {
+			let reduce_a = reduce_a.clone();
+			let reduce_b = reduce_b.clone();
+			let work = FnOnce!(move |pool: &P::ThreadPool| {
+				let chunk = (tasks.len() + pool.threads() - 1) / pool.threads();
+				let policy = pool.retry_policy();
+				let job = Job::new((tasks.len() + chunk - 1) / chunk);
+				let mut tasks = tasks.into_iter();
+				let handles = iter::from_fn(|| {
+					let tasks = tasks.by_ref().take(chunk).collect::<Vec<_>>();
+					if !tasks.is_empty() {
+						Some(tasks)
+					} else {
+						None
+					}
+				})
+				.map(|tasks| {
+					let work = (tasks, reduce_a.clone(), reduce_b.clone());
+					let pool = pool.clone();
+					pool::spawn_retrying(
+						policy.clone(),
+						job.clone(),
+						work,
+						move |(tasks, reduce_a, reduce_b)| {
+							pool.spawn(move || async move {
+								let mut partials = Vec::with_capacity(tasks.len());
+								for task in tasks.into_iter().map(StreamTask::into_async) {
+									pin_mut!(task);
+									let sink = reduce_a.clone().into_async();
+									pin_mut!(sink);
+									let done = match sink.send_all(&mut task).await {
+										Some(done) => done,
+										None => sink.done().await,
+									};
+									let reduce_b = reduce_b.clone().into_async();
+									pin_mut!(reduce_b);
+									partials.push(
+										stream::once(future::ready(done)).sink(reduce_b).await,
+									);
+								}
+								partials
+							})
+						},
+					)
+				})
+				.collect::<futures::stream::FuturesOrdered<_>>();
+				handles
+					.map(|item| {
+						item.unwrap_or_else(|err| {
+							panic!("Amadeus: task '<unnamed>' panicked at '{}'", err)
+						})
+					})
+					.concat()
+			});
+			let checkpoint = checkpoint.clone();
+			pool::spawn_retrying(policy.clone(), job.clone(), work, |work| pool.spawn(work)).map(
+				move |partials| {
+					let partials = partials.unwrap_or_else(|err| {
+						panic!("Amadeus: task '<unnamed>' panicked at '{}'", err)
+					});
+					for (key, partial) in keys.iter().zip(&partials) {
+						// a partial result that isn't recorded is just recomputed next time
+						if let Some(key) = key {
+							let _ = checkpoint.store(key, partial);
+						}
+					}
+					stream::iter(partials)
+				},
+			)
+		})
+		.collect::<futures::stream::FuturesOrdered<_>>()
+		.flatten();
+	pin_mut!(handles);
+
+	let mut partials = partials.into_iter().peekable();
+	let stream = stream::poll_fn(|cx| match partials.peek() {
+		Some(Some(_)) => Poll::Ready(partials.next().unwrap()),
+		Some(None) => {
+			let partial = ready!(handles.as_mut().poll_next(cx));
+			let _ = partials.next();
+			Poll::Ready(partial)
+		}
+		None => Poll::Ready(None),
+	});
+	let reduce_c = reduce_c.into_async();
+	pin_mut!(reduce_c);
+	stream.sink(reduce_c).await
+}
@@ -0,0 +1,113 @@
+use derive_new::new;
+use futures::{ready, Stream};
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_closure::{traits::FnMut, FnNamed};
+use std::{
+	collections::HashSet, hash::Hash, pin::Pin, task::{Context, Poll}
+};
+
+use super::{ParallelStream, Repartition, RepartitionTask, RepartitionTaskAsync, StreamTask};
+use crate::util::IoError;
+
+FnNamed! {
+	pub type DistinctKey<T> = |self|row=> &T| -> T where ; where T: (Clone) {
+		row.clone()
+	}
+}
+
+/// A stream with the duplicate rows removed, as returned by
+/// [`distinct`](ParallelStream::distinct) and
+/// [`dedup_by_key`](ParallelStream::dedup_by_key).
+///
+/// The rows have been hash-partitioned by key, so each worker removes the duplicates
+/// within the partitions it streams. Which of a set of duplicates is kept is
+/// unspecified. The rows are `Result`s, as reading a spill file can fail.
+#[pin_project]
+#[derive(new)]
+#[must_use]
+pub struct Dedup<T, F> {
+	#[pin]
+	repartition: Repartition<T>,
+	f: F,
+}
+
+impl_par_dist! {
+	impl<T, F, K> ParallelStream for Dedup<T, F>
+	where
+		T: DeserializeOwned + 'static,
+		F: for<'a> FnMut<(&'a T,), Output = K> + Clone + Send + 'static,
+		K: Eq + Hash,
+	{
+		type Item = Result<T, IoError>;
+		type Task = DedupTask<T, F>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			ParallelStream::size_hint(&self.repartition)
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let f = self_.f;
+			ParallelStream::next_task(self_.repartition, cx).map(|task| {
+				task.map(|task| {
+					let f = f.clone();
+					DedupTask { task, f }
+				})
+			})
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "F: Serialize"),
+	bound(deserialize = "F: Deserialize<'de>")
+)]
+pub struct DedupTask<T, F> {
+	task: RepartitionTask<T>,
+	f: F,
+}
+impl<T, F, K> StreamTask for DedupTask<T, F>
+where
+	T: DeserializeOwned,
+	F: for<'a> FnMut<(&'a T,), Output = K>,
+	K: Eq + Hash,
+{
+	type Item = Result<T, IoError>;
+	type Async = DedupTaskAsync<T, F, K>;
+
+	fn into_async(self) -> Self::Async {
+		DedupTaskAsync {
+			task: self.task.into_async(),
+			f: self.f,
+			seen: HashSet::new(),
+		}
+	}
+}
+
+#[pin_project]
+pub struct DedupTaskAsync<T, F, K> {
+	#[pin]
+	task: RepartitionTaskAsync<T>,
+	f: F,
+	seen: HashSet<K>,
+}
+impl<T, F, K> Stream for DedupTaskAsync<T, F, K>
+where
+	T: DeserializeOwned,
+	F: for<'a> FnMut<(&'a T,), Output = K>,
+	K: Eq + Hash,
+{
+	type Item = Result<T, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		while let Some(row) = ready!(self_.task.as_mut().poll_next(cx)) {
+			match row {
+				Ok(row) if !self_.seen.insert(self_.f.call_mut((&row,))) => (),
+				row => return Poll::Ready(Some(row)),
+			}
+		}
+		Poll::Ready(None)
+	}
+}
@@ -0,0 +1,143 @@
+#![allow(clippy::type_complexity)]
+
+use derive_new::new;
+use futures::{ready, stream, Stream};
+use indexmap::IndexMap;
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	hash::Hash, mem, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{ParallelStream, Repartition, RepartitionTask, RepartitionTaskAsync, StreamTask};
+use crate::{par_sink::Reducer, pipe::Sink, util::IoError};
+
+/// The groups of a stream, as returned by
+/// [`group_by_spilling`](ParallelStream::group_by_spilling).
+///
+/// The partial aggregates of each group have been hash-partitioned by key and spilled
+/// to disk; this yields a task for each partition, which merges the partial aggregates
+/// of the groups in it and streams `(key, aggregate)` rows. Only the groups of one
+/// partition at a time need fit in a worker's memory.
+///
+/// The rows are `Result`s, as reading a spill file can fail, in which case the task
+/// yields the error instead of the partition's groups.
+#[pin_project]
+#[derive(new)]
+#[must_use]
+pub struct Grouped<T, U, R> {
+	#[pin]
+	repartition: Repartition<(T, U)>,
+	reducer: R,
+}
+
+impl_par_dist! {
+	impl<T, U, R> ParallelStream for Grouped<T, U, R>
+	where
+		T: Eq + Hash + DeserializeOwned + 'static,
+		U: DeserializeOwned + 'static,
+		R: Reducer<U> + Clone + Send + 'static,
+	{
+		type Item = Result<(T, R::Done), IoError>;
+		type Task = GroupedTask<T, U, R>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			(0, None)
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let reducer = self_.reducer;
+			ParallelStream::next_task(self_.repartition, cx).map(|task| {
+				task.map(|task| {
+					let reducer = reducer.clone();
+					GroupedTask { task, reducer }
+				})
+			})
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+	bound(serialize = "R: Serialize"),
+	bound(deserialize = "R: Deserialize<'de>")
+)]
+pub struct GroupedTask<T, U, R> {
+	task: RepartitionTask<(T, U)>,
+	reducer: R,
+}
+impl<T, U, R> StreamTask for GroupedTask<T, U, R>
+where
+	T: Eq + Hash + DeserializeOwned,
+	U: DeserializeOwned,
+	R: Reducer<U> + Clone,
+{
+	type Item = Result<(T, R::Done), IoError>;
+	type Async = GroupedTaskAsync<T, U, R>;
+
+	fn into_async(self) -> Self::Async {
+		GroupedTaskAsync {
+			task: self.task.into_async(),
+			reducer: self.reducer,
+			map: IndexMap::new(),
+			groups: None,
+			pending: None,
+		}
+	}
+}
+
+#[pin_project]
+pub struct GroupedTaskAsync<T, U, R>
+where
+	R: Reducer<U>,
+{
+	#[pin]
+	task: RepartitionTaskAsync<(T, U)>,
+	reducer: R,
+	map: IndexMap<T, Vec<U>>,
+	groups: Option<indexmap::map::IntoIter<T, Vec<U>>>,
+	pending: Option<(T, stream::Iter<vec::IntoIter<U>>, Pin<Box<R::Async>>)>,
+}
+impl<T, U, R> Stream for GroupedTaskAsync<T, U, R>
+where
+	T: Eq + Hash + DeserializeOwned,
+	U: DeserializeOwned,
+	R: Reducer<U> + Clone,
+{
+	type Item = Result<(T, R::Done), IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		if self_.groups.is_none() {
+			while let Some(row) = ready!(self_.task.as_mut().poll_next(cx)) {
+				match row {
+					Ok((key, partial)) => self_.map.entry(key).or_default().push(partial),
+					Err(err) => {
+						*self_.groups = Some(IndexMap::new().into_iter());
+						self_.map.clear();
+						return Poll::Ready(Some(Err(err)));
+					}
+				}
+			}
+			*self_.groups = Some(mem::take(self_.map).into_iter());
+		}
+		if self_.pending.is_none() {
+			*self_.pending = self_
+				.groups
+				.as_mut()
+				.unwrap()
+				.next()
+				.map(|(key, partials)| {
+					let reducer = Box::pin(self_.reducer.clone().into_async());
+					(key, stream::iter(partials), reducer)
+				});
+		}
+		let (partials, reducer) = match self_.pending {
+			Some((_, partials, reducer)) => (partials, reducer),
+			None => return Poll::Ready(None),
+		};
+		let done = ready!(reducer.as_mut().poll_forward(cx, Pin::new(partials)));
+		let (key, _, _) = self_.pending.take().unwrap();
+		Poll::Ready(Some(Ok((key, done))))
+	}
+}
@@ -0,0 +1,237 @@
+#![allow(clippy::type_complexity)]
+
+use futures::Stream;
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	collections::{hash_map, HashMap}, hash::Hash, marker::PhantomData, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{ImplIter, ParallelStream, StreamTask};
+use crate::{par_sink::ShuffleFile, spill::SpillFile, util::IoError};
+
+/// Which rows a [`HashJoin`] yields, given the rows of the left and right streams with
+/// each key.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum JoinKind {
+	/// Keys with rows on both sides.
+	Inner,
+	/// Keys with rows on the left side, along with any rows on the right.
+	Left,
+	/// Keys with rows on the right side, along with any rows on the left.
+	Right,
+	/// Keys with rows on either side.
+	Full,
+	/// Keys with rows on both sides, yielding only the rows on the left.
+	Semi,
+	/// Keys with rows on the left side but not the right, yielding the rows on the left.
+	Anti,
+}
+
+/// The join of two streams of `(key, value)` rows, as returned by
+/// [`hash_join`](ParallelStream::hash_join).
+///
+/// Both streams have been hash-partitioned by key into spill files; this yields a task
+/// for each partition, which reads the partition's rows from both sides into memory
+/// and yields a row for each key according to the [`JoinKind`]. The number of
+/// partitions should be chosen so that a partition fits in a worker's memory.
+///
+/// Reading a partition's spill files can fail, in which case its task yields the
+/// error and no rows. The spill files are deleted when this is dropped.
+#[pin_project]
+#[must_use]
+pub struct HashJoin<K, V1, V2> {
+	partitions: vec::IntoIter<(Vec<ShuffleFile>, Vec<ShuffleFile>)>,
+	kind: JoinKind,
+	_files: Vec<SpillFile>,
+	marker: PhantomData<fn() -> (K, V1, V2)>,
+}
+impl<K, V1, V2> HashJoin<K, V1, V2> {
+	/// Joins the spilled rows of both sides, or fails with the error spilling either,
+	/// removing the files of the other.
+	pub(crate) fn new(
+		left: Result<Vec<ShuffleFile>, IoError>, right: Result<Vec<ShuffleFile>, IoError>,
+		partitions: usize, kind: JoinKind,
+	) -> Result<Self, IoError> {
+		let files = left
+			.iter()
+			.chain(&right)
+			.flatten()
+			.map(|file| SpillFile(file.path.clone()))
+			.collect();
+		let (left, right) = (left?, right?);
+		let mut partitions_ = (0..partitions)
+			.map(|_| (Vec::new(), Vec::new()))
+			.collect::<Vec<_>>();
+		for file in left {
+			partitions_[file.partition].0.push(file);
+		}
+		for file in right {
+			partitions_[file.partition].1.push(file);
+		}
+		Ok(Self {
+			partitions: partitions_.into_iter(),
+			kind,
+			_files: files,
+			marker: PhantomData,
+		})
+	}
+}
+
+impl_par_dist! {
+	impl<K, V1, V2> ParallelStream for HashJoin<K, V1, V2>
+	where
+		K: Eq + Hash + DeserializeOwned + 'static,
+		V1: DeserializeOwned + 'static,
+		V2: DeserializeOwned + 'static,
+	{
+		type Item = Result<(K, ImplIter<V1>, ImplIter<V2>), IoError>;
+		type Task = HashJoinTask<K, V1, V2>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.partitions.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let kind = *self_.kind;
+			Poll::Ready(self_.partitions.next().map(|(left, right)| HashJoinTask {
+				left,
+				right,
+				kind,
+				marker: PhantomData,
+			}))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct HashJoinTask<K, V1, V2> {
+	left: Vec<ShuffleFile>,
+	right: Vec<ShuffleFile>,
+	kind: JoinKind,
+	marker: PhantomData<fn() -> (K, V1, V2)>,
+}
+impl<K, V1, V2> StreamTask for HashJoinTask<K, V1, V2>
+where
+	K: Eq + Hash + DeserializeOwned,
+	V1: DeserializeOwned,
+	V2: DeserializeOwned,
+{
+	type Item = Result<(K, ImplIter<V1>, ImplIter<V2>), IoError>;
+	type Async = HashJoinTaskAsync<K, V1, V2>;
+
+	fn into_async(self) -> Self::Async {
+		HashJoinTaskAsync {
+			task: Some(self),
+			groups: None,
+		}
+	}
+}
+
+/// The rows of a partition with a particular key.
+struct Group<V1, V2> {
+	left: Vec<V1>,
+	right: Vec<V2>,
+	matched: bool,
+}
+
+#[pin_project]
+pub struct HashJoinTaskAsync<K, V1, V2> {
+	task: Option<HashJoinTask<K, V1, V2>>,
+	groups: Option<(JoinKind, hash_map::IntoIter<K, Group<V1, V2>>)>,
+}
+impl<K, V1, V2> HashJoinTaskAsync<K, V1, V2>
+where
+	K: Eq + Hash + DeserializeOwned,
+	V1: DeserializeOwned,
+	V2: DeserializeOwned,
+{
+	fn build(task: HashJoinTask<K, V1, V2>) -> Result<HashMap<K, Group<V1, V2>>, IoError> {
+		let mut groups = HashMap::<K, Group<V1, V2>>::new();
+		for file in &task.left {
+			for row in file.open::<(K, V1)>()? {
+				let (key, value) = row?;
+				groups
+					.entry(key)
+					.or_insert_with(|| Group {
+						left: Vec::new(),
+						right: Vec::new(),
+						matched: false,
+					})
+					.left
+					.push(value);
+			}
+		}
+		// Only keep the right rows if they're yielded, and the keys if they're unmatched
+		let (keep_rows, keep_unmatched) = match task.kind {
+			JoinKind::Inner | JoinKind::Left => (true, false),
+			JoinKind::Right | JoinKind::Full => (true, true),
+			JoinKind::Semi | JoinKind::Anti => (false, false),
+		};
+		for file in &task.right {
+			for row in file.open::<(K, V2)>()? {
+				let (key, value) = row?;
+				let group = if keep_unmatched {
+					Some(groups.entry(key).or_insert_with(|| Group {
+						left: Vec::new(),
+						right: Vec::new(),
+						matched: false,
+					}))
+				} else {
+					groups.get_mut(&key)
+				};
+				if let Some(group) = group {
+					group.matched = true;
+					if keep_rows {
+						group.right.push(value);
+					}
+				}
+			}
+		}
+		Ok(groups)
+	}
+}
+impl<K, V1, V2> Stream for HashJoinTaskAsync<K, V1, V2>
+where
+	K: Eq + Hash + DeserializeOwned,
+	V1: DeserializeOwned,
+	V2: DeserializeOwned,
+{
+	type Item = Result<(K, ImplIter<V1>, ImplIter<V2>), IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if let Some(task) = self_.task.take() {
+			let kind = task.kind;
+			let groups = Self::build(task);
+			let (groups, err) = match groups {
+				Ok(groups) => (groups, None),
+				Err(err) => (HashMap::new(), Some(err)),
+			};
+			*self_.groups = Some((kind, groups.into_iter()));
+			if let Some(err) = err {
+				return Poll::Ready(Some(Err(err)));
+			}
+		}
+		let (kind, groups) = self_.groups.as_mut().unwrap();
+		for (key, group) in groups {
+			let (left, matched) = (!group.left.is_empty(), group.matched);
+			let yielded = match kind {
+				JoinKind::Inner | JoinKind::Semi => left && matched,
+				JoinKind::Left => left,
+				JoinKind::Right => matched,
+				JoinKind::Full => true,
+				JoinKind::Anti => left && !matched,
+			};
+			if yielded {
+				return Poll::Ready(Some(Ok((
+					key,
+					ImplIter(group.left.into_iter()),
+					ImplIter(group.right.into_iter()),
+				))));
+			}
+		}
+		Poll::Ready(None)
+	}
+}
@@ -6,7 +6,7 @@ use std::{
 };
 
 use super::{
-	All, Any, Collect, Combine, Count, Filter, FlatMap, Fold, ForEach, Fork, GroupBy, Histogram, Inspect, Map, Max, MaxBy, MaxByKey, Mean, Min, MinBy, MinByKey, MostDistinct, MostFrequent, ParallelPipe, Pipe, PipeTask, SampleUnstable, StdDev, Sum, Update
+	All, Any, Collect, CollectErrors, Combine, Count, CountDistinctApprox, FailAfter, Filter, FilterMapSync, FlatMap, Fold, ForEach, Fork, GroupBy, Histogram, Inspect, Map, Max, MaxBy, MaxByKey, Mean, Min, MinBy, MinByKey, MostDistinct, MostFrequent, ParallelPipe, Pipe, PipeTask, SampleUnstable, SkipErrors, SplitErrors, StdDev, Sum, Take, Update
 };
 
 // TODO: add type parameter to Identity when type the type system includes HRTB in the ParallelPipe impl https://github.com/dtolnay/ghost/
@@ -43,6 +43,28 @@ mod workaround {
 			Fork::new(self, sink, sink_ref)
 		}
 
+		#[inline]
+		pub fn skip_errors<T, E>(self) -> FilterMapSync<Self, SkipErrors<T, E>> {
+			FilterMapSync::new(self, SkipErrors::new())
+		}
+
+		#[inline]
+		pub fn split_errors<A, B>(self, sink: A, err_sink: B) -> SplitErrors<Self, A, B> {
+			SplitErrors::new(self, sink, err_sink)
+		}
+
+		#[inline]
+		pub fn collect_errors<S>(
+			self, limit: usize, sink: S,
+		) -> SplitErrors<Self, S, CollectErrors<Identity>> {
+			SplitErrors::new(self, sink, CollectErrors::new(Identity, limit))
+		}
+
+		#[inline]
+		pub fn fail_after<S>(self, n: usize, sink: S) -> FailAfter<Self, S> {
+			FailAfter::new(self, sink, n)
+		}
+
 		#[inline]
 		pub fn inspect<F>(self, f: F) -> Inspect<Self, F>
 		where
@@ -125,6 +147,11 @@ mod workaround {
 			Count::new(self)
 		}
 
+		#[inline]
+		pub fn count_distinct_approx(self, error_rate: f64) -> CountDistinctApprox<Self> {
+			CountDistinctApprox::new(self, error_rate)
+		}
+
 		#[inline]
 		pub fn sum<B>(self) -> Sum<Self, B>
 		where
@@ -228,6 +255,11 @@ mod workaround {
 			Any::new(self, f)
 		}
 
+		#[inline]
+		pub fn take(self, n: usize) -> Take<Self> {
+			Take::new(self, n)
+		}
+
 		#[inline]
 		pub fn collect<B>(self) -> Collect<Self, B> {
 			Collect::new(self)
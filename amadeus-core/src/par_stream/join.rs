@@ -124,7 +124,7 @@ FnMutNamed! {
 	}
 }
 
-pub struct ImplIter<T>(vec::IntoIter<T>);
+pub struct ImplIter<T>(pub(crate) vec::IntoIter<T>);
 impl<T> Iterator for ImplIter<T> {
 	type Item = T;
 
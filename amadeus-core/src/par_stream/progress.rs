@@ -0,0 +1,451 @@
+use derive_new::new;
+use futures::Stream;
+use once_cell::sync::Lazy;
+use pin_project::{pin_project, pinned_drop};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	collections::HashMap, convert::TryFrom, fmt, pin::Pin, sync::{
+		atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, Mutex, Weak
+	}, task::{Context, Poll}, thread, time::{Duration, SystemTime}
+};
+
+use super::{ParallelStream, StreamTask};
+use crate::file::bytes_read;
+
+/// Receives the progress of a stream wrapped with
+/// [`with_progress`](ParallelStream::with_progress), as its tasks are dispatched and
+/// run.
+///
+/// The methods are called from the threads running the tasks, so should be quick.
+#[allow(unused_variables)]
+pub trait Observer: Send + Sync {
+	/// A task was handed out to be run.
+	fn task_dispatched(&self, progress: &Metrics) {}
+	/// A task finished streaming its rows.
+	fn task_completed(&self, task: &TaskMetrics, progress: &Metrics) {}
+	/// A task panicked.
+	fn task_failed(&self, task: &TaskMetrics, progress: &Metrics) {}
+}
+
+/// The progress of a single task.
+#[derive(Copy, Clone, Default, Serialize, Deserialize, Debug)]
+pub struct TaskMetrics {
+	/// The rows the task streamed.
+	pub items: u64,
+	/// The bytes the task read from [`Page`](crate::file::Page)s.
+	pub bytes: u64,
+	/// The time from the task first being polled to it finishing.
+	pub duration: Duration,
+}
+
+/// The progress of a stream, as of some point in time.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Metrics {
+	/// The tasks handed out to be run.
+	pub tasks_dispatched: usize,
+	/// The tasks that finished streaming their rows.
+	pub tasks_completed: usize,
+	/// The tasks that panicked.
+	pub tasks_failed: usize,
+	/// The stream's [`size_hint`](ParallelStream::size_hint), i.e. the bounds on the
+	/// number of tasks yet to be handed out.
+	pub tasks_remaining: (usize, Option<usize>),
+	/// The rows streamed by all tasks.
+	pub items: u64,
+	/// The bytes read from [`Page`](crate::file::Page)s by all tasks.
+	pub bytes: u64,
+	/// The total time spent running tasks.
+	pub task_time: Duration,
+	/// The time since the first task was handed out.
+	pub elapsed: Duration,
+}
+
+impl Metrics {
+	/// The estimated total number of tasks: those handed out so far, plus the upper
+	/// bound of those remaining if there is one, otherwise the lower bound.
+	pub fn tasks_total(&self) -> usize {
+		let (lower, upper) = self.tasks_remaining;
+		self.tasks_dispatched + upper.unwrap_or(lower)
+	}
+
+	/// The estimated fraction of tasks finished, between `0.0` and `1.0`. Returns `None`
+	/// if no tasks are known of.
+	#[allow(clippy::cast_precision_loss)]
+	pub fn fraction_done(&self) -> Option<f64> {
+		let total = self.tasks_total();
+		if total != 0 {
+			Some((self.tasks_completed + self.tasks_failed) as f64 / total as f64)
+		} else {
+			None
+		}
+	}
+
+	/// The estimated time until all tasks are finished, extrapolated from the time taken
+	/// so far. Returns `None` until a task has finished.
+	pub fn eta(&self) -> Option<Duration> {
+		let done = self.fraction_done().filter(|&done| done > 0.0)?;
+		Some(self.elapsed.mul_f64((1.0 - done) / done))
+	}
+
+	/// Returns `true` once every task has been handed out and finished.
+	pub fn is_done(&self) -> bool {
+		self.tasks_remaining.1 == Some(0)
+			&& self.tasks_completed + self.tasks_failed == self.tasks_dispatched
+	}
+}
+
+/// A handle with which to observe the progress of a stream, as returned by
+/// [`metrics`](Progress::metrics) or reported to an [`Observer`].
+///
+/// Give it to a stream with [`with_progress`](ParallelStream::with_progress). Tasks
+/// sent to the processes of a [`ProcessPool`](crate::pool::ProcessPool) send their
+/// metrics back with the results of the batch they were sent in, so they're observed
+/// as finished once that batch is.
+#[derive(Clone)]
+pub struct Progress {
+	inner: Arc<ProgressInner>,
+}
+
+struct ProgressInner {
+	id: u64,
+	/// Whether this is a copy deserialized from another process, whose tasks' metrics
+	/// are to be sent back there.
+	remote: bool,
+	tasks_dispatched: AtomicUsize,
+	tasks_completed: AtomicUsize,
+	tasks_failed: AtomicUsize,
+	tasks_remaining: Mutex<(usize, Option<usize>)>,
+	items: AtomicU64,
+	bytes: AtomicU64,
+	task_nanos: AtomicU64,
+	started: Mutex<Option<SystemTime>>,
+	observer: Option<Box<dyn Observer>>,
+}
+
+impl Progress {
+	/// Create a handle that counts the progress of the streams it's given to, for
+	/// retrieval with [`metrics`](Progress::metrics).
+	pub fn new() -> Self {
+		Self::new_with(None)
+	}
+
+	/// Create a handle that additionally reports progress to `observer`.
+	pub fn with_observer(observer: impl Observer + 'static) -> Self {
+		Self::new_with(Some(Box::new(observer)))
+	}
+
+	/// Create a handle that draws a [`ProgressBar`] to stderr.
+	pub fn bar() -> Self {
+		Self::with_observer(ProgressBar::new())
+	}
+
+	fn new_with(observer: Option<Box<dyn Observer>>) -> Self {
+		Self::get(thread_rng().gen(), false, observer)
+	}
+
+	fn get(id: u64, remote: bool, observer: Option<Box<dyn Observer>>) -> Self {
+		let mut handles = HANDLES.lock().unwrap();
+		if let Some(inner) = handles.get(&id).and_then(Weak::upgrade) {
+			return Self { inner };
+		}
+		handles.retain(|_, inner| inner.strong_count() != 0);
+		let progress = Self {
+			inner: Arc::new(ProgressInner {
+				id,
+				remote,
+				tasks_dispatched: AtomicUsize::new(0),
+				tasks_completed: AtomicUsize::new(0),
+				tasks_failed: AtomicUsize::new(0),
+				tasks_remaining: Mutex::new((0, None)),
+				items: AtomicU64::new(0),
+				bytes: AtomicU64::new(0),
+				task_nanos: AtomicU64::new(0),
+				started: Mutex::new(None),
+				observer,
+			}),
+		};
+		let _ = handles.insert(id, Arc::downgrade(&progress.inner));
+		progress
+	}
+
+	/// Retrieve the progress so far.
+	pub fn metrics(&self) -> Metrics {
+		let inner = &*self.inner;
+		let elapsed = inner
+			.started
+			.lock()
+			.unwrap()
+			.map(|started| started.elapsed().unwrap_or_default())
+			.unwrap_or_default();
+		Metrics {
+			tasks_dispatched: inner.tasks_dispatched.load(Ordering::Relaxed),
+			tasks_completed: inner.tasks_completed.load(Ordering::Relaxed),
+			tasks_failed: inner.tasks_failed.load(Ordering::Relaxed),
+			tasks_remaining: *inner.tasks_remaining.lock().unwrap(),
+			items: inner.items.load(Ordering::Relaxed),
+			bytes: inner.bytes.load(Ordering::Relaxed),
+			task_time: Duration::from_nanos(inner.task_nanos.load(Ordering::Relaxed)),
+			elapsed,
+		}
+	}
+
+	fn dispatched(&self, tasks_remaining: (usize, Option<usize>)) {
+		let inner = &*self.inner;
+		let _ = inner
+			.started
+			.lock()
+			.unwrap()
+			.get_or_insert_with(SystemTime::now);
+		*inner.tasks_remaining.lock().unwrap() = tasks_remaining;
+		let _ = inner.tasks_dispatched.fetch_add(1, Ordering::Relaxed);
+		if let Some(observer) = &inner.observer {
+			observer.task_dispatched(&self.metrics());
+		}
+	}
+
+	fn exhausted(&self) {
+		*self.inner.tasks_remaining.lock().unwrap() = (0, Some(0));
+	}
+
+	fn finished(&self, task: &TaskMetrics, failed: bool) {
+		let inner = &*self.inner;
+		if inner.remote {
+			REPORTS.lock().unwrap().push(ProgressReport {
+				id: inner.id,
+				task: *task,
+				failed,
+			});
+			return;
+		}
+		let _ = inner.items.fetch_add(task.items, Ordering::Relaxed);
+		let _ = inner.bytes.fetch_add(task.bytes, Ordering::Relaxed);
+		let nanos = u64::try_from(task.duration.as_nanos()).unwrap_or(u64::MAX);
+		let _ = inner.task_nanos.fetch_add(nanos, Ordering::Relaxed);
+		let _ = if !failed {
+			&inner.tasks_completed
+		} else {
+			&inner.tasks_failed
+		}
+		.fetch_add(1, Ordering::Relaxed);
+		if let Some(observer) = &inner.observer {
+			let progress = self.metrics();
+			if !failed {
+				observer.task_completed(task, &progress);
+			} else {
+				observer.task_failed(task, &progress);
+			}
+		}
+	}
+}
+impl Default for Progress {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl fmt::Debug for Progress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Progress").field(&self.metrics()).finish()
+	}
+}
+impl Serialize for Progress {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		self.inner.id.serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Progress {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		u64::deserialize(deserializer).map(|id| Self::get(id, true, None))
+	}
+}
+
+/// The handles in this process by id, so that the tasks deserialized here share one,
+/// and metrics sent back from another process reach the original.
+static HANDLES: Lazy<Mutex<HashMap<u64, Weak<ProgressInner>>>> = Lazy::new(Default::default);
+
+/// The metrics of the tasks finished in this process that report to a handle in
+/// another process, yet to be sent back to it.
+static REPORTS: Lazy<Mutex<Vec<ProgressReport>>> = Lazy::new(Default::default);
+
+/// The metrics of a task finished in one process, reporting to the handle with id
+/// `id` in another.
+#[doc(hidden)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProgressReport {
+	id: u64,
+	task: TaskMetrics,
+	failed: bool,
+}
+
+/// Take the metrics of the tasks finished in this process since the last call, to be
+/// sent back to the process that sent the tasks, and applied there with
+/// [`progress_reported`]. Called by `ProcessPool` once a batch of tasks finishes.
+#[doc(hidden)]
+pub fn take_progress_reports() -> Vec<ProgressReport> {
+	std::mem::take(&mut *REPORTS.lock().unwrap())
+}
+
+/// Apply the metrics sent back from another process to the handles in this process
+/// they report to, if they're still alive.
+#[doc(hidden)]
+pub fn progress_reported(reports: Vec<ProgressReport>) {
+	for ProgressReport { id, task, failed } in reports {
+		let inner = HANDLES.lock().unwrap().get(&id).and_then(Weak::upgrade);
+		if let Some(inner) = inner {
+			Progress { inner }.finished(&task, failed);
+		}
+	}
+}
+
+/// An [`Observer`] that draws a progress bar to stderr, with the rows and bytes
+/// processed and an estimate of the time remaining.
+#[derive(Debug, Default)]
+pub struct ProgressBar {
+	width: usize,
+}
+impl ProgressBar {
+	/// Create a progress bar 40 characters wide.
+	pub fn new() -> Self {
+		Self::with_width(40)
+	}
+
+	/// Create a progress bar `width` characters wide.
+	pub fn with_width(width: usize) -> Self {
+		Self { width }
+	}
+
+	#[allow(
+		clippy::cast_possible_truncation,
+		clippy::cast_sign_loss,
+		clippy::cast_precision_loss
+	)]
+	fn draw(&self, progress: &Metrics) {
+		let done = progress.fraction_done().unwrap_or(0.0).min(1.0);
+		let filled = ((done * self.width as f64) as usize).min(self.width);
+		let eta = progress
+			.eta()
+			.map(|eta| format!("{}s", eta.as_secs()))
+			.unwrap_or_else(|| String::from("?"));
+		eprint!(
+			"\r[{}{}] {}/{} tasks, {} failed, {} rows, {:.1} MiB, eta {}{}",
+			"=".repeat(filled),
+			" ".repeat(self.width - filled),
+			progress.tasks_completed + progress.tasks_failed,
+			progress.tasks_total(),
+			progress.tasks_failed,
+			progress.items,
+			progress.bytes as f64 / 1024.0 / 1024.0,
+			eta,
+			if progress.is_done() { "\n" } else { "" }
+		);
+	}
+}
+impl Observer for ProgressBar {
+	fn task_completed(&self, _task: &TaskMetrics, progress: &Metrics) {
+		self.draw(progress)
+	}
+	fn task_failed(&self, _task: &TaskMetrics, progress: &Metrics) {
+		self.draw(progress)
+	}
+}
+
+#[pin_project]
+#[derive(new)]
+#[must_use]
+pub struct WithProgress<S> {
+	#[pin]
+	stream: S,
+	progress: Progress,
+}
+
+impl_par_dist! {
+	impl<S: ParallelStream> ParallelStream for WithProgress<S> {
+		type Item = S::Item;
+		type Task = WithProgressTask<S::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.stream.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let mut self_ = self.project();
+			let task = futures::ready!(self_.stream.as_mut().next_task(cx));
+			let progress = self_.progress.clone();
+			if task.is_some() {
+				progress.dispatched(self_.stream.size_hint());
+			} else {
+				progress.exhausted();
+			}
+			Poll::Ready(task.map(|task| WithProgressTask { task, progress }))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WithProgressTask<T> {
+	task: T,
+	progress: Progress,
+}
+impl<T: StreamTask> StreamTask for WithProgressTask<T> {
+	type Item = T::Item;
+	type Async = WithProgressTaskAsync<T::Async>;
+
+	fn into_async(self) -> Self::Async {
+		WithProgressTaskAsync {
+			task: self.task.into_async(),
+			progress: Some(self.progress),
+			started: None,
+			metrics: TaskMetrics::default(),
+		}
+	}
+}
+
+#[pin_project(PinnedDrop)]
+pub struct WithProgressTaskAsync<T> {
+	#[pin]
+	task: T,
+	progress: Option<Progress>,
+	started: Option<SystemTime>,
+	metrics: TaskMetrics,
+}
+impl<T: Stream> Stream for WithProgressTaskAsync<T> {
+	type Item = T::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		let started = *self_.started.get_or_insert_with(SystemTime::now);
+		let bytes = bytes_read();
+		let ret = self_.task.poll_next(cx);
+		self_.metrics.bytes += bytes_read() - bytes;
+		match &ret {
+			Poll::Ready(Some(_)) => self_.metrics.items += 1,
+			Poll::Ready(None) => {
+				if let Some(progress) = self_.progress.take() {
+					self_.metrics.duration = started.elapsed().unwrap_or_default();
+					progress.finished(self_.metrics, false);
+				}
+			}
+			Poll::Pending => (),
+		}
+		ret
+	}
+}
+#[pinned_drop]
+impl<T> PinnedDrop for WithProgressTaskAsync<T> {
+	fn drop(self: Pin<&mut Self>) {
+		// Tasks dropped early, because the sink has all the rows it needs, count as
+		// completed; those dropped while unwinding as failed.
+		let self_ = self.project();
+		if let Some(progress) = self_.progress.take() {
+			if let Some(started) = self_.started {
+				self_.metrics.duration = started.elapsed().unwrap_or_default();
+			}
+			progress.finished(self_.metrics, thread::panicking());
+		}
+	}
+}
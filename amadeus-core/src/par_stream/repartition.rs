@@ -0,0 +1,118 @@
+use futures::Stream;
+use pin_project::pin_project;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	marker::PhantomData, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{ParallelStream, StreamTask};
+use crate::{
+	par_sink::ShuffleFile, spill::{SpillFile, SpillReader}, util::IoError
+};
+
+/// A stream whose rows have been hash-partitioned by key, as returned by
+/// [`repartition_by`](ParallelStream::repartition_by).
+///
+/// Each partition's rows have been spilled to files by the workers that saw them; this
+/// yields a task for each partition, so all the rows with a given key are streamed by
+/// the same worker.
+///
+/// The rows are `Result`s, as reading a spill file can fail. The spill files are
+/// deleted when this is dropped.
+#[pin_project]
+#[must_use]
+pub struct Repartition<T> {
+	partitions: vec::IntoIter<Vec<ShuffleFile>>,
+	_files: Vec<SpillFile>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> Repartition<T> {
+	pub(crate) fn new(files: Vec<ShuffleFile>, partitions: usize) -> Self {
+		let files_ = files
+			.iter()
+			.map(|file| SpillFile(file.path.clone()))
+			.collect();
+		let mut partitions_ = (0..partitions).map(|_| Vec::new()).collect::<Vec<_>>();
+		for file in files {
+			partitions_[file.partition].push(file);
+		}
+		Self {
+			partitions: partitions_.into_iter(),
+			_files: files_,
+			marker: PhantomData,
+		}
+	}
+}
+
+impl_par_dist! {
+	impl<T> ParallelStream for Repartition<T>
+	where
+		T: DeserializeOwned + 'static,
+	{
+		type Item = Result<T, IoError>;
+		type Task = RepartitionTask<T>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.partitions.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			Poll::Ready(self_.partitions.next().map(|files| RepartitionTask {
+				files,
+				marker: PhantomData,
+			}))
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RepartitionTask<T> {
+	files: Vec<ShuffleFile>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> StreamTask for RepartitionTask<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+	type Async = RepartitionTaskAsync<T>;
+
+	fn into_async(self) -> Self::Async {
+		RepartitionTaskAsync {
+			files: self.files.into_iter(),
+			rows: None,
+		}
+	}
+}
+
+#[pin_project]
+pub struct RepartitionTaskAsync<T> {
+	files: vec::IntoIter<ShuffleFile>,
+	rows: Option<SpillReader<T>>,
+}
+impl<T> Stream for RepartitionTaskAsync<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = Result<T, IoError>;
+
+	fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		loop {
+			if let Some(row) = self_.rows.as_mut().and_then(Iterator::next) {
+				return Poll::Ready(Some(row.map_err(Into::into)));
+			}
+			match self_.files.next() {
+				Some(file) => match file.open() {
+					Ok(rows) => *self_.rows = Some(rows),
+					Err(err) => {
+						*self_.rows = None;
+						return Poll::Ready(Some(Err(err.into())));
+					}
+				},
+				None => return Poll::Ready(None),
+			}
+		}
+	}
+}
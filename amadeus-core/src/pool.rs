@@ -1,3 +1,6 @@
+mod checkpoint;
+mod retry;
+
 use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 use serde_closure::traits;
@@ -8,6 +11,9 @@ use std::{
 pub trait ProcessSend: Send + Serialize + for<'de> Deserialize<'de> {}
 impl<T: ?Sized> ProcessSend for T where T: Send + Serialize + for<'de> Deserialize<'de> {}
 
+pub(crate) use self::retry::{spawn as spawn_retrying, Job};
+pub use self::{checkpoint::Checkpoint, retry::RetryPolicy};
+
 type Result<T> = std::result::Result<T, Box<dyn Error + Send>>;
 
 #[cfg_attr(not(nightly), serde_closure::desugar)]
@@ -16,6 +22,18 @@ pub trait ProcessPool: Clone + Send + Sync + RefUnwindSafe + UnwindSafe + Unpin
 
 	fn processes(&self) -> usize;
 
+	/// How the tasks of distributed streams run on this pool are retried and
+	/// speculatively re-executed.
+	fn retry_policy(&self) -> RetryPolicy {
+		RetryPolicy::new()
+	}
+
+	/// Where the partial results of distributed streams' finished tasks are recorded,
+	/// if anywhere.
+	fn checkpoint(&self) -> Option<Checkpoint> {
+		None
+	}
+
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'static,
@@ -42,6 +60,12 @@ pub trait ProcessPool: Clone + Send + Sync + RefUnwindSafe + UnwindSafe + Unpin
 pub trait ThreadPool: Clone + Send + Sync + RefUnwindSafe + UnwindSafe + Unpin {
 	fn threads(&self) -> usize;
 
+	/// How the tasks of distributed streams run on this pool are retried and
+	/// speculatively re-executed. Parallel streams' tasks aren't.
+	fn retry_policy(&self) -> RetryPolicy {
+		RetryPolicy::new()
+	}
+
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: FnOnce() -> Fut + Send + 'static,
@@ -75,6 +99,12 @@ where
 	fn processes(&self) -> usize {
 		(*self).processes()
 	}
+	fn retry_policy(&self) -> RetryPolicy {
+		(*self).retry_policy()
+	}
+	fn checkpoint(&self) -> Option<Checkpoint> {
+		(*self).checkpoint()
+	}
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'static,
@@ -101,6 +131,9 @@ where
 	fn threads(&self) -> usize {
 		(*self).threads()
 	}
+	fn retry_policy(&self) -> RetryPolicy {
+		(*self).retry_policy()
+	}
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: FnOnce() -> Fut + Send + 'static,
@@ -0,0 +1,72 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	ffi::OsStr, fs, io, path::{Path, PathBuf}, sync::Arc
+};
+
+/// A directory in which the partial results of a distributed stream's finished tasks
+/// are recorded, so that a later run of the same pipeline can skip those tasks and
+/// merge the recorded partial results instead.
+///
+/// Tasks are identified by their serialized form along with that of the reducers they
+/// are run with, so the same tasks reduced differently, or with closures that capture
+/// different values, are recorded separately. A task whose partial result couldn't be
+/// recorded is simply run again, and one that can't be serialized isn't recorded.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+	dir: Arc<PathBuf>,
+}
+
+impl Checkpoint {
+	/// Record partial results in `dir`, creating it if it doesn't exist.
+	pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+		let dir = dir.into();
+		fs::create_dir_all(&dir)?;
+		Ok(Self { dir: Arc::new(dir) })
+	}
+
+	/// The directory partial results are recorded in.
+	pub fn dir(&self) -> &Path {
+		&self.dir
+	}
+
+	/// Remove all recorded partial results, for example once the job has finished.
+	pub fn clear(&self) -> io::Result<()> {
+		for entry in fs::read_dir(&*self.dir)? {
+			let path = entry?.path();
+			if path.extension() == Some(OsStr::new("partial")) {
+				fs::remove_file(path)?;
+			}
+		}
+		Ok(())
+	}
+
+	pub(crate) fn load<T: DeserializeOwned>(&self, key: &[u8]) -> Option<T> {
+		let record = fs::read(self.path(key)).ok()?;
+		let (key_, partial): (&[u8], &[u8]) = bincode::deserialize(&record).ok()?;
+		if key_ != key {
+			return None;
+		}
+		bincode::deserialize(partial).ok()
+	}
+
+	pub(crate) fn store<T: Serialize>(&self, key: &[u8], partial: &T) -> io::Result<()> {
+		let partial = bincode::serialize(partial).map_err(into_io_error)?;
+		let record = bincode::serialize(&(key, &*partial)).map_err(into_io_error)?;
+		let path = self.path(key);
+		let tmp = path.with_extension("tmp");
+		fs::write(&tmp, record)?;
+		fs::rename(tmp, path)
+	}
+
+	fn path(&self, key: &[u8]) -> PathBuf {
+		// FNV-1a, as the file names need to be stable across runs
+		let hash = key.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+			(hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+		});
+		self.dir.join(format!("{:016x}.partial", hash))
+	}
+}
+
+fn into_io_error(err: bincode::Error) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, err)
+}
@@ -0,0 +1,188 @@
+use futures::{
+	future::{self, Either}, ready
+};
+use serde::{Deserialize, Serialize};
+use std::{
+	error::Error, future::Future, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll, Waker}, time::{Duration, Instant}
+};
+use tokio::time::{delay_until, Delay};
+
+use super::{ProcessSend, Result};
+
+/// How a pool handles the tasks of a job that fail, by panicking or by their process
+/// being lost, and those that run much slower than the rest of their job.
+///
+/// Retried and speculatively re-executed tasks may run more than once, so a policy
+/// other than the default should only be used for idempotent work, whose side effects
+/// are safe to repeat.
+///
+/// A policy only applies to distributed streams, whose tasks are serializable and so
+/// can be copied to be re-run. The tasks of a parallel stream are neither retried nor
+/// re-executed, whatever the policy of the thread pool it runs on.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct RetryPolicy {
+	retries: usize,
+	speculate: Option<f64>,
+}
+
+impl RetryPolicy {
+	/// Create a policy that neither retries nor speculatively re-executes tasks.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Re-queue a failed task up to `retries` times before failing the job. Each retry
+	/// is spawned afresh, so typically lands on another process or thread.
+	pub fn retries(self, retries: usize) -> Self {
+		Self { retries, ..self }
+	}
+
+	/// Once half of a job's tasks have finished, spawn a second copy of any task that
+	/// has been running for more than `slowdown` times the median duration of those
+	/// finished, and take whichever copy finishes first.
+	///
+	/// This needs the Tokio runtime's time driver to be enabled. It has no effect on
+	/// `wasm32`.
+	pub fn speculate(self, slowdown: f64) -> Self {
+		Self {
+			speculate: Some(slowdown),
+			..self
+		}
+	}
+
+	fn slowdown(&self) -> Option<f64> {
+		if !cfg!(target_arch = "wasm32") {
+			self.speculate
+		} else {
+			None
+		}
+	}
+}
+
+/// The tasks of a job, tracked so that its stragglers can be spotted.
+#[derive(Clone)]
+pub(crate) struct Job(Arc<Mutex<JobInner>>);
+
+struct JobInner {
+	tasks: usize,
+	durations: Vec<Duration>,
+	wakers: Vec<Waker>,
+}
+
+impl Job {
+	pub(crate) fn new(tasks: usize) -> Self {
+		Self(Arc::new(Mutex::new(JobInner {
+			tasks,
+			durations: Vec::new(),
+			wakers: Vec::new(),
+		})))
+	}
+
+	fn finished(&self, duration: Duration) {
+		let mut inner = self.0.lock().unwrap();
+		let index = inner
+			.durations
+			.binary_search(&duration)
+			.unwrap_or_else(|index| index);
+		inner.durations.insert(index, duration);
+		for waker in inner.wakers.drain(..) {
+			waker.wake();
+		}
+	}
+}
+
+impl JobInner {
+	/// When a task started at `start` becomes a straggler, if enough of the job has
+	/// finished to tell.
+	fn deadline(&self, start: Instant, slowdown: f64) -> Option<Instant> {
+		if self.durations.is_empty() || self.durations.len() * 2 < self.tasks {
+			return None;
+		}
+		let median = self.durations[self.durations.len() / 2];
+		Some(start + median.mul_f64(slowdown))
+	}
+}
+
+/// Resolves once the task started at `start` has become a straggler.
+struct Straggler<'a> {
+	job: &'a Job,
+	start: Instant,
+	slowdown: f64,
+	delay: Option<Delay>,
+}
+
+impl Future for Straggler<'_> {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+		let self_ = self.get_mut();
+		loop {
+			if let Some(delay) = &mut self_.delay {
+				ready!(Pin::new(delay).poll(cx));
+				self_.delay = None;
+			}
+			let mut job = self_.job.0.lock().unwrap();
+			match job.deadline(self_.start, self_.slowdown) {
+				None => {
+					job.wakers.push(cx.waker().clone());
+					return Poll::Pending;
+				}
+				Some(deadline) if deadline <= Instant::now() => return Poll::Ready(()),
+				Some(deadline) => {
+					drop(job);
+					self_.delay = Some(delay_until(deadline.into()));
+				}
+			}
+		}
+	}
+}
+
+/// Spawn `work` with `spawn`, retrying it and speculatively re-executing it as `policy`
+/// dictates. `work` is serialized so that copies of it can be spawned; failing to
+/// serialize or deserialize it fails the task.
+pub(crate) async fn spawn<W, F, Fut, T>(
+	policy: RetryPolicy, job: Job, work: W, mut spawn: F,
+) -> Result<T>
+where
+	W: ProcessSend,
+	F: FnMut(W) -> Fut,
+	Fut: Future<Output = Result<T>> + Unpin,
+{
+	if policy == RetryPolicy::default() {
+		return spawn(work).await;
+	}
+	let work = bincode::serialize(&work).map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+	let mut work = || match bincode::deserialize(&work) {
+		Ok(work) => Either::Left(spawn(work)),
+		Err(e) => Either::Right(future::ready(Err(Box::new(e) as Box<dyn Error + Send>))),
+	};
+	let mut retries = policy.retries;
+	loop {
+		let res = if let Some(slowdown) = policy.slowdown() {
+			let start = Instant::now();
+			let straggler = Straggler {
+				job: &job,
+				start,
+				slowdown,
+				delay: None,
+			};
+			let res = match future::select(work(), straggler).await {
+				Either::Left((res, _)) => res,
+				Either::Right(((), primary)) => match future::select(primary, work()).await {
+					Either::Left((Ok(t), _)) | Either::Right((Ok(t), _)) => Ok(t),
+					Either::Left((Err(_), other)) | Either::Right((Err(_), other)) => other.await,
+				},
+			};
+			if res.is_ok() {
+				job.finished(start.elapsed());
+			}
+			res
+		} else {
+			work().await
+		};
+		match res {
+			Err(_) if retries > 0 => retries -= 1,
+			res => break res,
+		}
+	}
+}
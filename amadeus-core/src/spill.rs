@@ -0,0 +1,151 @@
+//! Spilling rows to files in the temporary directory, for operations like sorting and
+//! shuffling whose state can exceed memory.
+//!
+//! Spill files are read by whichever worker is given them, so with a
+//! [`ProcessPool`](crate::pool::ProcessPool) spanning several machines the temporary
+//! directory needs to be shared between them. Shuffles check this, by tagging their
+//! files with the id of the temporary directory they were written to.
+
+use rand::{thread_rng, Rng};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	convert::TryInto, env, fs, io::{self, BufReader, BufWriter, Seek, SeekFrom, Write}, marker::PhantomData, path::{Path, PathBuf}, sync::atomic::{AtomicU64, Ordering}
+};
+
+/// A random id for the temporary directory, shared by all the processes that use it,
+/// so that a worker can tell whether it can read a spill file written by another.
+pub(crate) fn temp_dir_id() -> io::Result<u64> {
+	static ID: AtomicU64 = AtomicU64::new(0);
+	let id = ID.load(Ordering::Relaxed);
+	if id != 0 {
+		return Ok(id);
+	}
+	let path = env::temp_dir().join("amadeus.id");
+	if !path.exists() {
+		// Write a new id to a temporary file, then link it into place, which fails if
+		// another process has beaten us to it
+		let new = thread_rng().gen_range(1, u64::MAX);
+		let tmp = env::temp_dir().join(format!("amadeus-{:016x}.id", new));
+		fs::write(&tmp, new.to_le_bytes())?;
+		let res = fs::hard_link(&tmp, &path);
+		let _ = fs::remove_file(&tmp);
+		match res {
+			Err(err) if err.kind() != io::ErrorKind::AlreadyExists => return Err(err),
+			_ => (),
+		}
+	}
+	let id = fs::read(&path)?
+		.as_slice()
+		.try_into()
+		.map(u64::from_le_bytes)
+		.map_err(|_| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{} isn't a valid id", path.display()),
+			)
+		})?;
+	ID.store(id, Ordering::Relaxed);
+	Ok(id)
+}
+
+/// Writes rows to a new spill file. The file is removed if it's dropped before it's
+/// finished.
+pub(crate) struct SpillWriter {
+	file: BufWriter<fs::File>,
+	path: PathBuf,
+	offset: u64,
+	len: usize,
+	finished: bool,
+}
+impl SpillWriter {
+	pub(crate) fn new(name: &str) -> io::Result<Self> {
+		let path = env::temp_dir().join(format!(
+			"amadeus-{}-{:016x}.spill",
+			name,
+			thread_rng().gen::<u64>()
+		));
+		let file = BufWriter::new(fs::File::create(&path)?);
+		Ok(Self {
+			file,
+			path,
+			offset: 0,
+			len: 0,
+			finished: false,
+		})
+	}
+	/// The offset the next row will be written at.
+	pub(crate) fn offset(&self) -> u64 {
+		self.offset
+	}
+	pub(crate) fn write<T: Serialize>(&mut self, row: &T) -> io::Result<()> {
+		let bytes = bincode::serialize(row)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		self.file.write_all(&bytes)?;
+		self.offset += bytes.len() as u64;
+		self.len += 1;
+		Ok(())
+	}
+	/// Flushes the file, returning its path and the number of rows written.
+	pub(crate) fn finish(mut self) -> io::Result<(PathBuf, usize)> {
+		self.file.flush()?;
+		self.finished = true;
+		Ok((self.path.clone(), self.len))
+	}
+}
+impl Drop for SpillWriter {
+	fn drop(&mut self) {
+		if !self.finished {
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+}
+
+/// Reads `len` rows from a spill file, starting at `offset`. It ends after the first
+/// error.
+pub(crate) struct SpillReader<T> {
+	file: BufReader<fs::File>,
+	remaining: usize,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> SpillReader<T> {
+	pub(crate) fn open(path: &Path, offset: u64, len: usize) -> io::Result<Self> {
+		let mut file = BufReader::new(fs::File::open(path)?);
+		let _ = file.seek(SeekFrom::Start(offset))?;
+		Ok(Self {
+			file,
+			remaining: len,
+			marker: PhantomData,
+		})
+	}
+}
+impl<T> Iterator for SpillReader<T>
+where
+	T: DeserializeOwned,
+{
+	type Item = io::Result<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+		self.remaining -= 1;
+		Some(bincode::deserialize_from(&mut self.file).map_err(|err| {
+			self.remaining = 0;
+			match *err {
+				bincode::ErrorKind::Io(err) => err,
+				err => io::Error::new(io::ErrorKind::InvalidData, err),
+			}
+		}))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
+	}
+}
+
+/// Removes a spill file when dropped.
+pub(crate) struct SpillFile(pub(crate) PathBuf);
+impl Drop for SpillFile {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.0);
+	}
+}
@@ -3,11 +3,55 @@ use futures::{ready, Stream};
 use pin_project::pin_project;
 use serde::{de::Deserializer, ser::Serializer, Deserialize, Serialize};
 use std::{
-	any::{Any, TypeId}, error, fmt, hash::{Hash, Hasher}, io, marker::PhantomData, pin::Pin, sync::Arc, task::{Context, Poll}
+	any::{Any, TypeId}, collections::BTreeMap, error, fmt, hash::{Hash, Hasher}, io, marker::PhantomData, pin::Pin, sync::Arc, task::{Context, Poll}
 };
 
 use crate::par_stream::{DistributedStream, ParallelStream};
 
+/// Yields the results of tasks, tagged with the index of their task, either as they
+/// arrive or, if `ordered`, in the order of their tasks.
+#[pin_project]
+pub(crate) struct InOrder<S, T> {
+	#[pin]
+	stream: S,
+	ordered: bool,
+	next: usize,
+	pending: BTreeMap<usize, T>,
+}
+impl<S, T> InOrder<S, T> {
+	pub(crate) fn new(stream: S, ordered: bool) -> Self {
+		Self {
+			stream,
+			ordered,
+			next: 0,
+			pending: BTreeMap::new(),
+		}
+	}
+}
+impl<S, T> Stream for InOrder<S, T>
+where
+	S: Stream<Item = (usize, T)>,
+{
+	type Item = T;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		loop {
+			if let Some(item) = self_.pending.remove(self_.next) {
+				*self_.next += 1;
+				break Poll::Ready(Some(item));
+			}
+			match ready!(self_.stream.as_mut().poll_next(cx)) {
+				Some((_, item)) if !*self_.ordered => break Poll::Ready(Some(item)),
+				Some((index, item)) => {
+					let _ = self_.pending.insert(index, item);
+				}
+				None => break Poll::Ready(None),
+			}
+		}
+	}
+}
+
 pub struct ResultExpand<T, E>(pub Result<T, E>);
 impl<T, E> IntoIterator for ResultExpand<T, E>
 where
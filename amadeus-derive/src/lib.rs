@@ -384,7 +384,7 @@ fn impl_struct(
 	let mut postgres_derives = None;
 	if cfg!(feature = "postgres") {
 		postgres_includes = Some(quote! {
-			pub use #amadeus_path::amadeus_postgres::{Names,read_be_i32,read_value,_internal as postgres,PostgresData};
+			pub use #amadeus_path::amadeus_postgres::{Names,read_be_i32,read_value,write_value,_internal as postgres,PostgresData,PostgresRow};
 		});
 		postgres_derives = Some(quote! {
 			#[automatically_derived]
@@ -428,6 +428,34 @@ fn impl_struct(
 						)*
 					})
 				}
+				fn type_name(_f: &mut __::fmt::Formatter) -> __::fmt::Result {
+					__::Err(__::fmt::Error)
+				}
+				fn encode(&self, _buf: &mut __::postgres::types::private::BytesMut) -> __::Result<__::postgres::types::IsNull, __::Box<__::Error + __::Sync + __::Send>> {
+					__::Err(__::Into::into(__::format!("{} can't be written to Postgres as a column. Tracking at https://github.com/constellation-rs/amadeus/issues/63", #name_str)))
+				}
+			}
+			#[automatically_derived]
+			impl #impl_generics __::PostgresRow for #name #ty_generics #where_clause_with_postgres_data {
+				fn columns(f: &mut __::fmt::Formatter, types: bool) -> __::fmt::Result {
+					let mut comma = false;
+					#(
+						if comma { __::Write::write_str(f, ",")? } comma = true;
+						__::fmt::Display::fmt(&__::Names(__::None, #field_renames1), f)?;
+						if types {
+							__::Write::write_str(f, " ")?;
+							<#field_types1 as __::PostgresData>::type_name(f)?;
+						}
+					)*
+					__::Ok(())
+				}
+				fn encode_row(&self, buf: &mut __::postgres::types::private::BytesMut) -> __::Result<(), __::Box<__::Error + __::Sync + __::Send>> {
+					buf.extend_from_slice(&(#num_fields as i16).to_be_bytes());
+					#(
+						__::write_value(&self.#field_names1, buf)?;
+					)*
+					__::Ok(())
+				}
 			}
 		});
 	}
@@ -474,7 +502,7 @@ fn impl_struct(
 			pub use #amadeus_path::amadeus_core::util::Wrapper;
 			pub use #amadeus_path::amadeus_types::{AmadeusOrd, Data as CoreData, DowncastFrom, Downcast, DowncastError, Value, Group, SchemaIncomplete, ListVec, __internal::{Serialize as Serialize_, Deserialize as Deserialize_, Serializer as Serializer_, Deserializer as Deserializer_, SerializeTuple, Error as SerdeError, Visitor, SeqAccess}};
 			pub use #amadeus_path::data::Data;
-			pub use ::std::{borrow::ToOwned, boxed::Box, clone::Clone, collections::HashMap, convert::{From, Into}, cmp::{Ordering, PartialEq}, default::Default, error::Error, fmt::{self, Debug, Write}, format, hash::{Hash, Hasher}, iter::{ExactSizeIterator, IntoIterator, Iterator}, marker::{PhantomData, Send, Sized, Sync}, result::Result::{self, Ok, Err}, string::String, panic, vec, vec::{IntoIter, Vec}, option::Option::{self, Some, None}};
+			pub use ::std::{borrow::ToOwned, boxed::Box, clone::Clone, collections::HashMap, convert::{From, Into}, cmp::{Ordering, PartialEq}, default::Default, error::Error, fmt::{self, Debug, Write}, format, hash::{Hash, Hasher}, iter::{ExactSizeIterator, IntoIterator, Iterator}, marker::{PhantomData, Send, Sized, Sync}, result::Result::{self, Ok, Err}, string::String, panic, todo, vec, vec::{IntoIter, Vec}, option::Option::{self, Some, None}};
 		}
 
 		#parquet_derives
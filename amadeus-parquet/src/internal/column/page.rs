@@ -18,7 +18,7 @@
 //! Contains Parquet Page definitions and page reader interface.
 
 use crate::internal::{
-	basic::{Encoding, PageType}, errors::Result, file::{metadata::ColumnChunkMetaData, statistics::Statistics}, schema::types::{ColumnDescPtr, SchemaDescPtr}, util::memory::ByteBufferPtr
+	basic::{Encoding, PageType}, errors::Result, file::{bloom_filter::BloomFilter, metadata::ColumnChunkMetaData, statistics::Statistics}, schema::types::{ColumnDescPtr, SchemaDescPtr}, util::memory::ByteBufferPtr
 };
 
 /// Parquet Page definition.
@@ -211,6 +211,13 @@ pub trait PageWriter {
 	/// finalised in column writer.
 	fn write_metadata(&mut self, metadata: &ColumnChunkMetaData) -> Result<()>;
 
+	/// Writes the Bloom filter of a column chunk into the output stream/sink.
+	/// Returns the offset it was written at and the number of bytes written.
+	///
+	/// This method is called at most once, after all pages have been written and
+	/// before the column chunk metadata is.
+	fn write_bloom_filter(&mut self, bloom_filter: &BloomFilter) -> Result<(u64, u64)>;
+
 	/// Closes resources and flushes underlying sink.
 	/// Page writer should not be used after this method is called.
 	fn close(&mut self) -> Result<()>;
@@ -17,14 +17,16 @@
 
 //! Contains column writer API.
 
+use byteorder::{ByteOrder, LittleEndian};
+use fxhash::FxHashSet;
 use std::{cmp, collections::VecDeque, mem, rc::Rc};
 
 use crate::internal::{
-	basic::{Compression, Encoding, PageType, Type}, column::page::{CompressedPage, Page, PageWriteSpec, PageWriter}, compression::{create_codec, Codec}, data_type::*, encodings::{
+	basic::{ColumnOrder, Compression, Encoding, PageType, SortOrder, Type}, column::page::{CompressedPage, Page, PageWriteSpec, PageWriter}, compression::{create_codec, Codec}, data_type::*, encodings::{
 		encoding::{get_encoder, DictEncoder, Encoder}, levels::{max_buffer_size, LevelEncoder}
 	}, errors::{ParquetError, Result}, file::{
-		metadata::ColumnChunkMetaData, properties::{WriterProperties, WriterPropertiesPtr, WriterVersion}
-	}, schema::types::ColumnDescPtr, util::memory::{ByteBufferPtr, MemTracker}
+		bloom_filter::BloomFilter, metadata::ColumnChunkMetaData, properties::{WriterProperties, WriterPropertiesPtr, WriterVersion}, statistics::{self, Statistics}
+	}, format::Statistics as TStatistics, schema::types::ColumnDescPtr, util::memory::{ByteBufferPtr, MemTracker}
 };
 
 /// Column writer for a Parquet type.
@@ -112,6 +114,15 @@ pub struct ColumnWriterImpl<T: DataType> {
 	total_num_values: u64,
 	dictionary_page_offset: Option<u64>,
 	data_page_offset: Option<u64>,
+	// Hashes of the distinct values written, if a Bloom filter is to be written
+	bloom_filter_hashes: Option<FxHashSet<u64>>,
+	// The order values are compared in for the column chunk's statistics, if they're
+	// enabled, and the least and greatest values written, plain-encoded, and the number
+	// of nulls
+	statistics_order: Option<SortOrder>,
+	min_value: Option<Vec<u8>>,
+	max_value: Option<Vec<u8>>,
+	null_count: u64,
 	// Reused buffers
 	def_levels_sink: Vec<i16>,
 	rep_levels_sink: Vec<i16>,
@@ -136,6 +147,23 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		// Whether or not this column writer has a dictionary encoding.
 		let has_dictionary = dict_encoder.is_some();
 
+		// The Bloom filter is sized for the number of distinct values once they're all
+		// written, so their hashes are collected until then.
+		let bloom_filter_hashes = if props.bloom_filter_enabled(descr.path()) {
+			Some(FxHashSet::default())
+		} else {
+			None
+		};
+
+		let statistics_order = if props.statistics_enabled(descr.path()) {
+			Some(ColumnOrder::get_sort_order(
+				descr.logical_type(),
+				descr.physical_type(),
+			))
+		} else {
+			None
+		};
+
 		// Set either main encoder or fallback encoder.
 		let fallback_encoder = get_encoder(
 			descr.clone(),
@@ -165,6 +193,11 @@ impl<T: DataType> ColumnWriterImpl<T> {
 			total_num_values: 0,
 			dictionary_page_offset: None,
 			data_page_offset: None,
+			bloom_filter_hashes,
+			statistics_order,
+			min_value: None,
+			max_value: None,
+			null_count: 0,
 			def_levels_sink: vec![],
 			rep_levels_sink: vec![],
 			data_pages: VecDeque::new(),
@@ -250,7 +283,8 @@ impl<T: DataType> ColumnWriterImpl<T> {
 			self.write_dictionary_page()?;
 		}
 		self.flush_data_pages()?;
-		let metadata = self.write_column_metadata()?;
+		let bloom_filter = self.write_bloom_filter()?;
+		let metadata = self.write_column_metadata(bloom_filter)?;
 		self.dict_encoder = None;
 		self.page_writer.close()?;
 
@@ -334,6 +368,20 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		}
 
 		// TODO: update page statistics
+		self.null_count += (num_values - values_to_write) as u64;
+		if let Some(order) = self.statistics_order {
+			for value in &values[0..values_to_write] {
+				self.update_min_max(order, value);
+			}
+		}
+
+		if let Some(ref mut hashes) = self.bloom_filter_hashes {
+			hashes.extend(
+				values[0..values_to_write]
+					.iter()
+					.map(|value| BloomFilter::hash(value.as_bytes())),
+			);
+		}
 
 		self.write_values(&values[0..values_to_write])?;
 
@@ -351,6 +399,50 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		Ok(values_to_write)
 	}
 
+	/// Updates the least and greatest values written with `value`, unless it can't be
+	/// compared in `order`, like NaN.
+	fn update_min_max(&mut self, order: SortOrder, value: &T::Type) {
+		let physical_type = self.descr.physical_type();
+		let value = value.as_bytes();
+		if compare_plain(physical_type, order, value, value).is_none() {
+			return;
+		}
+		let compare = |bound: &Option<Vec<u8>>| {
+			bound
+				.as_ref()
+				.and_then(|bound| compare_plain(physical_type, order, value, bound))
+		};
+		if self.min_value.is_none() || compare(&self.min_value) == Some(cmp::Ordering::Less) {
+			replace(&mut self.min_value, value);
+		}
+		if self.max_value.is_none() || compare(&self.max_value) == Some(cmp::Ordering::Greater) {
+			replace(&mut self.max_value, value);
+		}
+	}
+
+	/// The column chunk's statistics, leaving out the least and greatest values if
+	/// either is longer than the maximum statistics size.
+	fn statistics(&self) -> Option<Statistics> {
+		let max_size = self.props.max_statistics_size(self.descr.path());
+		let (min_value, max_value) = match (&self.min_value, &self.max_value) {
+			(Some(min), Some(max)) if min.len() <= max_size && max.len() <= max_size => {
+				(Some(min.clone()), Some(max.clone()))
+			}
+			_ => (None, None),
+		};
+		statistics::from_thrift(
+			self.descr.physical_type(),
+			Some(TStatistics {
+				max: None,
+				min: None,
+				null_count: Some(self.null_count as i64),
+				distinct_count: None,
+				max_value,
+				min_value,
+			}),
+		)
+	}
+
 	#[inline]
 	fn write_definition_levels(&mut self, def_levels: &[i16]) {
 		self.def_levels_sink.extend_from_slice(def_levels);
@@ -553,8 +645,27 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		Ok(())
 	}
 
+	/// Writes the Bloom filter of the values written, if enabled.
+	/// Returns the offset and length in bytes of the written filter.
+	fn write_bloom_filter(&mut self) -> Result<Option<(u64, u64)>> {
+		let hashes = match self.bloom_filter_hashes.take() {
+			Some(hashes) => hashes,
+			None => return Ok(None),
+		};
+		let mut bloom_filter = BloomFilter::with_num_distinct(
+			hashes.len() as u64,
+			self.props.bloom_filter_fpp(self.descr.path()),
+		);
+		for hash in hashes {
+			bloom_filter.insert(hash);
+		}
+		self.page_writer.write_bloom_filter(&bloom_filter).map(Some)
+	}
+
 	/// Assembles and writes column chunk metadata.
-	fn write_column_metadata(&mut self) -> Result<ColumnChunkMetaData> {
+	fn write_column_metadata(
+		&mut self, bloom_filter: Option<(u64, u64)>,
+	) -> Result<ColumnChunkMetaData> {
 		let total_compressed_size = self.total_compressed_size as i64;
 		let total_uncompressed_size = self.total_uncompressed_size as i64;
 		let num_values = self.total_num_values as i64;
@@ -562,7 +673,7 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		// If data page offset is not set, then no pages have been written
 		let data_page_offset = self.data_page_offset.unwrap_or(0) as i64;
 
-		let file_offset;
+		let mut file_offset;
 		let mut encodings = Vec::new();
 
 		if self.has_dictionary {
@@ -582,7 +693,13 @@ impl<T: DataType> ColumnWriterImpl<T> {
 		// We use only RLE level encoding for data page v1 and data page v2.
 		encodings.push(Encoding::Rle);
 
-		let metadata = ColumnChunkMetaData::builder(self.descr.clone())
+		// The Bloom filter is written between the pages and the metadata.
+		let bloom_filter_offset = bloom_filter.map(|(offset, len)| {
+			file_offset = (offset + len) as i64;
+			offset as i64
+		});
+
+		let mut metadata = ColumnChunkMetaData::builder(self.descr.clone())
 			.set_compression(self.codec)
 			.set_encodings(encodings)
 			.set_file_offset(file_offset)
@@ -591,7 +708,13 @@ impl<T: DataType> ColumnWriterImpl<T> {
 			.set_num_values(num_values)
 			.set_data_page_offset(data_page_offset)
 			.set_dictionary_page_offset(dict_page_offset)
-			.build()?;
+			.set_bloom_filter_offset(bloom_filter_offset);
+		if self.statistics_order.is_some() {
+			if let Some(statistics) = self.statistics() {
+				metadata = metadata.set_statistics(statistics);
+			}
+		}
+		let metadata = metadata.build()?;
 
 		self.page_writer.write_metadata(&metadata)?;
 
@@ -780,6 +903,46 @@ impl EncodingWriteSupport for ColumnWriterImpl<FixedLenByteArrayType> {
 	}
 }
 
+/// Sets `bound` to `value`, reusing its allocation.
+fn replace(bound: &mut Option<Vec<u8>>, value: &[u8]) {
+	let bound = bound.get_or_insert_with(Vec::new);
+	bound.clear();
+	bound.extend_from_slice(value);
+}
+
+/// Compares two plain-encoded values of a column of `physical_type` in `order`, or
+/// returns `None` if they can't be, either because one is NaN or because statistics
+/// aren't kept for that type in that order.
+fn compare_plain(
+	physical_type: Type, order: SortOrder, a: &[u8], b: &[u8],
+) -> Option<cmp::Ordering> {
+	match (physical_type, order) {
+		(Type::Boolean, _) => Some(a[0].cmp(&b[0])),
+		(Type::Int32, SortOrder::Signed) => {
+			Some(LittleEndian::read_i32(a).cmp(&LittleEndian::read_i32(b)))
+		}
+		(Type::Int32, SortOrder::Unsigned) => {
+			Some(LittleEndian::read_u32(a).cmp(&LittleEndian::read_u32(b)))
+		}
+		(Type::Int64, SortOrder::Signed) => {
+			Some(LittleEndian::read_i64(a).cmp(&LittleEndian::read_i64(b)))
+		}
+		(Type::Int64, SortOrder::Unsigned) => {
+			Some(LittleEndian::read_u64(a).cmp(&LittleEndian::read_u64(b)))
+		}
+		(Type::Float, SortOrder::Signed) => {
+			LittleEndian::read_f32(a).partial_cmp(&LittleEndian::read_f32(b))
+		}
+		(Type::Double, SortOrder::Signed) => {
+			LittleEndian::read_f64(a).partial_cmp(&LittleEndian::read_f64(b))
+		}
+		(Type::ByteArray, SortOrder::Unsigned) | (Type::FixedLenByteArray, SortOrder::Unsigned) => {
+			Some(a.cmp(b))
+		}
+		_ => None,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -1195,6 +1358,45 @@ mod tests {
 		assert_eq!(metadata.dictionary_page_offset(), Some(0));
 	}
 
+	#[test]
+	fn test_column_writer_statistics() {
+		let props = Rc::new(WriterProperties::builder().build());
+		let mut writer = get_test_column_writer::<Int32Type>(get_test_page_writer(), 1, 0, props);
+		let _ = writer
+			.write_batch(&[3, -7, 5], Some(&[1, 0, 1, 1, 0]), None)
+			.unwrap();
+		let (_, _, metadata) = writer.close().unwrap();
+		let stats = metadata.statistics().unwrap();
+		assert!(!stats.is_min_max_deprecated());
+		assert_eq!(stats.null_count(), 2);
+		match stats {
+			Statistics::Int32(stats) => assert_eq!((*stats.min(), *stats.max()), (-7, 5)),
+			_ => panic!("Expected Int32 statistics"),
+		}
+
+		// NaN can't be ordered, so is left out
+		let props = Rc::new(WriterProperties::builder().build());
+		let mut writer = get_test_column_writer::<DoubleType>(get_test_page_writer(), 0, 0, props);
+		let _ = writer
+			.write_batch(&[1.5, f64::NAN, -0.5], None, None)
+			.unwrap();
+		let (_, _, metadata) = writer.close().unwrap();
+		match metadata.statistics().unwrap() {
+			Statistics::Double(stats) => assert_eq!((*stats.min(), *stats.max()), (-0.5, 1.5)),
+			_ => panic!("Expected Double statistics"),
+		}
+
+		let props = Rc::new(
+			WriterProperties::builder()
+				.set_statistics_enabled(false)
+				.build(),
+		);
+		let mut writer = get_test_column_writer::<Int32Type>(get_test_page_writer(), 0, 0, props);
+		let _ = writer.write_batch(&[1, 2, 3], None, None).unwrap();
+		let (_, _, metadata) = writer.close().unwrap();
+		assert!(metadata.statistics().is_none());
+	}
+
 	#[test]
 	fn test_column_writer_empty_column_roundtrip() {
 		let props = WriterProperties::builder().build();
@@ -1636,6 +1838,10 @@ mod tests {
 			Ok(())
 		}
 
+		fn write_bloom_filter(&mut self, bloom_filter: &BloomFilter) -> Result<(u64, u64)> {
+			Ok((0, bloom_filter.num_bytes() as u64))
+		}
+
 		fn close(&mut self) -> Result<()> {
 			Ok(())
 		}
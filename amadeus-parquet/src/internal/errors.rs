@@ -25,7 +25,7 @@ use thrift;
 
 quick_error! {
   /// Set of errors that can be produced during different operations in Parquet.
-  #[derive(Debug, PartialEq)]
+  #[derive(Clone, Debug, PartialEq)]
   pub enum ParquetError {
 	  /// General Parquet error.
 	  /// Returned when code violates normal workflow of working with Parquet files.
@@ -0,0 +1,236 @@
+//! Split-block Bloom filters, which record the values of a column chunk so that
+//! equality filters can rule out the chunk when its statistics can't.
+//!
+//! Filters are as described by the Parquet specification: a number of 256-bit blocks,
+//! into which values are inserted by the 64-bit xxHash of their plain encoding. In a
+//! file, each is a Thrift `BloomFilterHeader` followed by its bitset, at the offset
+//! given by its column chunk's metadata.
+
+use std::{
+	hash::Hasher, io::{Read, Write}
+};
+
+use thrift::protocol::{TCompactInputProtocol, TCompactOutputProtocol, TOutputProtocol};
+use twox_hash::XxHash64;
+
+use crate::internal::{
+	errors::{ParquetError, Result}, format::{
+		BloomFilterAlgorithm, BloomFilterCompression, BloomFilterHash, BloomFilterHeader, SplitBlockAlgorithm, Uncompressed, XxHash
+	}
+};
+
+/// The number of bytes in a block.
+const BLOCK_SIZE: usize = 32;
+/// The largest filter in bytes, as recommended by the specification.
+const MAX_SIZE: usize = 128 * 1024 * 1024;
+/// The odd constants that the bit set in each word of a block is derived with.
+const SALT: [u32; 8] = [
+	0x47b6_137b,
+	0x4497_4d91,
+	0x8824_ad5b,
+	0xa2b7_289d,
+	0x7054_95c7,
+	0x2df1_424b,
+	0x9efc_4947,
+	0x5c6b_fb31,
+];
+
+type Block = [u32; 8];
+
+/// A split-block Bloom filter of the values of a column chunk.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BloomFilter {
+	blocks: Vec<Block>,
+}
+
+impl BloomFilter {
+	/// Creates an empty filter of `num_bytes` bytes, rounded up to a power of two
+	/// between 32 bytes and 128 MiB.
+	pub fn new(num_bytes: usize) -> Self {
+		let num_bytes = num_bytes.max(BLOCK_SIZE).next_power_of_two().min(MAX_SIZE);
+		Self {
+			blocks: vec![[0; 8]; num_bytes / BLOCK_SIZE],
+		}
+	}
+
+	/// Creates an empty filter big enough to hold `num_distinct` values with a false
+	/// positive probability of at most about `fpp`.
+	pub fn with_num_distinct(num_distinct: u64, fpp: f64) -> Self {
+		assert!(
+			fpp > 0.0 && fpp < 1.0,
+			"false positive probability must be between 0 and 1"
+		);
+		let num_bits = -8.0 * num_distinct as f64 / (1.0 - fpp.powf(1.0 / 8.0)).ln();
+		Self::new((num_bits / 8.0).min(MAX_SIZE as f64) as usize)
+	}
+
+	/// Returns the size of the filter's bitset in bytes.
+	pub fn num_bytes(&self) -> usize {
+		self.blocks.len() * BLOCK_SIZE
+	}
+
+	/// Hashes a value given by its plain encoding, without the length prefix of byte
+	/// arrays, as it's hashed to be inserted into or looked up in a filter.
+	pub fn hash(value: &[u8]) -> u64 {
+		let mut hasher = XxHash64::with_seed(0);
+		hasher.write(value);
+		hasher.finish()
+	}
+
+	/// Inserts the value with hash `hash`.
+	pub fn insert(&mut self, hash: u64) {
+		let index = self.block_index(hash);
+		for (word, mask) in self.blocks[index].iter_mut().zip(&mask(hash as u32)) {
+			*word |= mask;
+		}
+	}
+
+	/// Returns `false` if the value with hash `hash` definitely hasn't been inserted,
+	/// or `true` if it might have been.
+	pub fn may_contain(&self, hash: u64) -> bool {
+		self.blocks[self.block_index(hash)]
+			.iter()
+			.zip(&mask(hash as u32))
+			.all(|(word, mask)| word & mask != 0)
+	}
+
+	fn block_index(&self, hash: u64) -> usize {
+		(((hash >> 32) * self.blocks.len() as u64) >> 32) as usize
+	}
+
+	/// Reads a filter: its header, followed by its bitset.
+	pub fn read<R: Read>(reader: &mut R) -> Result<Self> {
+		let header = {
+			let mut prot = TCompactInputProtocol::new(&mut *reader);
+			BloomFilterHeader::read_from_in_protocol(&mut prot)?
+		};
+		let mut bitset = vec![0; bitset_len(&header)?];
+		reader.read_exact(&mut bitset)?;
+		Ok(Self::from_bitset(&bitset))
+	}
+
+	/// Reads the header of a filter from the start of `bytes`, returning the length of
+	/// the header and the length of the bitset that follows it.
+	pub(crate) fn read_header(bytes: &[u8]) -> Result<(usize, usize)> {
+		let mut reader = bytes;
+		let header = {
+			let mut prot = TCompactInputProtocol::new(&mut reader);
+			BloomFilterHeader::read_from_in_protocol(&mut prot)?
+		};
+		Ok((bytes.len() - reader.len(), bitset_len(&header)?))
+	}
+
+	/// Creates a filter from its bitset, whose length must be a multiple of 32 bytes.
+	pub(crate) fn from_bitset(bitset: &[u8]) -> Self {
+		let blocks = bitset
+			.chunks_exact(BLOCK_SIZE)
+			.map(|block| {
+				let mut words = [0; 8];
+				for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+					*word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+				}
+				words
+			})
+			.collect();
+		Self { blocks }
+	}
+
+	/// Writes the filter: its header, followed by its bitset. Returns the number of
+	/// bytes written.
+	pub fn write<W: Write>(&self, writer: &mut W) -> Result<usize> {
+		let header = BloomFilterHeader {
+			num_bytes: self.num_bytes() as i32,
+			algorithm: BloomFilterAlgorithm::BLOCK(SplitBlockAlgorithm::new()),
+			hash: BloomFilterHash::XXHASH(XxHash::new()),
+			compression: BloomFilterCompression::UNCOMPRESSED(Uncompressed::new()),
+		};
+		let mut buf = Vec::with_capacity(16 + self.num_bytes());
+		{
+			let mut protocol = TCompactOutputProtocol::new(&mut buf);
+			header.write_to_out_protocol(&mut protocol)?;
+			protocol.flush()?;
+		}
+		for word in self.blocks.iter().flat_map(|block| block.iter()) {
+			buf.extend_from_slice(&word.to_le_bytes());
+		}
+		writer.write_all(&buf)?;
+		Ok(buf.len())
+	}
+}
+
+/// The bits to set in each word of a block for a value, whose hash's lower 32 bits
+/// are `key`.
+fn mask(key: u32) -> Block {
+	let mut mask = [0; 8];
+	for (mask, salt) in mask.iter_mut().zip(&SALT) {
+		*mask = 1 << (key.wrapping_mul(*salt) >> 27);
+	}
+	mask
+}
+
+fn bitset_len(header: &BloomFilterHeader) -> Result<usize> {
+	let num_bytes = header.num_bytes;
+	if num_bytes <= 0 || num_bytes as usize % BLOCK_SIZE != 0 || num_bytes as usize > MAX_SIZE {
+		return Err(general_err!("Invalid Bloom filter size {}", num_bytes));
+	}
+	Ok(num_bytes as usize)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bloom_filter_hash() {
+		// Test vectors of 64-bit xxHash with a seed of 0
+		assert_eq!(BloomFilter::hash(b""), 0xef46_db37_51d8_e999);
+		assert_eq!(BloomFilter::hash(b"a"), 0xd24e_c4f1_a98c_6e5b);
+	}
+
+	#[test]
+	fn test_bloom_filter_size() {
+		assert_eq!(BloomFilter::new(0).num_bytes(), 32);
+		assert_eq!(BloomFilter::new(100).num_bytes(), 128);
+		assert_eq!(
+			BloomFilter::new(usize::max_value() / 2).num_bytes(),
+			MAX_SIZE
+		);
+		assert_eq!(BloomFilter::with_num_distinct(0, 0.01).num_bytes(), 32);
+		let filter = BloomFilter::with_num_distinct(1_000_000, 0.01);
+		assert!(filter.num_bytes() >= 1_000_000 && filter.num_bytes() <= 4_000_000);
+	}
+
+	#[test]
+	fn test_bloom_filter_insert() {
+		let mut filter = BloomFilter::with_num_distinct(1000, 0.01);
+		for i in 0..1000_i64 {
+			filter.insert(BloomFilter::hash(&i.to_le_bytes()));
+		}
+		for i in 0..1000_i64 {
+			assert!(filter.may_contain(BloomFilter::hash(&i.to_le_bytes())));
+		}
+		let false_positives = (1000..11000_i64)
+			.filter(|i| filter.may_contain(BloomFilter::hash(&i.to_le_bytes())))
+			.count();
+		assert!(false_positives < 300, "{}", false_positives);
+	}
+
+	#[test]
+	fn test_bloom_filter_roundtrip() {
+		let mut filter = BloomFilter::new(1024);
+		for value in &["a", "b", "c"] {
+			filter.insert(BloomFilter::hash(value.as_bytes()));
+		}
+		let mut buf = Vec::new();
+		let len = filter.write(&mut buf).unwrap();
+		assert_eq!(len, buf.len());
+
+		let (header_len, bitset_len) = BloomFilter::read_header(&buf).unwrap();
+		assert_eq!(header_len + bitset_len, buf.len());
+		assert_eq!(BloomFilter::from_bitset(&buf[header_len..]), filter);
+		assert_eq!(BloomFilter::read(&mut &*buf).unwrap(), filter);
+
+		assert!(BloomFilter::read(&mut &buf[..buf.len() - 1]).is_err());
+		assert!(BloomFilter::read_header(&buf[..2]).is_err());
+	}
+}
@@ -96,11 +96,12 @@
 //!     println!("{}", row);
 //! }
 //! ```
+pub mod bloom_filter;
 pub mod metadata;
 pub mod properties;
 pub mod reader;
 pub mod statistics;
 pub mod writer;
 
-const FOOTER_SIZE: usize = 8;
+pub(crate) const FOOTER_SIZE: usize = 8;
 const PARQUET_MAGIC: [u8; 4] = [b'P', b'A', b'R', b'1'];
@@ -62,6 +62,8 @@ const DEFAULT_DICTIONARY_ENABLED: bool = true;
 const DEFAULT_DICTIONARY_PAGE_SIZE_LIMIT: usize = DEFAULT_PAGE_SIZE;
 const DEFAULT_STATISTICS_ENABLED: bool = true;
 const DEFAULT_MAX_STATISTICS_SIZE: usize = 4096;
+const DEFAULT_BLOOM_FILTER_ENABLED: bool = false;
+const DEFAULT_BLOOM_FILTER_FPP: f64 = 0.05;
 const DEFAULT_MAX_ROW_GROUP_SIZE: usize = 128 * 1024 * 1024;
 const DEFAULT_CREATED_BY: &str = concat!(
 	env!("CARGO_PKG_NAME"),
@@ -213,6 +215,26 @@ impl WriterProperties {
 			.or_else(|| self.default_column_properties.max_statistics_size())
 			.unwrap_or(DEFAULT_MAX_STATISTICS_SIZE)
 	}
+
+	/// Returns `true` if a Bloom filter is written for each chunk of a column.
+	pub fn bloom_filter_enabled(&self, col: &ColumnPath) -> bool {
+		self.column_properties
+			.get(col)
+			.and_then(|c| c.bloom_filter_enabled())
+			.or_else(|| self.default_column_properties.bloom_filter_enabled())
+			.unwrap_or(DEFAULT_BLOOM_FILTER_ENABLED)
+	}
+
+	/// Returns the false positive probability that Bloom filters for a column are
+	/// sized for.
+	/// Only applicable if Bloom filters are enabled.
+	pub fn bloom_filter_fpp(&self, col: &ColumnPath) -> f64 {
+		self.column_properties
+			.get(col)
+			.and_then(|c| c.bloom_filter_fpp())
+			.or_else(|| self.default_column_properties.bloom_filter_fpp())
+			.unwrap_or(DEFAULT_BLOOM_FILTER_FPP)
+	}
 }
 
 /// Writer properties builder.
@@ -340,6 +362,22 @@ impl WriterPropertiesBuilder {
 		self
 	}
 
+	/// Sets flag to enable/disable Bloom filters for any column.
+	pub fn set_bloom_filter_enabled(mut self, value: bool) -> Self {
+		self.default_column_properties
+			.set_bloom_filter_enabled(value);
+		self
+	}
+
+	/// Sets the false positive probability of Bloom filters for any column.
+	/// Applicable only if Bloom filters are enabled.
+	///
+	/// Panics if the probability isn't between 0 and 1.
+	pub fn set_bloom_filter_fpp(mut self, value: f64) -> Self {
+		self.default_column_properties.set_bloom_filter_fpp(value);
+		self
+	}
+
 	// ----------------------------------------------------------------------
 	// Setters for a specific column
 
@@ -393,6 +431,22 @@ impl WriterPropertiesBuilder {
 		self.get_mut_props(col).set_max_statistics_size(value);
 		self
 	}
+
+	/// Sets flag to enable/disable Bloom filters for a column.
+	/// Takes precedence over globally defined settings.
+	pub fn set_column_bloom_filter_enabled(mut self, col: ColumnPath, value: bool) -> Self {
+		self.get_mut_props(col).set_bloom_filter_enabled(value);
+		self
+	}
+
+	/// Sets the false positive probability of Bloom filters for a column.
+	/// Takes precedence over globally defined settings.
+	///
+	/// Panics if the probability isn't between 0 and 1.
+	pub fn set_column_bloom_filter_fpp(mut self, col: ColumnPath, value: f64) -> Self {
+		self.get_mut_props(col).set_bloom_filter_fpp(value);
+		self
+	}
 }
 
 /// Container for column properties that can be changed as part of writer.
@@ -406,6 +460,8 @@ struct ColumnProperties {
 	dictionary_enabled: Option<bool>,
 	statistics_enabled: Option<bool>,
 	max_statistics_size: Option<usize>,
+	bloom_filter_enabled: Option<bool>,
+	bloom_filter_fpp: Option<f64>,
 }
 
 impl ColumnProperties {
@@ -417,6 +473,8 @@ impl ColumnProperties {
 			dictionary_enabled: None,
 			statistics_enabled: None,
 			max_statistics_size: None,
+			bloom_filter_enabled: None,
+			bloom_filter_fpp: None,
 		}
 	}
 
@@ -456,6 +514,21 @@ impl ColumnProperties {
 		self.max_statistics_size = Some(value);
 	}
 
+	/// Sets whether or not Bloom filters are enabled for this column.
+	fn set_bloom_filter_enabled(&mut self, enabled: bool) {
+		self.bloom_filter_enabled = Some(enabled);
+	}
+
+	/// Sets the false positive probability of Bloom filters for this column.
+	///
+	/// Panics if the probability isn't between 0 and 1.
+	fn set_bloom_filter_fpp(&mut self, value: f64) {
+		if !(value > 0.0 && value < 1.0) {
+			panic!("Bloom filter false positive probability must be between 0 and 1");
+		}
+		self.bloom_filter_fpp = Some(value);
+	}
+
 	/// Returns optional encoding for this column.
 	fn encoding(&self) -> Option<Encoding> {
 		self.encoding
@@ -483,6 +556,18 @@ impl ColumnProperties {
 	fn max_statistics_size(&self) -> Option<usize> {
 		self.max_statistics_size
 	}
+
+	/// Returns `Some(true)` if Bloom filters are enabled for this column, if disabled
+	/// then returns `Some(false)`. If result is `None`, then no setting has been
+	/// provided.
+	fn bloom_filter_enabled(&self) -> Option<bool> {
+		self.bloom_filter_enabled
+	}
+
+	/// Returns optional false positive probability of Bloom filters.
+	fn bloom_filter_fpp(&self) -> Option<f64> {
+		self.bloom_filter_fpp
+	}
 }
 
 #[cfg(test)]
@@ -524,6 +609,14 @@ mod tests {
 			props.max_statistics_size(&ColumnPath::from("col")),
 			DEFAULT_MAX_STATISTICS_SIZE
 		);
+		assert_eq!(
+			props.bloom_filter_enabled(&ColumnPath::from("col")),
+			DEFAULT_BLOOM_FILTER_ENABLED
+		);
+		assert_eq!(
+			props.bloom_filter_fpp(&ColumnPath::from("col")),
+			DEFAULT_BLOOM_FILTER_FPP
+		);
 	}
 
 	#[test]
@@ -594,12 +687,15 @@ mod tests {
 			.set_dictionary_enabled(false)
 			.set_statistics_enabled(false)
 			.set_max_statistics_size(50)
+			.set_bloom_filter_fpp(0.1)
 			// specific column settings
 			.set_column_encoding(ColumnPath::from("col"), Encoding::Rle)
 			.set_column_compression(ColumnPath::from("col"), Compression::Snappy)
 			.set_column_dictionary_enabled(ColumnPath::from("col"), true)
 			.set_column_statistics_enabled(ColumnPath::from("col"), true)
 			.set_column_max_statistics_size(ColumnPath::from("col"), 123)
+			.set_column_bloom_filter_enabled(ColumnPath::from("col"), true)
+			.set_column_bloom_filter_fpp(ColumnPath::from("col"), 0.01)
 			.build();
 
 		assert_eq!(props.writer_version(), WriterVersion::Parquet2_0);
@@ -617,6 +713,8 @@ mod tests {
 		assert_eq!(props.dictionary_enabled(&ColumnPath::from("a")), false);
 		assert_eq!(props.statistics_enabled(&ColumnPath::from("a")), false);
 		assert_eq!(props.max_statistics_size(&ColumnPath::from("a")), 50);
+		assert_eq!(props.bloom_filter_enabled(&ColumnPath::from("a")), false);
+		assert_eq!(props.bloom_filter_fpp(&ColumnPath::from("a")), 0.1);
 
 		assert_eq!(
 			props.encoding(&ColumnPath::from("col")),
@@ -629,6 +727,16 @@ mod tests {
 		assert_eq!(props.dictionary_enabled(&ColumnPath::from("col")), true);
 		assert_eq!(props.statistics_enabled(&ColumnPath::from("col")), true);
 		assert_eq!(props.max_statistics_size(&ColumnPath::from("col")), 123);
+		assert_eq!(props.bloom_filter_enabled(&ColumnPath::from("col")), true);
+		assert_eq!(props.bloom_filter_fpp(&ColumnPath::from("col")), 0.01);
+	}
+
+	#[test]
+	#[should_panic(expected = "Bloom filter false positive probability must be between 0 and 1")]
+	fn test_writer_properties_panic_when_bloom_filter_fpp_is_invalid() {
+		let _ = WriterProperties::builder()
+			.set_column_bloom_filter_fpp(ColumnPath::from("col"), 1.0)
+			.build();
 	}
 
 	#[test]
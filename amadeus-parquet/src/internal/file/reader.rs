@@ -28,7 +28,7 @@ use thrift::protocol::TCompactInputProtocol;
 use crate::internal::{
 	basic::{ColumnOrder, Compression, Encoding, Type}, column::{
 		page::{Page, PageReader}, reader::{ColumnReader, ColumnReaderImpl}
-	}, compression::{create_codec, Codec}, errors::{ParquetError, Result}, file::{metadata::*, statistics, FOOTER_SIZE, PARQUET_MAGIC}, format::{ColumnOrder as TColumnOrder, FileMetaData as TFileMetaData, PageHeader, PageType}, record::{ParquetData, RowIter}, schema::types::{self, SchemaDescriptor}, util::{
+	}, compression::{create_codec, Codec}, errors::{ParquetError, Result}, file::{bloom_filter::BloomFilter, metadata::*, statistics, FOOTER_SIZE, PARQUET_MAGIC}, format::{ColumnOrder as TColumnOrder, FileMetaData as TFileMetaData, PageHeader, PageType}, record::{ParquetData, RowIter}, schema::types::{self, SchemaDescriptor}, util::{
 		io::{BufReader, FileSource}, memory::ByteBufferPtr
 	}
 };
@@ -122,6 +122,9 @@ pub trait RowGroupReader {
 	/// Get value reader for the `i`th column chunk.
 	fn get_column_reader(&self, i: usize) -> Result<ColumnReader>;
 
+	/// Get the Bloom filter of the `i`th column chunk, if it has one.
+	fn get_column_bloom_filter(&self, i: usize) -> Result<Option<BloomFilter>>;
+
 	/// Get iterator of `Row`s from this row group.
 	///
 	/// [`Row`](`crate::internal::record::types::Row`) can be used for the type parameter `T` to
@@ -162,6 +165,87 @@ impl<'a> ParquetReader for Cursor<&'a [u8]> {
 	}
 }
 
+/// A Parquet file of which only some byte ranges have been read, like its footer and
+/// the column chunks of a row group. Reading outside of them returns an error.
+pub struct PartialFile {
+	len: u64,
+	ranges: Vec<(u64, Box<[u8]>)>,
+	pos: u64,
+}
+
+impl PartialFile {
+	/// Creates a file of length `len` from the `(offset, bytes)` ranges that have been
+	/// read of it, which mustn't overlap.
+	pub fn new(len: u64, mut ranges: Vec<(u64, Box<[u8]>)>) -> Self {
+		ranges.sort_by_key(|&(offset, _)| offset);
+		Self {
+			len,
+			ranges,
+			pos: 0,
+		}
+	}
+
+	fn unread(&self) -> io::Error {
+		io::Error::new(
+			io::ErrorKind::UnexpectedEof,
+			format!("byte {} of the file hasn't been read", self.pos),
+		)
+	}
+}
+
+impl Read for PartialFile {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() || self.pos >= self.len {
+			return Ok(0);
+		}
+		let index = match self
+			.ranges
+			.binary_search_by_key(&self.pos, |&(offset, _)| offset)
+		{
+			Ok(index) => index,
+			Err(0) => return Err(self.unread()),
+			Err(index) => index - 1,
+		};
+		let (offset, bytes) = &self.ranges[index];
+		let bytes = &bytes[(self.pos - offset).min(bytes.len() as u64) as usize..];
+		if bytes.is_empty() {
+			return Err(self.unread());
+		}
+		let read = bytes.len().min(buf.len());
+		buf[..read].copy_from_slice(&bytes[..read]);
+		self.pos += read as u64;
+		Ok(read)
+	}
+}
+
+impl Seek for PartialFile {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let pos = match pos {
+			SeekFrom::Start(pos) => Some(pos),
+			SeekFrom::End(offset) => checked_add(self.len, offset),
+			SeekFrom::Current(offset) => checked_add(self.pos, offset),
+		};
+		self.pos = pos.ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")
+		})?;
+		Ok(self.pos)
+	}
+}
+
+impl ParquetReader for PartialFile {
+	fn len(&self) -> u64 {
+		self.len
+	}
+}
+
+fn checked_add(pos: u64, offset: i64) -> Option<u64> {
+	if offset >= 0 {
+		pos.checked_add(offset as u64)
+	} else {
+		pos.checked_sub(offset.wrapping_neg() as u64)
+	}
+}
+
 pub enum Never {}
 impl Read for Never {
 	fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
@@ -198,6 +282,12 @@ impl<R: ParquetReader> SerializedFileReader<R> {
 		})
 	}
 
+	/// Creates file reader from a Parquet file whose metadata has already been read.
+	pub fn with_metadata(reader: R, metadata: ParquetMetaDataPtr) -> Self {
+		let buf = Rc::new(RefCell::new(BufReader::with_capacity(256 * 1024, reader)));
+		Self { buf, metadata }
+	}
+
 	// Layout of Parquet file
 	// +---------------------------+---+-----+
 	// |      Rest of file         | B |  A  |
@@ -384,12 +474,8 @@ impl<R: 'static + ParquetReader> RowGroupReader for SerializedRowGroupReader<R>
 	// TODO: fix PARQUET-816
 	fn get_column_page_reader(&self, i: usize) -> Result<Box<dyn PageReader>> {
 		let col = self.metadata.column(i);
-		let mut col_start = col.data_page_offset();
-		if col.has_dictionary_page() {
-			col_start = col.dictionary_page_offset().unwrap();
-		}
-		let col_length = col.compressed_size();
-		let file_chunk = FileSource::new(self.buf.clone(), col_start as u64, col_length as u64);
+		let (col_start, col_length) = col.byte_range();
+		let file_chunk = FileSource::new(self.buf.clone(), col_start, col_length);
 		let page_reader = SerializedPageReader::new(
 			file_chunk,
 			col.num_values(),
@@ -433,6 +519,22 @@ impl<R: 'static + ParquetReader> RowGroupReader for SerializedRowGroupReader<R>
 		Ok(col_reader)
 	}
 
+	fn get_column_bloom_filter(&self, i: usize) -> Result<Option<BloomFilter>> {
+		let offset = match self.metadata.column(i).bloom_filter_offset() {
+			Some(offset) => offset as u64,
+			None => return Ok(None),
+		};
+		let len = self.buf.borrow().len();
+		if offset >= len {
+			return Err(general_err!(
+				"Bloom filter offset {} is beyond the end of the file",
+				offset
+			));
+		}
+		let mut source = FileSource::new(self.buf.clone(), offset, len - offset);
+		BloomFilter::read(&mut source).map(Some)
+	}
+
 	fn get_row_iter<T>(
 		&self, projection: Option<T::Predicate>,
 	) -> Result<RowIter<SerializedFileReader<Never>, T>>
@@ -735,6 +837,32 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_partial_file_read_and_seek() {
+		let mut file = PartialFile::new(
+			20,
+			vec![
+				(12, vec![7, 8, 9].into_boxed_slice()),
+				(2, vec![1, 2, 3, 4].into_boxed_slice()),
+			],
+		);
+		assert_eq!(file.len(), 20);
+		let mut buf = [0; 4];
+		assert!(file.read(&mut buf).is_err());
+
+		assert_eq!(file.seek(SeekFrom::Start(3)).unwrap(), 3);
+		assert_eq!(file.read(&mut buf).unwrap(), 3);
+		assert_eq!(&buf[..3], &[2, 3, 4]);
+		assert!(file.read(&mut buf).is_err());
+
+		assert_eq!(file.seek(SeekFrom::End(-8)).unwrap(), 12);
+		file.read_exact(&mut buf[..3]).unwrap();
+		assert_eq!(&buf[..3], &[7, 8, 9]);
+		assert!(file.seek(SeekFrom::Current(-16)).is_err());
+		assert_eq!(file.seek(SeekFrom::Current(5)).unwrap(), 20);
+		assert_eq!(file.read(&mut buf).unwrap(), 0);
+	}
+
 	#[test]
 	fn test_file_reader_column_orders_parse() {
 		// Define simple schema, we do not need to provide logical types.
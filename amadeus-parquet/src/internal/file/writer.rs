@@ -19,7 +19,7 @@
 //! using row group writers and column writers respectively.
 
 use std::{
-	fs::File, io::{Seek, SeekFrom, Write}, rc::Rc
+	fs::File, io::{self, Seek, SeekFrom, Write}, rc::Rc
 };
 
 use byteorder::{ByteOrder, LittleEndian};
@@ -29,13 +29,30 @@ use crate::internal::{
 	basic::PageType, column::{
 		page::{CompressedPage, Page, PageWriteSpec, PageWriter}, writer::{get_column_writer, ColumnWriter}
 	}, errors::{ParquetError, Result}, file::{
-		metadata::*, properties::WriterPropertiesPtr, statistics::to_thrift as statistics_to_thrift, FOOTER_SIZE, PARQUET_MAGIC
+		bloom_filter::BloomFilter, metadata::*, properties::WriterPropertiesPtr, statistics::to_thrift as statistics_to_thrift, FOOTER_SIZE, PARQUET_MAGIC
 	}, format as parquet, schema::types::{self, SchemaDescPtr, SchemaDescriptor, TypePtr}, util::io::{FileSink, Position}
 };
 
 // ----------------------------------------------------------------------
 // APIs for file & row group writers
 
+/// ParquetWriter is the interface which needs to be fulfilled to be able to write a
+/// parquet file. Row group and column writers each hold their own handle to the
+/// underlying sink, which they obtain with [`TryClone::try_clone`].
+pub trait ParquetWriter: Write + Seek + TryClone {}
+impl<T> ParquetWriter for T where T: Write + Seek + TryClone {}
+
+/// Creates a new handle to the same underlying sink, sharing its position.
+pub trait TryClone: Sized {
+	fn try_clone(&self) -> io::Result<Self>;
+}
+
+impl TryClone for File {
+	fn try_clone(&self) -> io::Result<Self> {
+		File::try_clone(self)
+	}
+}
+
 /// Parquet file writer API.
 /// Provides methods to write row groups sequentially.
 ///
@@ -109,8 +126,8 @@ pub trait RowGroupWriter {
 
 /// A serialized implementation for Parquet [`FileWriter`].
 /// See documentation on file writer for more information.
-pub struct SerializedFileWriter {
-	file: File,
+pub struct SerializedFileWriter<W: ParquetWriter> {
+	file: W,
 	schema: TypePtr,
 	descr: SchemaDescPtr,
 	props: WriterPropertiesPtr,
@@ -120,9 +137,9 @@ pub struct SerializedFileWriter {
 	is_closed: bool,
 }
 
-impl SerializedFileWriter {
+impl<W: ParquetWriter> SerializedFileWriter<W> {
 	/// Creates new file writer.
-	pub fn new(mut file: File, schema: TypePtr, properties: WriterPropertiesPtr) -> Result<Self> {
+	pub fn new(mut file: W, schema: TypePtr, properties: WriterPropertiesPtr) -> Result<Self> {
 		Self::start_file(&mut file)?;
 		Ok(Self {
 			file,
@@ -137,7 +154,7 @@ impl SerializedFileWriter {
 	}
 
 	/// Writes magic bytes at the beginning of the file.
-	fn start_file(file: &mut File) -> Result<()> {
+	fn start_file(file: &mut W) -> Result<()> {
 		file.write_all(&PARQUET_MAGIC)?;
 		Ok(())
 	}
@@ -207,7 +224,7 @@ impl SerializedFileWriter {
 	}
 }
 
-impl FileWriter for SerializedFileWriter {
+impl<W: ParquetWriter + 'static> FileWriter for SerializedFileWriter<W> {
 	#[inline]
 	fn next_row_group(&mut self) -> Result<Box<dyn RowGroupWriter>> {
 		self.assert_closed()?;
@@ -239,10 +256,10 @@ impl FileWriter for SerializedFileWriter {
 /// A serialized implementation for Parquet [`RowGroupWriter`].
 /// Coordinates writing of a row group with column writers.
 /// See documentation on row group writer for more information.
-pub struct SerializedRowGroupWriter {
+pub struct SerializedRowGroupWriter<W: ParquetWriter> {
 	descr: SchemaDescPtr,
 	props: WriterPropertiesPtr,
-	file: File,
+	file: W,
 	total_rows_written: Option<u64>,
 	total_bytes_written: u64,
 	column_index: usize,
@@ -251,8 +268,8 @@ pub struct SerializedRowGroupWriter {
 	column_chunks: Vec<ColumnChunkMetaDataPtr>,
 }
 
-impl SerializedRowGroupWriter {
-	pub fn new(schema_descr: SchemaDescPtr, properties: WriterPropertiesPtr, file: &File) -> Self {
+impl<W: ParquetWriter> SerializedRowGroupWriter<W> {
+	pub fn new(schema_descr: SchemaDescPtr, properties: WriterPropertiesPtr, file: &W) -> Self {
 		let num_columns = schema_descr.num_columns();
 		Self {
 			descr: schema_descr,
@@ -317,7 +334,7 @@ impl SerializedRowGroupWriter {
 	}
 }
 
-impl RowGroupWriter for SerializedRowGroupWriter {
+impl<W: ParquetWriter + 'static> RowGroupWriter for SerializedRowGroupWriter<W> {
 	#[inline]
 	fn next_column(&mut self) -> Result<Option<ColumnWriter>> {
 		self.assert_closed()?;
@@ -493,6 +510,12 @@ impl<T: Write + Position> PageWriter for SerializedPageWriter<T> {
 		self.serialize_column_chunk(metadata.to_thrift())
 	}
 
+	fn write_bloom_filter(&mut self, bloom_filter: &BloomFilter) -> Result<(u64, u64)> {
+		let offset = self.sink.pos();
+		let bytes_written = bloom_filter.write(&mut self.sink)?;
+		Ok((offset, bytes_written as u64))
+	}
+
 	fn close(&mut self) -> Result<()> {
 		self.sink.flush()?;
 		Ok(())
@@ -508,7 +531,7 @@ mod tests {
 	use crate::internal::{
 		basic::{Compression, Encoding, Repetition, Type}, column::page::PageReader, compression::{create_codec, Codec}, file::{
 			properties::WriterProperties, reader::{FileReader, RowGroupReader, SerializedFileReader, SerializedPageReader}, statistics::{from_thrift, to_thrift, Statistics}
-		}, util::{memory::ByteBufferPtr, test_common::get_temp_file}
+		}, record::Filter, schema::types::ColumnPath, util::{memory::ByteBufferPtr, test_common::get_temp_file}
 	};
 	use amadeus_types::Group;
 
@@ -691,6 +714,66 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_file_writer_bloom_filters() {
+		let file = get_temp_file("test_file_writer_bloom_filters", &[]);
+		let schema = Rc::new(
+			types::Type::group_type_builder("schema")
+				.with_fields(&mut vec![Rc::new(
+					types::Type::primitive_type_builder("col1", Type::Int32)
+						.with_repetition(Repetition::Required)
+						.build()
+						.unwrap(),
+				)])
+				.build()
+				.unwrap(),
+		);
+		let props = Rc::new(
+			WriterProperties::builder()
+				.set_column_bloom_filter_enabled(ColumnPath::from("col1"), true)
+				.build(),
+		);
+		let data = vec![vec![1, 2, 3], vec![124; 10], (200..300).collect()];
+		let mut file_writer =
+			SerializedFileWriter::new(file.try_clone().unwrap(), schema, props).unwrap();
+		for subset in &data {
+			let mut row_group_writer = file_writer.next_row_group().unwrap();
+			let mut writer = row_group_writer.next_column().unwrap().unwrap();
+			match writer {
+				ColumnWriter::Int32ColumnWriter(ref mut typed) => {
+					let _ = typed.write_batch(&subset[..], None, None).unwrap();
+				}
+				_ => unimplemented!(),
+			}
+			row_group_writer.close_column(writer).unwrap();
+			file_writer.close_row_group(row_group_writer).unwrap();
+		}
+		file_writer.close().unwrap();
+
+		let reader = SerializedFileReader::new(file).unwrap();
+		for (i, subset) in data.iter().enumerate() {
+			let bloom_filter = reader
+				.get_row_group(i)
+				.unwrap()
+				.get_column_bloom_filter(0)
+				.unwrap()
+				.unwrap();
+			for value in subset {
+				assert!(bloom_filter.may_contain(BloomFilter::hash(&value.to_le_bytes())));
+			}
+		}
+
+		// Only the second row group can hold 124, and its rows are still read correctly
+		let res = reader
+			.get_row_iter::<Group>(None)
+			.unwrap()
+			.with_filter(&Filter::eq("col1", 124))
+			.unwrap()
+			.map(|elem| elem.unwrap()[0].as_i32().unwrap())
+			.collect::<Vec<i32>>();
+		assert_eq!(res, data[1]);
+	}
+
 	#[test]
 	fn test_page_writer_data_pages() {
 		let pages = vec![
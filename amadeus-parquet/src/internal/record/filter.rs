@@ -0,0 +1,666 @@
+//! Filters on rows, evaluated against the statistics and Bloom filters of row groups so
+//! that row groups none of whose rows can match are skipped without being read.
+
+use serde::{Deserialize, Serialize};
+use std::{
+	cmp::Ordering, collections::HashMap, ops::{Bound, RangeBounds}
+};
+
+use amadeus_types::Value;
+
+use super::writer::Values;
+use crate::internal::{
+	basic::{ColumnOrder, SortOrder, Type as PhysicalType}, errors::{ParquetError, Result}, file::{
+		bloom_filter::BloomFilter, metadata::RowGroupMetaData, reader::RowGroupReader, statistics::Statistics
+	}, schema::types::{ColumnDescriptor, SchemaDescriptor}
+};
+
+/// A filter on the rows of a Parquet file, used to skip the row groups whose
+/// statistics show that none of their rows match.
+///
+/// Columns are named by their dotted path, for example `"a.b"` for the field `b` of the
+/// group `a`. Values are converted to the physical type of their column as they would be
+/// when written, so must be of a type that can be written to it.
+///
+/// Row groups are skipped only when their statistics rule them out, or when the Bloom
+/// filters of their column chunks show that they hold none of the values an
+/// [`Eq`](Filter::Eq) or [`In`](Filter::In) filter looks for. The rows of those that
+/// aren't skipped are not filtered, so rows should still be filtered for exact results.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum Filter {
+	/// The column equals the value.
+	Eq(String, Value),
+	/// The column is within the bounds.
+	Range(String, Bound<Value>, Bound<Value>),
+	/// The column equals any of the values.
+	In(String, Vec<Value>),
+	/// The column is null.
+	IsNull(String),
+	/// All of the filters match.
+	And(Vec<Filter>),
+	/// Any of the filters match.
+	Or(Vec<Filter>),
+}
+
+impl Filter {
+	/// The column `column` equals `value`.
+	pub fn eq<C, V>(column: C, value: V) -> Self
+	where
+		C: Into<String>,
+		V: Into<Value>,
+	{
+		Filter::Eq(column.into(), value.into())
+	}
+
+	/// The column `column` is within `range`, for example `Filter::range("a", 10..20)`.
+	pub fn range<C, V, R>(column: C, range: R) -> Self
+	where
+		C: Into<String>,
+		V: Into<Value> + Clone,
+		R: RangeBounds<V>,
+	{
+		let bound = |bound: Bound<&V>| match bound {
+			Bound::Included(value) => Bound::Included(value.clone().into()),
+			Bound::Excluded(value) => Bound::Excluded(value.clone().into()),
+			Bound::Unbounded => Bound::Unbounded,
+		};
+		Filter::Range(
+			column.into(),
+			bound(range.start_bound()),
+			bound(range.end_bound()),
+		)
+	}
+
+	/// The column `column` equals any of `values`.
+	pub fn is_in<C, I>(column: C, values: I) -> Self
+	where
+		C: Into<String>,
+		I: IntoIterator,
+		I::Item: Into<Value>,
+	{
+		Filter::In(column.into(), values.into_iter().map(Into::into).collect())
+	}
+
+	/// The column `column` is null.
+	pub fn is_null<C>(column: C) -> Self
+	where
+		C: Into<String>,
+	{
+		Filter::IsNull(column.into())
+	}
+
+	/// Both this filter and `other` match.
+	pub fn and(self, other: Self) -> Self {
+		match self {
+			Filter::And(mut filters) => {
+				filters.push(other);
+				Filter::And(filters)
+			}
+			filter => Filter::And(vec![filter, other]),
+		}
+	}
+
+	/// Either this filter or `other` matches.
+	pub fn or(self, other: Self) -> Self {
+		match self {
+			Filter::Or(mut filters) => {
+				filters.push(other);
+				Filter::Or(filters)
+			}
+			filter => Filter::Or(vec![filter, other]),
+		}
+	}
+
+	/// Returns `false` if the statistics of `row_group` show that none of its rows
+	/// match, or `true` if some might.
+	///
+	/// Returns an error if a column isn't in the row group's schema, or a value can't be
+	/// converted to its column's type.
+	pub fn may_match(&self, row_group: &RowGroupMetaData) -> Result<bool> {
+		Ok(self
+			.bind(row_group.schema_descr())?
+			.may_match(row_group, &HashMap::new()))
+	}
+
+	/// Resolve the columns of this filter in `schema`, and convert its values to their
+	/// columns' physical types.
+	pub(crate) fn bind(&self, schema: &SchemaDescriptor) -> Result<RowGroupFilter> {
+		Ok(match self {
+			Filter::Eq(column, value) => {
+				let (index, column) = find(schema, column)?;
+				RowGroupFilter::In {
+					column: index,
+					order: sort_order(column),
+					values: vec![literal(column, value.clone())?],
+				}
+			}
+			Filter::Range(column, lower, upper) => {
+				let (index, column) = find(schema, column)?;
+				let bound = |bound: &Bound<Value>| -> Result<_> {
+					Ok(match bound {
+						Bound::Included(value) => Bound::Included(literal(column, value.clone())?),
+						Bound::Excluded(value) => Bound::Excluded(literal(column, value.clone())?),
+						Bound::Unbounded => Bound::Unbounded,
+					})
+				};
+				RowGroupFilter::Range {
+					column: index,
+					order: sort_order(column),
+					lower: bound(lower)?,
+					upper: bound(upper)?,
+				}
+			}
+			Filter::In(column, values) => {
+				let (index, column) = find(schema, column)?;
+				RowGroupFilter::In {
+					column: index,
+					order: sort_order(column),
+					values: values
+						.iter()
+						.map(|value| literal(column, value.clone()))
+						.collect::<Result<_>>()?,
+				}
+			}
+			Filter::IsNull(column) => {
+				// Only columns with an optional or repeated ancestor can be null
+				let (_, column) = find(schema, column)?;
+				RowGroupFilter::Const(column.max_def_level() > 0)
+			}
+			Filter::And(filters) => RowGroupFilter::And(
+				filters
+					.iter()
+					.map(|filter| filter.bind(schema))
+					.collect::<Result<_>>()?,
+			),
+			Filter::Or(filters) => RowGroupFilter::Or(
+				filters
+					.iter()
+					.map(|filter| filter.bind(schema))
+					.collect::<Result<_>>()?,
+			),
+		})
+	}
+}
+
+/// A [`Filter`] bound to the schema of a file.
+#[derive(Clone, Debug)]
+pub(crate) enum RowGroupFilter {
+	Range {
+		column: usize,
+		order: SortOrder,
+		lower: Bound<Literal>,
+		upper: Bound<Literal>,
+	},
+	In {
+		column: usize,
+		order: SortOrder,
+		values: Vec<Literal>,
+	},
+	Const(bool),
+	And(Vec<RowGroupFilter>),
+	Or(Vec<RowGroupFilter>),
+}
+
+impl RowGroupFilter {
+	/// Whether any rows of `row_group` might match, going by its statistics and the
+	/// Bloom filters of its column chunks that are in `bloom_filters`.
+	pub(crate) fn may_match(
+		&self, row_group: &RowGroupMetaData, bloom_filters: &HashMap<usize, BloomFilter>,
+	) -> bool {
+		match self {
+			RowGroupFilter::Range {
+				column,
+				order,
+				lower,
+				upper,
+			} => may_overlap(row_group, *column, *order, as_ref(lower), as_ref(upper)),
+			RowGroupFilter::In {
+				column,
+				order,
+				values,
+			} => values.iter().any(|value| {
+				let bound = Bound::Included(value);
+				may_overlap(row_group, *column, *order, bound, bound)
+					&& may_contain(bloom_filters.get(column), value)
+			}),
+			RowGroupFilter::Const(may_match) => *may_match,
+			RowGroupFilter::And(filters) => filters
+				.iter()
+				.all(|filter| filter.may_match(row_group, bloom_filters)),
+			RowGroupFilter::Or(filters) => filters
+				.iter()
+				.any(|filter| filter.may_match(row_group, bloom_filters)),
+		}
+	}
+
+	/// The columns of `row_group` whose Bloom filters might rule it out.
+	pub(crate) fn bloom_filter_columns(&self, row_group: &RowGroupMetaData) -> Vec<usize> {
+		let mut columns = Vec::new();
+		self.push_bloom_filter_columns(row_group, &mut columns);
+		columns.sort_unstable();
+		columns.dedup();
+		columns
+	}
+
+	fn push_bloom_filter_columns(&self, row_group: &RowGroupMetaData, columns: &mut Vec<usize>) {
+		match self {
+			RowGroupFilter::In { column, values, .. } => {
+				if row_group.column(*column).bloom_filter_offset().is_some()
+					&& values
+						.iter()
+						.any(|value| value.bloom_filter_hash().is_some())
+				{
+					columns.push(*column);
+				}
+			}
+			RowGroupFilter::And(filters) | RowGroupFilter::Or(filters) => {
+				for filter in filters {
+					filter.push_bloom_filter_columns(row_group, columns);
+				}
+			}
+			RowGroupFilter::Range { .. } | RowGroupFilter::Const(_) => (),
+		}
+	}
+
+	/// Whether any rows of the row group read by `row_group_reader` might match, reading
+	/// the Bloom filters of its column chunks if its statistics don't rule it out.
+	pub(crate) fn may_match_reader(&self, row_group_reader: &dyn RowGroupReader) -> Result<bool> {
+		let row_group = row_group_reader.metadata();
+		let mut bloom_filters = HashMap::new();
+		if !self.may_match(&row_group, &bloom_filters) {
+			return Ok(false);
+		}
+		for column in self.bloom_filter_columns(&row_group) {
+			if let Some(bloom_filter) = row_group_reader.get_column_bloom_filter(column)? {
+				let _ = bloom_filters.insert(column, bloom_filter);
+			}
+		}
+		Ok(bloom_filters.is_empty() || self.may_match(&row_group, &bloom_filters))
+	}
+}
+
+/// A value of a column's physical type.
+#[derive(Clone, Debug)]
+pub(crate) enum Literal {
+	Bool(bool),
+	Int32(i32),
+	Int64(i64),
+	/// `int96` values have no defined sort order, so are never compared.
+	Int96,
+	Float(f32),
+	Double(f64),
+	Bytes(Vec<u8>),
+}
+
+impl Literal {
+	/// The hash the value is inserted into Bloom filters with, or `None` for values that
+	/// aren't looked up in them: booleans, `int96`s, and floating point values, which
+	/// can equal values of other bit patterns, like `-0.0` and `0.0`.
+	fn bloom_filter_hash(&self) -> Option<u64> {
+		match self {
+			Literal::Int32(value) => Some(BloomFilter::hash(&value.to_le_bytes())),
+			Literal::Int64(value) => Some(BloomFilter::hash(&value.to_le_bytes())),
+			Literal::Bytes(value) => Some(BloomFilter::hash(value)),
+			Literal::Bool(_) | Literal::Int96 | Literal::Float(_) | Literal::Double(_) => None,
+		}
+	}
+}
+
+fn find<'a>(schema: &'a SchemaDescriptor, column: &str) -> Result<(usize, &'a ColumnDescriptor)> {
+	schema
+		.columns()
+		.iter()
+		.enumerate()
+		.find(|(_, descr)| descr.path().string() == column)
+		.map(|(index, descr)| (index, &**descr))
+		.ok_or_else(|| {
+			general_err!(
+				"Can't filter on column {}, as it isn't in the schema",
+				column
+			)
+		})
+}
+
+fn sort_order(column: &ColumnDescriptor) -> SortOrder {
+	ColumnOrder::get_sort_order(column.logical_type(), column.physical_type())
+}
+
+fn literal(column: &ColumnDescriptor, value: Value) -> Result<Literal> {
+	let mut values = Values::new(column.physical_type());
+	values.push(column.self_type(), value)?;
+	Ok(match values {
+		Values::Bool(mut values) => Literal::Bool(values.pop().unwrap()),
+		Values::Int32(mut values) => Literal::Int32(values.pop().unwrap()),
+		Values::Int64(mut values) => Literal::Int64(values.pop().unwrap()),
+		Values::Int96(_) => Literal::Int96,
+		Values::Float(mut values) => Literal::Float(values.pop().unwrap()),
+		Values::Double(mut values) => Literal::Double(values.pop().unwrap()),
+		Values::ByteArray(mut values) | Values::FixedLenByteArray(mut values) => {
+			Literal::Bytes(values.pop().unwrap().into())
+		}
+	})
+}
+
+/// Whether a column chunk with the Bloom filter `bloom_filter`, if any, might contain
+/// `value`.
+fn may_contain(bloom_filter: Option<&BloomFilter>, value: &Literal) -> bool {
+	match (bloom_filter, value.bloom_filter_hash()) {
+		(Some(bloom_filter), Some(hash)) => bloom_filter.may_contain(hash),
+		_ => true,
+	}
+}
+
+fn as_ref<T>(bound: &Bound<T>) -> Bound<&T> {
+	match bound {
+		Bound::Included(value) => Bound::Included(value),
+		Bound::Excluded(value) => Bound::Excluded(value),
+		Bound::Unbounded => Bound::Unbounded,
+	}
+}
+
+/// Whether the values of a column chunk might include any within `lower` and `upper`.
+/// Anything the statistics can't rule out might.
+fn may_overlap(
+	row_group: &RowGroupMetaData, column: usize, order: SortOrder, lower: Bound<&Literal>,
+	upper: Bound<&Literal>,
+) -> bool {
+	let chunk = row_group.column(column);
+	let stats = match chunk.statistics() {
+		Some(stats) => stats,
+		None => return true,
+	};
+	if !stats.has_min_max_set() {
+		// A column chunk of only nulls has no values to match
+		return !(chunk.num_values() > 0 && stats.null_count() == chunk.num_values() as u64);
+	}
+	// The deprecated min and max were computed with a signed comparison whatever the
+	// column's sort order, so are unreliable for anything but signed and boolean columns
+	if stats.is_min_max_deprecated()
+		&& order != SortOrder::Signed
+		&& stats.physical_type() != PhysicalType::Boolean
+	{
+		return true;
+	}
+	let (min, max) = match min_max(stats) {
+		Some(min_max) => min_max,
+		None => return true,
+	};
+	let above_lower = match lower {
+		Bound::Included(lower) => compare(lower, &max, order) != Some(Ordering::Greater),
+		Bound::Excluded(lower) => !matches!(
+			compare(lower, &max, order),
+			Some(Ordering::Greater) | Some(Ordering::Equal)
+		),
+		Bound::Unbounded => true,
+	};
+	let below_upper = match upper {
+		Bound::Included(upper) => compare(upper, &min, order) != Some(Ordering::Less),
+		Bound::Excluded(upper) => !matches!(
+			compare(upper, &min, order),
+			Some(Ordering::Less) | Some(Ordering::Equal)
+		),
+		Bound::Unbounded => true,
+	};
+	above_lower && below_upper
+}
+
+fn min_max(stats: &Statistics) -> Option<(Literal, Literal)> {
+	Some(match stats {
+		Statistics::Boolean(stats) => (Literal::Bool(*stats.min()), Literal::Bool(*stats.max())),
+		Statistics::Int32(stats) => (Literal::Int32(*stats.min()), Literal::Int32(*stats.max())),
+		Statistics::Int64(stats) => (Literal::Int64(*stats.min()), Literal::Int64(*stats.max())),
+		Statistics::Int96(_) => return None,
+		Statistics::Float(stats) => (Literal::Float(*stats.min()), Literal::Float(*stats.max())),
+		Statistics::Double(stats) => (Literal::Double(*stats.min()), Literal::Double(*stats.max())),
+		Statistics::ByteArray(stats) => (
+			Literal::Bytes(stats.min().data().to_owned()),
+			Literal::Bytes(stats.max().data().to_owned()),
+		),
+		Statistics::FixedLenByteArray(stats) => (
+			Literal::Bytes(stats.min().data().to_owned()),
+			Literal::Bytes(stats.max().data().to_owned()),
+		),
+	})
+}
+
+/// Compare two values of a column with the sort order `order`, or `None` if they can't
+/// be compared, as is the case for NaNs, `int96` and signed byte arrays like decimals.
+fn compare(a: &Literal, b: &Literal, order: SortOrder) -> Option<Ordering> {
+	match (a, b, order) {
+		(Literal::Bool(a), Literal::Bool(b), _) => Some(a.cmp(b)),
+		(Literal::Int32(a), Literal::Int32(b), SortOrder::Signed) => Some(a.cmp(b)),
+		(Literal::Int32(a), Literal::Int32(b), SortOrder::Unsigned) => {
+			Some((*a as u32).cmp(&(*b as u32)))
+		}
+		(Literal::Int64(a), Literal::Int64(b), SortOrder::Signed) => Some(a.cmp(b)),
+		(Literal::Int64(a), Literal::Int64(b), SortOrder::Unsigned) => {
+			Some((*a as u64).cmp(&(*b as u64)))
+		}
+		(Literal::Float(a), Literal::Float(b), SortOrder::Signed) => a.partial_cmp(b),
+		(Literal::Double(a), Literal::Double(b), SortOrder::Signed) => a.partial_cmp(b),
+		(Literal::Bytes(a), Literal::Bytes(b), SortOrder::Unsigned) => Some(a.cmp(b)),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::rc::Rc;
+
+	use crate::internal::{
+		file::metadata::ColumnChunkMetaData, schema::{parser::parse_message_type, types::SchemaDescPtr}
+	};
+
+	fn schema() -> SchemaDescPtr {
+		let schema = parse_message_type(
+			"
+			message schema {
+				REQUIRED INT32 a;
+				OPTIONAL BYTE_ARRAY b (UTF8);
+				OPTIONAL INT32 c (UINT_32);
+				OPTIONAL DOUBLE d;
+			}
+			",
+		)
+		.unwrap();
+		Rc::new(SchemaDescriptor::new(Rc::new(schema)))
+	}
+
+	fn with_stats(stats: Vec<Option<Statistics>>) -> RowGroupMetaData {
+		let schema = schema();
+		let columns = schema
+			.columns()
+			.iter()
+			.zip(stats)
+			.map(|(column, stats)| {
+				let mut builder = ColumnChunkMetaData::builder(column.clone()).set_num_values(100);
+				if let Some(stats) = stats {
+					builder = builder.set_statistics(stats);
+				}
+				Rc::new(builder.build().unwrap())
+			})
+			.collect();
+		RowGroupMetaData::builder(schema)
+			.set_num_rows(100)
+			.set_column_metadata(columns)
+			.build()
+			.unwrap()
+	}
+
+	fn bytes(string: &str) -> Option<crate::internal::data_type::ByteArray> {
+		Some(string.as_bytes().to_owned().into())
+	}
+
+	fn test_row_group() -> RowGroupMetaData {
+		with_stats(vec![
+			Some(Statistics::int32(Some(10), Some(20), None, 0, false)),
+			Some(Statistics::byte_array(
+				bytes("banana"),
+				bytes("cherry"),
+				None,
+				5,
+				false,
+			)),
+			Some(Statistics::int32(Some(1), Some(-1), None, 0, false)),
+			None,
+		])
+	}
+
+	#[test]
+	fn test_filter_eq_range_in() {
+		let row_group = test_row_group();
+		let may_match = |filter: Filter| filter.may_match(&row_group).unwrap();
+
+		assert!(may_match(Filter::eq("a", 10)));
+		assert!(may_match(Filter::eq("a", 20)));
+		assert!(!may_match(Filter::eq("a", 9)));
+		assert!(!may_match(Filter::eq("a", 21)));
+
+		assert!(may_match(Filter::range("a", 0..=10)));
+		assert!(!may_match(Filter::range("a", 0..10)));
+		assert!(may_match(Filter::range("a", 20..)));
+		assert!(!may_match(Filter::range(
+			"a",
+			(Bound::Excluded(20), Bound::Unbounded)
+		)));
+		assert!(may_match(Filter::range("a", 12..15)));
+		assert!(may_match(Filter::range::<_, i32, _>("a", ..)));
+
+		assert!(!may_match(Filter::is_in("a", vec![1, 2, 30])));
+		assert!(may_match(Filter::is_in("a", vec![1, 15, 30])));
+		assert!(!may_match(Filter::is_in("a", Vec::<i32>::new())));
+
+		assert!(may_match(Filter::eq("b", String::from("cherry"))));
+		assert!(!may_match(Filter::eq("b", String::from("apple"))));
+		assert!(!may_match(Filter::range("b", String::from("cherryade")..)));
+	}
+
+	#[test]
+	fn test_filter_sort_order() {
+		let row_group = test_row_group();
+		let may_match = |filter: Filter| filter.may_match(&row_group).unwrap();
+
+		// c is unsigned, so its min 1 and max u32::MAX are stored as 1 and -1
+		assert!(may_match(Filter::eq("c", u32::max_value())));
+		assert!(may_match(Filter::eq("c", 1_000_u32)));
+		assert!(!may_match(Filter::eq("c", 0_u32)));
+
+		// without statistics nothing can be ruled out
+		assert!(may_match(Filter::eq("d", 1.0_f64)));
+		assert!(may_match(Filter::eq("d", std::f64::NAN)));
+	}
+
+	#[test]
+	fn test_filter_null() {
+		let row_group = test_row_group();
+		let may_match = |filter: Filter| filter.may_match(&row_group).unwrap();
+
+		assert!(!may_match(Filter::is_null("a")));
+		assert!(may_match(Filter::is_null("b")));
+
+		let nulls = with_stats(vec![
+			Some(Statistics::int32(Some(10), Some(20), None, 0, false)),
+			Some(Statistics::byte_array(None, None, None, 100, false)),
+			None,
+			None,
+		]);
+		assert!(!Filter::eq("b", String::from("banana"))
+			.may_match(&nulls)
+			.unwrap());
+		assert!(Filter::is_null("b").may_match(&nulls).unwrap());
+	}
+
+	#[test]
+	fn test_filter_and_or() {
+		let row_group = test_row_group();
+		let may_match = |filter: Filter| filter.may_match(&row_group).unwrap();
+
+		assert!(may_match(Filter::eq("a", 15).and(Filter::is_null("b"))));
+		assert!(!may_match(Filter::eq("a", 15).and(Filter::eq("a", 25))));
+		assert!(may_match(Filter::eq("a", 5).or(Filter::eq("a", 15))));
+		assert!(!may_match(
+			Filter::eq("a", 5)
+				.or(Filter::eq("a", 25))
+				.or(Filter::is_null("a"))
+		));
+		assert_eq!(
+			Filter::eq("a", 5)
+				.or(Filter::eq("a", 15))
+				.or(Filter::eq("a", 25)),
+			Filter::Or(vec![
+				Filter::eq("a", 5),
+				Filter::eq("a", 15),
+				Filter::eq("a", 25)
+			])
+		);
+	}
+
+	#[test]
+	fn test_filter_bloom_filters() {
+		let schema = schema();
+		let columns = schema
+			.columns()
+			.iter()
+			.map(|column| {
+				let builder = ColumnChunkMetaData::builder(column.clone())
+					.set_num_values(100)
+					.set_bloom_filter_offset(Some(4));
+				Rc::new(builder.build().unwrap())
+			})
+			.collect();
+		let row_group = RowGroupMetaData::builder(schema.clone())
+			.set_num_rows(100)
+			.set_column_metadata(columns)
+			.build()
+			.unwrap();
+		let mut a = BloomFilter::new(1024);
+		a.insert(BloomFilter::hash(&10_i32.to_le_bytes()));
+		let mut b = BloomFilter::new(1024);
+		b.insert(BloomFilter::hash(b"cherry"));
+		let bloom_filters = vec![(0, a), (1, b)].into_iter().collect();
+		let may_match = |filter: Filter| {
+			filter
+				.bind(&schema)
+				.unwrap()
+				.may_match(&row_group, &bloom_filters)
+		};
+
+		assert!(may_match(Filter::eq("a", 10)));
+		assert!(!may_match(Filter::eq("a", 11)));
+		assert!(may_match(Filter::is_in("a", vec![11, 10])));
+		assert!(!may_match(Filter::is_in("a", vec![11, 12])));
+		assert!(may_match(Filter::eq("b", String::from("cherry"))));
+		assert!(!may_match(Filter::eq("b", String::from("apple"))));
+		assert!(may_match(
+			Filter::eq("a", 11).or(Filter::eq("b", String::from("cherry")))
+		));
+		assert!(!may_match(
+			Filter::eq("a", 10).and(Filter::eq("b", String::from("apple")))
+		));
+
+		// ranges, and columns without a Bloom filter, can't be ruled out by one
+		assert!(may_match(Filter::range("a", 11..20)));
+		assert!(may_match(Filter::eq("c", 1_u32)));
+
+		let filter = Filter::eq("a", 11)
+			.or(Filter::range("c", 1_u32..))
+			.or(Filter::eq("d", 1.0_f64))
+			.and(Filter::eq("a", 12))
+			.bind(&schema)
+			.unwrap();
+		assert_eq!(filter.bloom_filter_columns(&row_group), vec![0]);
+		assert_eq!(filter.bloom_filter_columns(&test_row_group()), vec![]);
+	}
+
+	#[test]
+	fn test_filter_errors() {
+		let row_group = test_row_group();
+
+		assert!(Filter::eq("z", 1).may_match(&row_group).is_err());
+		assert!(Filter::eq("a", String::from("1"))
+			.may_match(&row_group)
+			.is_err());
+	}
+}
@@ -40,12 +40,14 @@
 //! ```
 
 mod display;
+mod filter;
 mod impls;
 pub mod predicates;
 mod reader;
 mod schemas;
 mod triplet;
 pub mod types;
+mod writer;
 
 use std::{
 	collections::HashMap, fmt::{self, Debug}
@@ -59,8 +61,11 @@ use crate::internal::{
 
 /// This is used by `#[derive(Data)]`
 pub use display::DisplaySchemaGroup;
+pub use filter::Filter;
+pub(crate) use filter::RowGroupFilter;
 pub use reader::RowIter;
 pub use schemas::RootSchema;
+pub use writer::{default_schema, ValueWriter};
 
 mod predicate {
 	/// This is for forward compatibility when Predicate pushdown and dynamic schemas are
@@ -31,6 +31,44 @@ impl GroupPredicate {
 	{
 		Self(fields.into_iter().collect())
 	}
+
+	/// Select only the columns at `columns`, given as dotted paths like `"a.b"` for the
+	/// field `b` of the group `a`. Fields are read in the order they're first given, and
+	/// a field that isn't a group, like a list or map, is read whole.
+	pub fn columns<I>(columns: I) -> Self
+	where
+		I: IntoIterator,
+		I::Item: AsRef<str>,
+	{
+		fn select(
+			fields: &mut LinkedHashMap<String, Option<ValuePredicate>, FxBuildHasher>,
+			path: &[&str],
+		) {
+			let (name, path) = path.split_first().unwrap();
+			if path.is_empty() {
+				// The whole field, superseding any of its fields selected already
+				match fields.get_mut(*name) {
+					Some(field) => *field = None,
+					None => {
+						let _ = fields.insert((*name).to_owned(), None);
+					}
+				}
+				return;
+			}
+			if !fields.contains_key(*name) {
+				let group = ValuePredicate::Group(Some(GroupPredicate(Default::default())));
+				let _ = fields.insert((*name).to_owned(), Some(group));
+			}
+			if let Some(Some(ValuePredicate::Group(Some(group)))) = fields.get_mut(*name) {
+				select(&mut group.0, path);
+			}
+		}
+		let mut fields = LinkedHashMap::default();
+		for column in columns {
+			select(&mut fields, &column.as_ref().split('.').collect::<Vec<_>>());
+		}
+		Self(fields)
+	}
 }
 
 #[derive(Clone, Debug)]
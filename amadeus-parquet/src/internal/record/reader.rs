@@ -30,7 +30,9 @@ use std::{
 };
 use sum::derive_sum;
 
-use super::{triplet::TypedTripletIter, types::Root, ParquetData, Reader};
+use super::{
+	filter::{Filter, RowGroupFilter}, triplet::TypedTripletIter, types::Root, ParquetData, Reader
+};
 use crate::internal::{
 	column::reader::ColumnReader, data_type::{
 		BoolType, ByteArrayType, DoubleType, FixedLenByteArrayType, FloatType, Int32Type, Int64Type, Int96, Int96Type
@@ -733,6 +735,7 @@ where
 	current_row_group: usize,
 	num_row_groups: usize,
 	row_iter: Option<ReaderIter<T>>,
+	filter: Option<RowGroupFilter>,
 }
 
 impl<R, T> RowIter<R, T>
@@ -756,6 +759,7 @@ where
 			current_row_group: 0,
 			num_row_groups,
 			row_iter,
+			filter: None,
 		}
 	}
 
@@ -786,12 +790,61 @@ where
 			current_row_group: 0,
 			num_row_groups: 0,
 			row_iter: Some(row_iter),
+			filter: None,
 		})
 	}
 
+	/// Skips the row groups whose statistics or Bloom filters show that none of their
+	/// rows match `filter`. The rows of the row groups that aren't skipped are not
+	/// filtered.
+	///
+	/// Returns an error if this iterator was created for a single row group, or if
+	/// `filter` doesn't fit the file's schema.
+	pub fn with_filter(mut self, filter: &Filter) -> Result<Self> {
+		let file_reader = self
+			.file_reader
+			.as_ref()
+			.ok_or_else(|| general_err!("File reader is required to use filters"))?;
+		let schema = file_reader.metadata().file_metadata().schema_descr_ptr();
+		self.filter = Some(filter.bind(&schema)?);
+		Ok(self)
+	}
+
+	/// Returns the indices of the columns of a row group that rows are read from, which
+	/// are the only column chunks that need to have been read of the file.
+	pub fn columns(
+		proj: Option<&T::Predicate>, row_group_reader: &dyn RowGroupReader,
+	) -> Result<Vec<usize>> {
+		let file_schema = row_group_reader.metadata().schema_descr_ptr();
+		let file_schema = file_schema.root_schema();
+		let schema = <Root<T> as ParquetData>::parse(file_schema, proj, None)?.1;
+
+		// Column readers don't read anything until they're advanced, so this leaves the
+		// columns that aren't read
+		let (_reader, paths) = Self::get_reader(&schema, row_group_reader);
+		let row_group_metadata = row_group_reader.metadata();
+		Ok((0..row_group_reader.num_columns())
+			.filter(|&col_index| {
+				!paths.contains_key(row_group_metadata.column(col_index).column_path())
+			})
+			.collect())
+	}
+
 	fn get_reader_iter(
 		schema: &<Root<T> as ParquetData>::Schema, row_group_reader: &dyn RowGroupReader,
 	) -> Result<ReaderIter<T>> {
+		let (reader, _paths) = Self::get_reader(schema, row_group_reader);
+		ReaderIter::new(reader, row_group_reader.metadata().num_rows() as u64)
+	}
+
+	/// Builds the reader for the message type, along with the column readers it doesn't
+	/// use.
+	fn get_reader(
+		schema: &<Root<T> as ParquetData>::Schema, row_group_reader: &dyn RowGroupReader,
+	) -> (
+		<Root<T> as ParquetData>::Reader,
+		HashMap<ColumnPath, ColumnReader>,
+	) {
 		// Prepare lookup table of column path -> original column index
 		// This allows to prune columns and map schema leaf nodes to the column readers
 		let mut paths: HashMap<ColumnPath, ColumnReader> =
@@ -810,7 +863,7 @@ where
 		// Build reader for the message type, requires definition level 0
 		let mut path = Vec::new();
 		let reader = <Root<T>>::reader(&schema, &mut path, 0, 0, &mut paths, DEFAULT_BATCH_SIZE);
-		ReaderIter::new(reader, row_group_reader.metadata().num_rows() as u64)
+		(reader, paths)
 	}
 
 	// /// Creates a iterator of [`Row`](crate::internal::record::api::Row)s from a
@@ -881,13 +934,26 @@ where
 		while row.is_none() && self.current_row_group < self.num_row_groups {
 			// We do not expect any failures when accessing a row group, and file reader
 			// must be set for selecting next row group.
-			let row_group_reader = self
+			let file_reader = self
 				.file_reader
 				.as_ref()
-				.expect("File reader is required to advance row group")
+				.expect("File reader is required to advance row group");
+
+			let row_group_reader = file_reader
 				.get_row_group(self.current_row_group)
 				.expect("Row group is required to advance");
 
+			if let Some(filter) = &self.filter {
+				match filter.may_match_reader(&row_group_reader) {
+					Err(err) => return Some(Err(err)),
+					Ok(false) => {
+						self.current_row_group += 1;
+						continue;
+					}
+					Ok(true) => (),
+				}
+			}
+
 			let mut row_iter = match Self::get_reader_iter(&self.schema, &row_group_reader) {
 				Err(err) => return Some(Err(err)),
 				Ok(row_iter) => row_iter,
@@ -0,0 +1,510 @@
+//! Shredding of [`Value`]s into columns, for writing.
+//!
+//! This is the inverse of the [`Reader`](super::Reader)s: a [`ValueWriter`] takes rows,
+//! decomposes them into the definition levels, repetition levels and values of each
+//! leaf column as described in the [Dremel paper](https://research.google/pubs/pub36632/),
+//! and writes them out as a row group.
+
+use std::{any::type_name, convert::TryInto, mem};
+
+use amadeus_types::{Group, List, Value};
+
+use crate::internal::{
+	basic::{LogicalType, Repetition, Type as PhysicalType}, column::writer::ColumnWriter, data_type::{ByteArray, Int96}, errors::{ParquetError, Result}, file::writer::RowGroupWriter, schema::types::{SchemaDescPtr, Type}
+};
+
+use super::{display::DisplayFmt, schemas::RootSchema, ParquetData, Schema};
+
+const JULIAN_DAY_OF_EPOCH: i64 = 2_440_588;
+const NANOS_PER_MICRO: u64 = 1_000;
+const NANOS_PER_MILLI: u64 = 1_000_000;
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Derive the schema to write `T` with, as a message type.
+///
+/// Some types can be read from several physical or logical types, for example
+/// [`Time`](amadeus_types::Time) from either `int32 (TIME_MILLIS)` or `int64
+/// (TIME_MICROS)`; for these the same representation as their default schema is
+/// chosen. Types whose schema can't be derived, like [`Value`], return an error.
+pub fn default_schema<T>() -> Result<String>
+where
+	T: ParquetData,
+{
+	let schema = DisplayFmt::new(|f| <T::Schema as Schema>::fmt(None, None, Some("amadeus"), f))
+		.to_string()
+		.replace(" int32|int64 ", " int64 ")
+		.replace(" (TIME_MILLIS|TIME_MICROS);", " (TIME_MICROS);")
+		.replace(" int64|int96 ", " int96 ")
+		.replace(" (TIMESTAMP_MILLIS|TIMESTAMP_MICROS);", ";");
+	let _: RootSchema<T> = schema.parse().map_err(|err| {
+		general_err!(
+			"Couldn't derive a schema for {}, so one needs to be given explicitly: {}",
+			type_name::<T>(),
+			err
+		)
+	})?;
+	Ok(schema)
+}
+
+/// Buffers rows as columns, and writes them out as row groups.
+pub struct ValueWriter {
+	descr: SchemaDescPtr,
+	columns: Vec<ColumnBuffer>,
+	num_rows: usize,
+}
+
+impl ValueWriter {
+	pub fn new(descr: SchemaDescPtr) -> Self {
+		let columns = descr
+			.columns()
+			.iter()
+			.map(|column| ColumnBuffer::new(column.physical_type()))
+			.collect();
+		Self {
+			descr,
+			columns,
+			num_rows: 0,
+		}
+	}
+
+	/// The number of rows buffered since the last row group was written.
+	pub fn num_rows(&self) -> usize {
+		self.num_rows
+	}
+
+	/// Shred a row into the column buffers. `row` must be a [`Value::Group`] matching
+	/// the schema.
+	pub fn write(&mut self, row: Value) -> Result<()> {
+		let descr = self.descr.clone();
+		let root = descr.root_schema();
+		let mut fields = group_fields(root, row)?.into_iter();
+		let mut column = 0;
+		for field in root.get_fields() {
+			self.write_field(field, fields.next().unwrap(), column, 0, 0, 0)?;
+			column += num_leaves(field);
+		}
+		self.num_rows += 1;
+		Ok(())
+	}
+
+	/// Write the buffered rows out as a row group. Does nothing if there are no
+	/// buffered rows.
+	pub fn flush(&mut self, row_group_writer: &mut dyn RowGroupWriter) -> Result<()> {
+		if self.num_rows == 0 {
+			return Ok(());
+		}
+		for column in &mut self.columns {
+			let column_writer = row_group_writer
+				.next_column()?
+				.ok_or_else(|| general_err!("Row group writer has too few columns"))?;
+			let column_writer = column.write(column_writer)?;
+			row_group_writer.close_column(column_writer)?;
+		}
+		self.num_rows = 0;
+		Ok(())
+	}
+
+	fn write_field(
+		&mut self, field: &Type, value: Value, column: usize, def_level: i16, rep_level: i16,
+		rep_depth: i16,
+	) -> Result<()> {
+		match field.get_basic_info().repetition() {
+			Repetition::Required => {
+				self.write_value(field, value, column, def_level, rep_level, rep_depth)
+			}
+			Repetition::Optional => {
+				let value = if let Value::Option(_) = value {
+					value.into_option().unwrap()
+				} else {
+					Some(value)
+				};
+				match value {
+					Some(value) => {
+						self.write_value(field, value, column, def_level + 1, rep_level, rep_depth)
+					}
+					None => {
+						self.write_null(field, column, def_level, rep_level);
+						Ok(())
+					}
+				}
+			}
+			Repetition::Repeated => {
+				let list = value.into_list().map_err(|err| general_err!("{}", err))?;
+				if list.is_empty() {
+					self.write_null(field, column, def_level, rep_level);
+				}
+				for (i, value) in list.into_iter().enumerate() {
+					let rep_level = if i == 0 { rep_level } else { rep_depth + 1 };
+					self.write_value(
+						field,
+						value,
+						column,
+						def_level + 1,
+						rep_level,
+						rep_depth + 1,
+					)?;
+				}
+				Ok(())
+			}
+		}
+	}
+
+	fn write_value(
+		&mut self, field: &Type, value: Value, column: usize, def_level: i16, rep_level: i16,
+		rep_depth: i16,
+	) -> Result<()> {
+		if field.is_primitive() {
+			let column_ = &mut self.columns[column];
+			column_.values.push(field, value)?;
+			column_.def_levels.push(def_level);
+			column_.rep_levels.push(rep_level);
+			return Ok(());
+		}
+		match field.get_basic_info().logical_type() {
+			LogicalType::List if is_list(field) => {
+				let list = value.into_list().map_err(|err| general_err!("{}", err))?;
+				let repeated = &field.get_fields()[0];
+				let list = if is_three_level_list(field) {
+					list.map(|element| Value::Group(Group::new(vec![element], None)))
+				} else {
+					list
+				};
+				self.write_field(
+					repeated,
+					Value::List(list),
+					column,
+					def_level,
+					rep_level,
+					rep_depth,
+				)
+			}
+			LogicalType::Map | LogicalType::MapKeyValue if is_map(field) => {
+				let map = value.into_map().map_err(|err| general_err!("{}", err))?;
+				let key_value = &field.get_fields()[0];
+				let list = map
+					.into_iter()
+					.map(|(key, value)| Value::Group(Group::new(vec![key, value], None)))
+					.collect::<List<_>>();
+				self.write_field(
+					key_value,
+					Value::List(list),
+					column,
+					def_level,
+					rep_level,
+					rep_depth,
+				)
+			}
+			_ => {
+				let mut column = column;
+				for (field, value) in field.get_fields().iter().zip(group_fields(field, value)?) {
+					self.write_field(field, value, column, def_level, rep_level, rep_depth)?;
+					column += num_leaves(field);
+				}
+				Ok(())
+			}
+		}
+	}
+
+	/// Write a null, or an empty list, to every leaf column under `field`.
+	fn write_null(&mut self, field: &Type, column: usize, def_level: i16, rep_level: i16) {
+		for column in &mut self.columns[column..column + num_leaves(field)] {
+			column.def_levels.push(def_level);
+			column.rep_levels.push(rep_level);
+		}
+	}
+}
+
+/// Definition levels, repetition levels and non-null values of a leaf column.
+struct ColumnBuffer {
+	values: Values,
+	def_levels: Vec<i16>,
+	rep_levels: Vec<i16>,
+}
+
+impl ColumnBuffer {
+	fn new(physical_type: PhysicalType) -> Self {
+		Self {
+			values: Values::new(physical_type),
+			def_levels: Vec::new(),
+			rep_levels: Vec::new(),
+		}
+	}
+
+	fn write(&mut self, column_writer: ColumnWriter) -> Result<ColumnWriter> {
+		let def_levels = mem::take(&mut self.def_levels);
+		let rep_levels = mem::take(&mut self.rep_levels);
+		macro_rules! write {
+			($($values:ident => $writer:ident,)*) => {
+				match (column_writer, &mut self.values) {
+					$((ColumnWriter::$writer(mut typed), Values::$values(values)) => {
+						let values = mem::take(values);
+						let _ = typed.write_batch(&values, Some(&def_levels), Some(&rep_levels))?;
+						Ok(ColumnWriter::$writer(typed))
+					})*
+					_ => Err(general_err!("Column writer doesn't match column type")),
+				}
+			};
+		}
+		write!(
+			Bool => BoolColumnWriter,
+			Int32 => Int32ColumnWriter,
+			Int64 => Int64ColumnWriter,
+			Int96 => Int96ColumnWriter,
+			Float => FloatColumnWriter,
+			Double => DoubleColumnWriter,
+			ByteArray => ByteArrayColumnWriter,
+			FixedLenByteArray => FixedLenByteArrayColumnWriter,
+		)
+	}
+}
+
+/// Non-null values of a leaf column, converted to its physical type.
+pub(super) enum Values {
+	Bool(Vec<bool>),
+	Int32(Vec<i32>),
+	Int64(Vec<i64>),
+	Int96(Vec<Int96>),
+	Float(Vec<f32>),
+	Double(Vec<f64>),
+	ByteArray(Vec<ByteArray>),
+	FixedLenByteArray(Vec<ByteArray>),
+}
+
+impl Values {
+	pub(super) fn new(physical_type: PhysicalType) -> Self {
+		match physical_type {
+			PhysicalType::Boolean => Values::Bool(Vec::new()),
+			PhysicalType::Int32 => Values::Int32(Vec::new()),
+			PhysicalType::Int64 => Values::Int64(Vec::new()),
+			PhysicalType::Int96 => Values::Int96(Vec::new()),
+			PhysicalType::Float => Values::Float(Vec::new()),
+			PhysicalType::Double => Values::Double(Vec::new()),
+			PhysicalType::ByteArray => Values::ByteArray(Vec::new()),
+			PhysicalType::FixedLenByteArray => Values::FixedLenByteArray(Vec::new()),
+		}
+	}
+
+	pub(super) fn push(&mut self, field: &Type, value: Value) -> Result<()> {
+		let err = |value: &Value| {
+			ParquetError::General(format!(
+				"Can't write {:?} to field {} of type {} ({})",
+				value,
+				field.name(),
+				field.get_physical_type(),
+				field.get_basic_info().logical_type()
+			))
+		};
+		let logical_type = field.get_basic_info().logical_type();
+		match self {
+			Values::Bool(values) => values.push(value.as_bool().map_err(|_| err(&value))?),
+			Values::Int32(values) => values.push(match (logical_type, &value) {
+				(_, Value::U8(x)) => i32::from(*x),
+				(_, Value::I8(x)) => i32::from(*x),
+				(_, Value::U16(x)) => i32::from(*x),
+				(_, Value::I16(x)) => i32::from(*x),
+				(_, Value::U32(x)) => *x as i32,
+				(_, Value::I32(x)) => *x,
+				(LogicalType::Date, Value::Date(date)) => {
+					date.as_days().try_into().map_err(|_| err(&value))?
+				}
+				(LogicalType::TimeMillis, Value::Time(time)) => {
+					(nanos_of_day(time.hour(), time.minute(), time.second(), time.nanosecond())
+						/ NANOS_PER_MILLI)
+						.try_into()
+						.unwrap()
+				}
+				(LogicalType::Decimal, Value::Decimal(decimal)) => decimal_to_i64(decimal.data())
+					.and_then(|x| x.try_into().ok())
+					.ok_or_else(|| err(&value))?,
+				_ => return Err(err(&value)),
+			}),
+			Values::Int64(values) => values.push(match (logical_type, &value) {
+				(_, Value::U64(x)) => *x as i64,
+				(_, Value::I64(x)) => *x,
+				(LogicalType::TimeMicros, Value::Time(time)) => {
+					(nanos_of_day(time.hour(), time.minute(), time.second(), time.nanosecond())
+						/ NANOS_PER_MICRO)
+						.try_into()
+						.unwrap()
+				}
+				(LogicalType::TimestampMillis, Value::DateTime(date_time)) => {
+					let (days, nanos) = date_time_to_days_nanos(date_time);
+					days * SECONDS_PER_DAY * 1_000 + (nanos / NANOS_PER_MILLI) as i64
+				}
+				(LogicalType::TimestampMicros, Value::DateTime(date_time)) => {
+					let (days, nanos) = date_time_to_days_nanos(date_time);
+					days * SECONDS_PER_DAY * 1_000_000 + (nanos / NANOS_PER_MICRO) as i64
+				}
+				(LogicalType::Decimal, Value::Decimal(decimal)) => {
+					decimal_to_i64(decimal.data()).ok_or_else(|| err(&value))?
+				}
+				_ => return Err(err(&value)),
+			}),
+			Values::Int96(values) => values.push(match &value {
+				Value::DateTime(date_time) => {
+					let (days, nanos) = date_time_to_days_nanos(date_time);
+					let day = (days + JULIAN_DAY_OF_EPOCH)
+						.try_into()
+						.map_err(|_| err(&value))?;
+					Int96::new(nanos as u32, (nanos >> 32) as u32, day)
+				}
+				_ => return Err(err(&value)),
+			}),
+			Values::Float(values) => values.push(value.as_f32().map_err(|_| err(&value))?),
+			Values::Double(values) => values.push(value.as_f64().map_err(|_| err(&value))?),
+			Values::ByteArray(values) => values.push(bytes(logical_type, value, &err)?.into()),
+			Values::FixedLenByteArray(values) => {
+				let len: usize = field.get_type_length().try_into().unwrap();
+				let mut bytes = bytes(logical_type, value, &err)?;
+				if logical_type == LogicalType::Decimal && bytes.len() < len {
+					// Sign-extend the big-endian two's complement representation
+					let pad = if bytes.first().map_or(false, |x| x & 0x80 != 0) {
+						0xff
+					} else {
+						0
+					};
+					let _ = bytes.splice(0..0, std::iter::repeat(pad).take(len - bytes.len()));
+				}
+				if bytes.len() != len {
+					return Err(general_err!(
+						"Can't write {} bytes to field {} of type fixed_len_byte_array({})",
+						bytes.len(),
+						field.name(),
+						len
+					));
+				}
+				values.push(bytes.into())
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Convert a value destined for a `byte_array` or `fixed_len_byte_array` column to
+/// bytes. Types that are stored as strings, like [`Url`](amadeus_types::Url), are
+/// written as their string representation.
+fn bytes(
+	logical_type: LogicalType, value: Value, err: &impl Fn(&Value) -> ParquetError,
+) -> Result<Vec<u8>> {
+	Ok(match (logical_type, value) {
+		(LogicalType::Decimal, Value::Decimal(decimal)) => decimal.data().to_owned(),
+		(LogicalType::Bson, Value::Bson(bson)) => bson.into(),
+		(LogicalType::Json, Value::Json(json)) => String::from(json).into_bytes(),
+		(LogicalType::Enum, Value::Enum(enum_)) => String::from(enum_).into_bytes(),
+		(LogicalType::Utf8, Value::String(string)) => string.into_bytes(),
+		(LogicalType::Utf8, Value::Url(url)) => url.to_string().into_bytes(),
+		(LogicalType::Utf8, Value::Webpage(webpage)) => webpage.to_string().into_bytes(),
+		(LogicalType::Utf8, Value::IpAddr(ip_addr)) => ip_addr.to_string().into_bytes(),
+		(LogicalType::Utf8, Value::Timezone(timezone)) => timezone.to_string().into_bytes(),
+		(LogicalType::Utf8, Value::DateWithoutTimezone(date)) => date.to_string().into_bytes(),
+		(LogicalType::Utf8, Value::TimeWithoutTimezone(time)) => time.to_string().into_bytes(),
+		(LogicalType::Utf8, Value::DateTimeWithoutTimezone(date_time)) => {
+			date_time.to_string().into_bytes()
+		}
+		(LogicalType::None, Value::List(list)) => list
+			.into_iter()
+			.map(|byte| byte.into_u8())
+			.collect::<std::result::Result<_, _>>()
+			.map_err(|error| general_err!("{}", error))?,
+		(_, value) => return Err(err(&value)),
+	})
+}
+
+fn nanos_of_day(hour: u8, minute: u8, second: u8, nanosecond: u32) -> u64 {
+	(u64::from(hour) * 3600 + u64::from(minute) * 60 + u64::from(second)) * NANOS_PER_SECOND
+		+ u64::from(nanosecond)
+}
+
+fn date_time_to_days_nanos(date_time: &amadeus_types::DateTime) -> (i64, u64) {
+	let time = date_time.time();
+	(
+		date_time.date().as_days(),
+		nanos_of_day(time.hour(), time.minute(), time.second(), time.nanosecond()),
+	)
+}
+
+/// Interpret big-endian two's complement bytes as an `i64`.
+fn decimal_to_i64(bytes: &[u8]) -> Option<i64> {
+	if bytes.len() > 8 {
+		return None;
+	}
+	let pad = if bytes.first().map_or(false, |x| x & 0x80 != 0) {
+		0xff
+	} else {
+		0
+	};
+	let mut buf = [pad; 8];
+	buf[8 - bytes.len()..].copy_from_slice(bytes);
+	Some(i64::from_be_bytes(buf))
+}
+
+/// Split a group value into one value per field of `schema`. Named groups are matched by
+/// name, unnamed groups (such as tuples and derived structs) positionally.
+fn group_fields(schema: &Type, value: Value) -> Result<Vec<Value>> {
+	let group = value.into_group().map_err(|err| general_err!("{}", err))?;
+	let fields = schema.get_fields();
+	if let Some(field_names) = group.field_names().cloned() {
+		let mut values = group
+			.into_fields()
+			.into_iter()
+			.map(Some)
+			.collect::<Vec<_>>();
+		fields
+			.iter()
+			.map(|field| {
+				field_names
+					.get(field.name())
+					.and_then(|&i| values[i].take())
+					.ok_or_else(|| general_err!("Missing field {}", field.name()))
+			})
+			.collect()
+	} else {
+		let values = group.into_fields();
+		if values.len() != fields.len() {
+			return Err(general_err!(
+				"Group has {} fields, but schema {} has {}",
+				values.len(),
+				schema.name(),
+				fields.len()
+			));
+		}
+		Ok(values)
+	}
+}
+
+fn num_leaves(schema: &Type) -> usize {
+	if schema.is_primitive() {
+		1
+	} else {
+		schema
+			.get_fields()
+			.iter()
+			.map(|field| num_leaves(field))
+			.sum()
+	}
+}
+
+fn is_list(schema: &Type) -> bool {
+	schema.get_fields().len() == 1
+		&& schema.get_fields()[0].get_basic_info().repetition() == Repetition::Repeated
+}
+
+/// Whether the repeated group of a list wraps the element, as opposed to being the
+/// element itself. See [`parse_list`](super::impls).
+fn is_three_level_list(schema: &Type) -> bool {
+	let repeated = &schema.get_fields()[0];
+	repeated.is_group()
+		&& repeated.get_fields().len() == 1
+		&& repeated.name() != "array"
+		&& repeated.name() != format!("{}_tuple", schema.name())
+}
+
+fn is_map(schema: &Type) -> bool {
+	schema.get_fields().len() == 1 && {
+		let key_value = &schema.get_fields()[0];
+		key_value.get_basic_info().repetition() == Repetition::Repeated
+			&& key_value.is_group()
+			&& key_value.get_fields().len() == 2
+	}
+}
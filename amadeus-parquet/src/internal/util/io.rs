@@ -16,10 +16,10 @@
 // under the License.
 
 use std::{
-	cell::RefCell, cmp, fs::File, io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write}, rc::Rc
+	cell::RefCell, cmp, io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write}, rc::Rc
 };
 
-use crate::internal::file::reader::ParquetReader;
+use crate::internal::file::{reader::ParquetReader, writer::ParquetWriter};
 
 // ----------------------------------------------------------------------
 // Read/Write wrappers for `File`.
@@ -138,17 +138,17 @@ impl<R: ParquetReader> Position for FileSource<R> {
 
 /// Struct that represents `File` output stream with position tracking.
 /// Used as a sink in file writer.
-pub struct FileSink {
-	buf: BufWriter<File>,
+pub struct FileSink<W: ParquetWriter> {
+	buf: BufWriter<W>,
 	// This is not necessarily position in the underlying file,
 	// but rather current position in the sink.
 	pos: u64,
 }
 
-impl FileSink {
+impl<W: ParquetWriter> FileSink<W> {
 	/// Creates new file sink.
 	/// Position is set to whatever position file has.
-	pub fn new(file: &File) -> Self {
+	pub fn new(file: &W) -> Self {
 		let mut owned_file = file.try_clone().unwrap();
 		let pos = owned_file.seek(SeekFrom::Current(0)).unwrap();
 		Self {
@@ -158,7 +158,7 @@ impl FileSink {
 	}
 }
 
-impl Write for FileSink {
+impl<W: ParquetWriter> Write for FileSink<W> {
 	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
 		let num_bytes = self.buf.write(buf)?;
 		self.pos += num_bytes as u64;
@@ -170,7 +170,7 @@ impl Write for FileSink {
 	}
 }
 
-impl Position for FileSink {
+impl<W: ParquetWriter> Position for FileSink<W> {
 	fn pos(&self) -> u64 {
 		self.pos
 	}
@@ -46,21 +46,28 @@ mod wrap {
 	use super::internal;
 	use async_trait::async_trait;
 	use educe::Educe;
-	use futures::{pin_mut, stream, AsyncReadExt, FutureExt, StreamExt};
+	use futures::{stream, FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt};
 	use internal::{
-		errors::ParquetError as InternalParquetError, file::reader::{FileReader, ParquetReader, SerializedFileReader}
+		errors::ParquetError as InternalParquetError, file::{
+			bloom_filter::BloomFilter, metadata::{ParquetMetaDataPtr, RowGroupMetaData}, properties::WriterProperties, reader::{FileReader, Never, PartialFile, RowGroupReader, SerializedFileReader}, writer::{FileWriter, SerializedFileWriter, TryClone}, FOOTER_SIZE
+		}, record::{
+			default_schema, predicates::{GroupPredicate, ValuePredicate}, RootSchema, RowGroupFilter, RowIter, ValueWriter
+		}, schema::{parser::parse_message_type, types::SchemaDescriptor}
 	};
 	use serde::{Deserialize, Serialize};
 	use serde_closure::*;
 	use std::{
-		error, fmt::{self, Debug, Display}, io::Cursor, marker::PhantomData, ops::FnMut
+		cell::RefCell, collections::HashMap, convert::TryInto, error, fmt::{self, Debug, Display}, io, marker::PhantomData, mem, ops::FnMut, rc::Rc
 	};
 
 	use amadeus_core::{
-		file::{Directory, File, Page, Partition, PathBuf}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+		file::{
+			existing_file, read_counted, Directory, File, OutputDirectory, Page, PartFileFormat, PartFileSink, Partition, PathBuf, WriteMode
+		}, into_par_stream::IntoDistributedStream, par_sink::RecordError, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Destination, Source
 	};
+	use amadeus_types::{Group, Value};
 
-	pub use internal::record::ParquetData;
+	pub use internal::record::{Filter as ParquetFilter, ParquetData};
 
 	#[doc(hidden)]
 	pub mod derive {
@@ -78,7 +85,9 @@ mod wrap {
 		File: amadeus_core::file::File,
 		Row: ParquetData,
 	{
-		partitions: Vec<File::Partition>,
+		files: Vec<ParquetFile<File::Partition>>,
+		filter: Option<ParquetFilter>,
+		projection: Option<Vec<String>>,
 		marker: PhantomData<fn() -> Row>,
 	}
 	impl<F, Row> Parquet<F, Row>
@@ -86,13 +95,124 @@ mod wrap {
 		F: File,
 		Row: ParquetData + 'static,
 	{
+		/// Read the footer of each file up front, so that its row groups can be read as
+		/// separate tasks.
 		pub async fn new(file: F) -> Result<Self, <Self as Source>::Error> {
+			let partitions = file.partitions().await.map_err(ParquetError::File)?;
+			let files = stream::iter(partitions)
+				.map(|partition| async move {
+					let pages = partition
+						.clone()
+						.pages()
+						.await
+						.map_err(ParquetError::Partition)?;
+					let mut files = Vec::new();
+					for (i, page) in pages.iter().enumerate() {
+						let footer = Footer::read(page).await?;
+						let _ = footer.metadata()?;
+						files.push(ParquetFile {
+							partition: partition.clone(),
+							page: i,
+							footer,
+						});
+					}
+					Ok::<_, <Self as Source>::Error>(files)
+				})
+				.buffered(READ_CONCURRENCY)
+				.try_collect::<Vec<_>>()
+				.await?
+				.into_iter()
+				.flatten()
+				.collect();
 			Ok(Self {
-				partitions: file.partitions().await.map_err(ParquetError::File)?,
+				files,
+				filter: None,
+				projection: None,
 				marker: PhantomData,
 			})
 		}
+
+		/// Skip the row groups whose statistics show that none of their rows match
+		/// `filter`, without reading them.
+		///
+		/// The rows of the row groups that aren't skipped are not filtered, so rows
+		/// should still be filtered for exact results.
+		pub fn with_filter(self, filter: ParquetFilter) -> Self {
+			Self {
+				filter: Some(filter),
+				..self
+			}
+		}
+
+		/// Split the row groups of each file into tasks of about `task_size` bytes,
+		/// leaving out those whose statistics show that none of their rows match the
+		/// filter.
+		fn split(&self, task_size: u64) -> Vec<RowGroups<F::Partition>> {
+			self.files
+				.iter()
+				.flat_map(|file| file.split(self.filter.as_ref(), task_size))
+				.collect()
+		}
+	}
+	impl<F, Row> Parquet<F, Row>
+	where
+		F: File,
+		Row: Projection + 'static,
+	{
+		/// Read only the columns at `columns`, given as dotted paths like `"a.b"` for the
+		/// field `b` of the group `a`, so that the rest are never read. Rows are groups of
+		/// the selected fields, in the order they're first given.
+		pub fn with_projection<I>(self, columns: I) -> Self
+		where
+			I: IntoIterator,
+			I::Item: Into<String>,
+		{
+			Self {
+				projection: Some(columns.into_iter().map(Into::into).collect()),
+				..self
+			}
+		}
+	}
+
+	/// Row types that can be read with only some of a file's columns, with
+	/// [`with_projection`](Parquet::with_projection).
+	pub trait Projection: ParquetData {
+		/// The predicate that reads only the columns at `columns`.
+		fn projection(columns: &[String]) -> Self::Predicate;
+	}
+	impl Projection for Value {
+		fn projection(columns: &[String]) -> Self::Predicate {
+			ValuePredicate::Group(Some(GroupPredicate::columns(columns)))
+		}
+	}
+	impl Projection for Group {
+		fn projection(columns: &[String]) -> Self::Predicate {
+			GroupPredicate::columns(columns)
+		}
+	}
+
+	/// The predicate that reads only the columns at `columns`, for the row types that
+	/// implement [`Projection`].
+	trait MaybeProjection: ParquetData {
+		fn maybe_projection(columns: &[String]) -> Option<Self::Predicate>;
+	}
+	impl<T> MaybeProjection for T
+	where
+		T: ParquetData,
+	{
+		default fn maybe_projection(_columns: &[String]) -> Option<Self::Predicate> {
+			None
+		}
+	}
+	impl<T> MaybeProjection for T
+	where
+		T: Projection,
+	{
+		fn maybe_projection(columns: &[String]) -> Option<Self::Predicate> {
+			Some(T::projection(columns))
+		}
 	}
+
 	impl<F, Row> Source for Parquet<F, Row>
 	where
 		F: File,
@@ -115,37 +235,316 @@ mod wrap {
 		}
 		#[allow(clippy::let_and_return)]
 		fn dist_stream(self) -> Self::DistStream {
-			self.partitions
+			let row_groups = self.split(TASK_SIZE);
+			let (filter, projection) = (self.filter, self.projection);
+			row_groups
 				.into_dist_stream()
-				.flat_map(FnMut!(|partition: F::Partition| async move {
-					Ok(stream::iter(
-						partition
-							.pages()
-							.await
-							.map_err(ParquetError::Partition)?
-							.into_iter(),
-					)
-					.flat_map(|page| {
-						async move {
-							let mut buf = Vec::with_capacity(10 * 1024 * 1024);
-							let reader = Page::reader(page);
-							pin_mut!(reader);
-							let buf = PassError::new(
-								reader.read_to_end(&mut buf).await.map(|_| Cursor::new(buf)),
-							);
-							Ok(stream::iter(
-								SerializedFileReader::new(buf)?.get_row_iter::<Row>(None)?,
-							))
-						}
-						.map(ResultExpandIter::new)
-						.flatten_stream()
+				.flat_map(FnMut!(move |row_groups: RowGroups<F::Partition>| {
+					row_groups.read::<Row, F::Error>(filter.clone(), projection.clone())
+				}))
+		}
+	}
+
+	/// The footer of a Parquet file, which holds its metadata. It's read once when the
+	/// file is opened and sent along with each task, so that tasks needn't read it again.
+	#[derive(Educe, Clone, Serialize, Deserialize)]
+	#[educe(Debug)]
+	struct Footer {
+		len: u64,
+		offset: u64,
+		#[educe(Debug(ignore))]
+		bytes: Box<[u8]>,
+	}
+	impl Footer {
+		/// Read the footer of a Parquet file, along with the length of the file.
+		async fn read<P, A, B>(page: &P) -> Result<Self, ParquetError<A, B, P::Error>>
+		where
+			P: Page,
+		{
+			let len = page.len().await.map_err(ParquetError::Page)?;
+			let mut offset = len - len.min(FOOTER_READ_SIZE);
+			let mut bytes = read_counted(page, offset, (len - offset) as usize)
+				.await
+				.map_err(ParquetError::Page)?;
+			if bytes.len() >= FOOTER_SIZE {
+				let footer = &bytes[bytes.len() - FOOTER_SIZE..];
+				let metadata_len = u32::from_le_bytes(footer[..4].try_into().unwrap());
+				let footer_len = u64::from(metadata_len) + FOOTER_SIZE as u64;
+				if footer_len > len - offset && footer_len <= len {
+					offset = len - footer_len;
+					bytes = read_counted(page, offset, footer_len as usize)
+						.await
+						.map_err(ParquetError::Page)?;
+				} else if footer_len < len - offset {
+					offset = len - footer_len;
+					bytes = bytes[bytes.len() - footer_len as usize..].into();
+				}
+			}
+			Ok(Self { len, offset, bytes })
+		}
+
+		/// Parse the metadata of the file, without reading any more of it.
+		fn metadata(&self) -> Result<ParquetMetaDataPtr, InternalParquetError> {
+			let file = PartialFile::new(self.len, vec![(self.offset, self.bytes.clone())]);
+			Ok(SerializedFileReader::new(file)?.metadata())
+		}
+	}
+
+	/// A Parquet file whose footer has been read.
+	#[derive(Clone, Serialize, Deserialize, Debug)]
+	struct ParquetFile<P> {
+		partition: P,
+		page: usize,
+		footer: Footer,
+	}
+	impl<P> ParquetFile<P>
+	where
+		P: Partition,
+	{
+		/// Split the row groups of the file into runs of about `task_size` bytes each,
+		/// leaving out those whose statistics show that none of their rows match
+		/// `filter`.
+		fn split(&self, filter: Option<&ParquetFilter>, task_size: u64) -> Vec<RowGroups<P>> {
+			let task = |row_groups| RowGroups {
+				partition: self.partition.clone(),
+				page: self.page,
+				footer: self.footer.clone(),
+				row_groups,
+			};
+			// If the footer or filter are invalid, the task reports it when it's run.
+			let metadata = match self.footer.metadata() {
+				Ok(metadata) => metadata,
+				Err(_) => return vec![task(Vec::new())],
+			};
+			let filter =
+				filter.and_then(|filter| filter.bind(metadata.file_metadata().schema_descr()).ok());
+			let mut tasks = Vec::new();
+			let (mut row_groups, mut size) = (Vec::new(), 0);
+			for (i, row_group) in metadata.row_groups().iter().enumerate() {
+				if let Some(filter) = &filter {
+					if !filter.may_match(row_group, &HashMap::new()) {
+						continue;
+					}
+				}
+				row_groups.push(i);
+				size += row_group.total_byte_size().max(0) as u64;
+				if size >= task_size {
+					tasks.push(task(mem::take(&mut row_groups)));
+					size = 0;
+				}
+			}
+			if !row_groups.is_empty() {
+				tasks.push(task(row_groups));
+			}
+			tasks
+		}
+	}
+
+	/// Row groups of a Parquet file, which are read as one task so that a large file can
+	/// be read by many workers at once.
+	#[derive(Clone, Serialize, Deserialize, Debug)]
+	struct RowGroups<P> {
+		partition: P,
+		page: usize,
+		footer: Footer,
+		row_groups: Vec<usize>,
+	}
+	impl<P> RowGroups<P>
+	where
+		P: Partition,
+	{
+		/// Read the rows of the row groups, skipping those whose Bloom filters show that
+		/// none of their rows match `filter`.
+		fn read<Row, A>(
+			self, filter: Option<ParquetFilter>, projection: Option<Vec<String>>,
+		) -> impl Stream<Item = Result<Row, ParquetError<A, P::Error, <P::Page as Page>::Error>>>
+		where
+			Row: ParquetData,
+		{
+			let RowGroups {
+				partition,
+				page,
+				footer,
+				row_groups,
+			} = self;
+			async move {
+				let metadata = footer.metadata()?;
+				let filter = match &filter {
+					Some(filter) => Some(filter.bind(metadata.file_metadata().schema_descr())?),
+					None => None,
+				};
+				let page = partition
+					.pages()
+					.await
+					.map_err(ParquetError::Partition)?
+					.into_iter()
+					.nth(page)
+					.ok_or_else(|| {
+						ParquetError::Parquet(InternalParquetError::General(format!(
+							"Page {} of the partition no longer exists",
+							page
+						)))
+					})?;
+				let page = Rc::new(page);
+				let len = footer.len;
+				let mut indices = Vec::new();
+				for i in row_groups {
+					let row_group = metadata.row_groups().get(i).ok_or_else(|| {
+						ParquetError::Parquet(InternalParquetError::General(format!(
+							"Row group {} of the file no longer exists",
+							i
+						)))
+					})?;
+					let may_match = match &filter {
+						Some(filter) => may_match(&*page, len, filter, row_group).await?,
+						None => true,
+					};
+					if may_match {
+						indices.push(i);
+					}
+				}
+				Ok(stream::iter(indices)
+					.then(move |i| {
+						read_row_group::<_, Row, _, _>(
+							page.clone(),
+							len,
+							metadata.clone(),
+							i,
+							projection.clone(),
+						)
 					})
-					.map(|row: Result<Result<Row, _>, Self::Error>| Ok(row??)))
+					.flat_map(|rows| stream::iter(ResultExpandIter::new(rows))))
+			}
+			.map(ResultExpandIter::new)
+			.flatten_stream()
+			.map(
+				|row: Result<
+					Result<Result<Row, InternalParquetError>, ParquetError<A, P::Error, _>>,
+					ParquetError<A, P::Error, _>,
+				>| Ok(row???),
+			)
+		}
+	}
+
+	/// How many bytes of row groups, uncompressed, are read as one task.
+	const TASK_SIZE: u64 = 64 * 1024 * 1024;
+	/// How much of the end of a file to read in the hope it holds all of the metadata,
+	/// so that it's usually read with a single request.
+	const FOOTER_READ_SIZE: u64 = 64 * 1024;
+	/// Column chunks closer than this are read with a single request.
+	const MAX_READ_GAP: u64 = 64 * 1024;
+	/// How many column chunks to read concurrently.
+	const READ_CONCURRENCY: usize = 16;
+	/// How much of a Bloom filter to read in the hope it holds all of it, so that it's
+	/// usually read with a single request.
+	const BLOOM_FILTER_READ_SIZE: u64 = 4 * 1024;
+
+	/// Whether any rows of `row_group` might match `filter`, reading the Bloom filters of
+	/// its column chunks if its statistics don't rule it out.
+	async fn may_match<P, A, B>(
+		page: &P, len: u64, filter: &RowGroupFilter, row_group: &RowGroupMetaData,
+	) -> Result<bool, ParquetError<A, B, P::Error>>
+	where
+		P: Page,
+	{
+		let mut bloom_filters = HashMap::new();
+		if !filter.may_match(row_group, &bloom_filters) {
+			return Ok(false);
+		}
+		for column in filter.bloom_filter_columns(row_group) {
+			let offset = row_group.column(column).bloom_filter_offset().unwrap() as u64;
+			let bloom_filter = read_bloom_filter(page, len, offset).await?;
+			let _ = bloom_filters.insert(column, bloom_filter);
+		}
+		Ok(bloom_filters.is_empty() || filter.may_match(row_group, &bloom_filters))
+	}
+
+	/// Read the Bloom filter at `offset` of a Parquet file of length `len`.
+	async fn read_bloom_filter<P, A, B>(
+		page: &P, len: u64, offset: u64,
+	) -> Result<BloomFilter, ParquetError<A, B, P::Error>>
+	where
+		P: Page,
+	{
+		if offset >= len {
+			return Err(ParquetError::Parquet(InternalParquetError::General(
+				format!(
+					"Bloom filter offset {} is beyond the end of the file",
+					offset
+				),
+			)));
+		}
+		let mut bytes = read_counted(
+			page,
+			offset,
+			(len - offset).min(BLOOM_FILTER_READ_SIZE) as usize,
+		)
+		.await
+		.map_err(ParquetError::Page)?
+		.into_vec();
+		let (header_len, bitset_len) = BloomFilter::read_header(&bytes)?;
+		if bytes.len() < header_len + bitset_len {
+			let rest = read_counted(
+				page,
+				offset + bytes.len() as u64,
+				header_len + bitset_len - bytes.len(),
+			)
+			.await
+			.map_err(ParquetError::Page)?;
+			bytes.extend_from_slice(&rest);
+		}
+		Ok(BloomFilter::from_bitset(
+			&bytes[header_len..header_len + bitset_len],
+		))
+	}
+
+	/// Read the column chunks of row group `i` of a Parquet file that rows are read from,
+	/// skipping the rest, and iterate over its rows.
+	async fn read_row_group<P, Row, A, B>(
+		page: Rc<P>, len: u64, metadata: ParquetMetaDataPtr, i: usize,
+		projection: Option<Vec<String>>,
+	) -> Result<RowIter<SerializedFileReader<Never>, Row>, ParquetError<A, B, P::Error>>
+	where
+		P: Page,
+		Row: ParquetData,
+	{
+		let projection = projection.as_deref().and_then(Row::maybe_projection);
+		let file_reader = SerializedFileReader::with_metadata(
+			PartialFile::new(len, Vec::new()),
+			metadata.clone(),
+		);
+		let columns = RowIter::<SerializedFileReader<Never>, Row>::columns(
+			projection.as_ref(),
+			&file_reader.get_row_group(i)?,
+		)?;
+		let row_group = metadata.row_group(i);
+		let mut ranges = columns
+			.into_iter()
+			.map(|column| row_group.column(column).byte_range())
+			.collect::<Vec<_>>();
+		ranges.sort_unstable();
+		let mut coalesced: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+		for (offset, len) in ranges {
+			match coalesced.last_mut() {
+				Some((last_offset, last_len))
+					if offset <= *last_offset + *last_len + MAX_READ_GAP =>
+				{
+					*last_len = (*last_len).max(offset + len - *last_offset);
 				}
-				.map(ResultExpandIter::new)
-				.flatten_stream()
-				.map(|row: Result<Result<Row, Self::Error>, Self::Error>| Ok(row??))))
+				_ => coalesced.push((offset, len)),
+			}
 		}
+		let ranges = stream::iter(coalesced)
+			.map(|(offset, len)| {
+				read_counted(&*page, offset, len as usize).map_ok(move |bytes| (offset, bytes))
+			})
+			.buffered(READ_CONCURRENCY)
+			.try_collect()
+			.await
+			.map_err(ParquetError::Page)?;
+		let file_reader =
+			SerializedFileReader::with_metadata(PartialFile::new(len, ranges), metadata);
+		Ok(file_reader.get_row_group(i)?.get_row_iter(projection)?)
 	}
 
 	// impl<P> ParquetReader for amadeus_core::file::Reader<P>
@@ -218,6 +617,203 @@ mod wrap {
 		}
 	}
 
+	/// The number of rows each worker buffers before writing them out as a row group.
+	const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+	/// Writes `Row`s as Parquet files to an [`OutputDirectory`].
+	///
+	/// Each worker writes its rows to its own part file; once they've all finished, the
+	/// part files are committed. See [`PartFileSink`]. Column chunks record the least and
+	/// greatest values and number of nulls written, so that reading with
+	/// [`with_filter`](Parquet::with_filter) can skip row groups.
+	#[derive(Educe)]
+	#[educe(Clone, Debug)]
+	pub struct ParquetDestination<D, Row>
+	where
+		D: OutputDirectory,
+		Row: ParquetData,
+	{
+		directory: D,
+		schema: String,
+		mode: WriteMode,
+		existing: Option<PathBuf>,
+		marker: PhantomData<fn() -> Row>,
+	}
+	impl<D, Row> ParquetDestination<D, Row>
+	where
+		D: OutputDirectory,
+		Row: ParquetData + 'static,
+	{
+		/// Write to `directory`, with the schema derived from `Row`, checking whether it
+		/// already contains files.
+		pub async fn new(directory: D) -> Result<Self, ParquetDestinationError<D::Error>> {
+			let schema = default_schema::<Row>().map_err(ParquetDestinationError::Parquet)?;
+			let existing = existing_file(&directory).await?;
+			Ok(Self {
+				directory,
+				schema,
+				mode: WriteMode::default(),
+				existing,
+				marker: PhantomData,
+			})
+		}
+		/// Write to `directory`, with the given message type as the schema. `Row` must be
+		/// readable from it.
+		pub async fn with_schema(
+			directory: D, schema: &str,
+		) -> Result<Self, ParquetDestinationError<D::Error>> {
+			let _: RootSchema<Row> = schema.parse().map_err(ParquetDestinationError::Parquet)?;
+			let existing = existing_file(&directory).await?;
+			Ok(Self {
+				directory,
+				schema: schema.to_owned(),
+				mode: WriteMode::default(),
+				existing,
+				marker: PhantomData,
+			})
+		}
+		/// What to do if `directory` already contains files. Defaults to
+		/// [`WriteMode::FailIfExists`].
+		pub fn mode(self, mode: WriteMode) -> Self {
+			Self { mode, ..self }
+		}
+	}
+	impl<D, Row> Destination for ParquetDestination<D, Row>
+	where
+		D: OutputDirectory,
+		Row: ParquetData + Into<Value> + 'static,
+	{
+		type Item = Row;
+		type Error = ParquetDestinationError<D::Error>;
+
+		type ParSink = PartFileSink<D, Row, ParquetFormat>;
+		type DistSink = PartFileSink<D, Row, ParquetFormat>;
+
+		fn par_sink(self) -> Self::ParSink {
+			let format = ParquetFormat {
+				schema: self.schema,
+				row_group_size: ROW_GROUP_SIZE,
+			};
+			PartFileSink::new(self.directory, format, self.mode, self.existing)
+		}
+		fn dist_sink(self) -> Self::DistSink {
+			self.par_sink()
+		}
+	}
+
+	#[derive(Clone, Serialize, Deserialize)]
+	pub struct ParquetFormat {
+		schema: String,
+		row_group_size: usize,
+	}
+	impl<D, Row> PartFileFormat<D, Row> for ParquetFormat
+	where
+		D: OutputDirectory,
+		Row: Into<Value>,
+	{
+		type Writer = ParquetFileWriter;
+		type Error = ParquetDestinationError<D::Error>;
+
+		const EXTENSION: &'static str = "parquet";
+
+		fn writer(&self) -> Result<Self::Writer, Self::Error> {
+			ParquetFileWriter::new(&self.schema).map_err(ParquetDestinationError::Parquet)
+		}
+		fn write(
+			&self, writer: &mut Self::Writer, row: Row, buf: &mut Vec<u8>,
+		) -> Result<(), Self::Error> {
+			writer
+				.rows
+				.write(row.into())
+				.map_err(ParquetDestinationError::Parquet)?;
+			if writer.rows.num_rows() >= self.row_group_size {
+				writer
+					.write_row_group()
+					.map_err(ParquetDestinationError::Parquet)?;
+				buf.extend_from_slice(&writer.buffer.take());
+			}
+			Ok(())
+		}
+		fn close(&self, mut writer: Self::Writer, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+			writer.close().map_err(ParquetDestinationError::Parquet)?;
+			buf.extend_from_slice(&writer.buffer.take());
+			Ok(())
+		}
+	}
+
+	/// The state kept while writing a Parquet file.
+	pub struct ParquetFileWriter {
+		file: SerializedFileWriter<SharedBuffer>,
+		rows: ValueWriter,
+		buffer: SharedBuffer,
+	}
+	impl ParquetFileWriter {
+		fn new(schema: &str) -> Result<Self, InternalParquetError> {
+			let schema = Rc::new(parse_message_type(schema)?);
+			let descr = Rc::new(SchemaDescriptor::new(schema.clone()));
+			let properties = Rc::new(WriterProperties::builder().build());
+			let buffer = SharedBuffer::default();
+			let file = SerializedFileWriter::new(buffer.clone(), schema, properties)?;
+			Ok(Self {
+				file,
+				rows: ValueWriter::new(descr),
+				buffer,
+			})
+		}
+		fn write_row_group(&mut self) -> Result<(), InternalParquetError> {
+			if self.rows.num_rows() == 0 {
+				return Ok(());
+			}
+			let mut row_group_writer = self.file.next_row_group()?;
+			self.rows.flush(&mut *row_group_writer)?;
+			self.file.close_row_group(row_group_writer)
+		}
+		fn close(&mut self) -> Result<(), InternalParquetError> {
+			self.write_row_group()?;
+			self.file.close()
+		}
+	}
+
+	/// An in-memory sink for [`SerializedFileWriter`], that is drained as the file is
+	/// written out.
+	#[derive(Clone, Default)]
+	struct SharedBuffer(Rc<RefCell<(u64, Vec<u8>)>>);
+	impl SharedBuffer {
+		/// Take the bytes written since the last call.
+		fn take(&self) -> Vec<u8> {
+			let (offset, buf) = &mut *self.0.borrow_mut();
+			let buf = mem::take(buf);
+			*offset += buf.len() as u64;
+			buf
+		}
+	}
+	impl io::Write for SharedBuffer {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0.borrow_mut().1.extend_from_slice(buf);
+			Ok(buf.len())
+		}
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+	impl io::Seek for SharedBuffer {
+		fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+			let (offset, buf) = &*self.0.borrow();
+			match pos {
+				io::SeekFrom::Current(0) => Ok(offset + buf.len() as u64),
+				_ => Err(io::Error::new(
+					io::ErrorKind::Other,
+					"SharedBuffer can only report its position",
+				)),
+			}
+		}
+	}
+	impl TryClone for SharedBuffer {
+		fn try_clone(&self) -> io::Result<Self> {
+			Ok(self.clone())
+		}
+	}
+
 	mod misc_serde {
 		use super::internal;
 		use internal::errors::ParquetError;
@@ -234,7 +830,8 @@ mod wrap {
 					&match self.0 {
 						ParquetError::General(message) => (0, message),
 						ParquetError::EOF(message) => (1, message),
-						_ => unimplemented!(),
+						ParquetError::NYI(message) => (2, message),
+						err => return (0, &*err.to_string()).serialize(serializer),
 					},
 					serializer,
 				)
@@ -248,6 +845,7 @@ mod wrap {
 				<(usize, String)>::deserialize(deserializer)
 					.map(|(kind, message)| match kind {
 						1 => ParquetError::EOF(message),
+						2 => ParquetError::NYI(message),
 						_ => ParquetError::General(message),
 					})
 					.map(Self)
@@ -315,46 +913,99 @@ mod wrap {
 			}
 		}
 	}
+	impl<A, B, C> RecordError for ParquetError<A, B, C>
+	where
+		A: Display,
+		B: Display,
+		C: Display,
+	{
+	}
 	impl<A, B, C> From<InternalParquetError> for ParquetError<A, B, C> {
 		fn from(err: InternalParquetError) -> Self {
 			Self::Parquet(err)
 		}
 	}
 
-	use std::io;
-
-	impl ParquetReader for PassError<Cursor<Vec<u8>>> {
-		fn len(&self) -> u64 {
-			self.0.as_ref().unwrap().get_ref().len() as u64
-		}
-	}
-
-	struct PassError<R>(Result<R, Option<io::Error>>);
-	impl<R> PassError<R> {
-		fn new(r: Result<R, io::Error>) -> Self {
-			Self(r.map_err(Some))
-		}
+	#[derive(Clone, Serialize, Deserialize, Debug)]
+	pub enum ParquetDestinationError<E> {
+		Directory(E),
+		Parquet(#[serde(with = "misc_serde")] InternalParquetError),
 	}
-	impl<R> io::Read for PassError<R>
+	impl<E> PartialEq for ParquetDestinationError<E>
 	where
-		R: io::Read,
+		E: PartialEq,
 	{
-		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-			match &mut self.0 {
-				Ok(r) => r.read(buf),
-				Err(r) => Err(r.take().unwrap()),
+		fn eq(&self, other: &Self) -> bool {
+			match (self, other) {
+				(Self::Directory(a), Self::Directory(b)) => a == b,
+				(Self::Parquet(a), Self::Parquet(b)) => a == b,
+				_ => false,
 			}
 		}
 	}
-	impl<R> io::Seek for PassError<R>
+	impl<E> error::Error for ParquetDestinationError<E> where E: error::Error {}
+	impl<E> Display for ParquetDestinationError<E>
 	where
-		R: io::Seek,
+		E: Display,
 	{
-		fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-			match &mut self.0 {
-				Ok(r) => r.seek(pos),
-				Err(r) => Err(r.take().unwrap()),
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			match self {
+				Self::Directory(err) => Display::fmt(err, f),
+				Self::Parquet(err) => Display::fmt(err, f),
+			}
+		}
+	}
+	impl<E> From<E> for ParquetDestinationError<E> {
+		fn from(err: E) -> Self {
+			Self::Directory(err)
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use std::{env, fs, path};
+
+		/// Read `parquet` with tasks of `task_size` bytes, returning the number of tasks
+		/// and the ids read.
+		async fn read(parquet: Parquet<path::PathBuf, Group>, task_size: u64) -> (usize, Vec<i64>) {
+			let tasks = parquet.split(task_size);
+			let mut ids = Vec::new();
+			for row_groups in tasks.iter().cloned() {
+				let rows = row_groups
+					.read::<Group, <path::PathBuf as File>::Error>(parquet.filter.clone(), None)
+					.map_ok(|row| row.into_fields()[0].as_i64().unwrap());
+				ids.extend(rows.try_collect::<Vec<_>>().await.unwrap());
 			}
+			(tasks.len(), ids)
+		}
+
+		#[tokio::test]
+		async fn split_row_groups() {
+			let mut writer = ParquetFileWriter::new("message test { required int64 id; }").unwrap();
+			for i in 0..4_000_i64 {
+				writer
+					.rows
+					.write(Value::Group(Group::new(vec![Value::I64(i)], None)))
+					.unwrap();
+				if writer.rows.num_rows() == 1_000 {
+					writer.write_row_group().unwrap();
+				}
+			}
+			writer.close().unwrap();
+			let path = env::temp_dir().join("split_row_groups.parquet");
+			fs::write(&path, writer.buffer.take()).unwrap();
+			let parquet = Parquet::<_, Group>::new(path).await.unwrap();
+
+			let all = (0..4_000).collect::<Vec<_>>();
+			assert_eq!(read(parquet.clone(), TASK_SIZE).await, (1, all.clone()));
+			assert_eq!(read(parquet.clone(), 1).await, (4, all));
+
+			let filter = ParquetFilter::range("id", 1_500_i64..2_500);
+			let ids = (1_000..3_000).collect();
+			assert_eq!(read(parquet.clone().with_filter(filter), 1).await, (2, ids));
+			let filter = ParquetFilter::eq("id", -1_i64);
+			assert_eq!(read(parquet.with_filter(filter), 1).await, (0, Vec::new()));
 		}
 	}
 }
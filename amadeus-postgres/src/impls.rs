@@ -1,10 +1,11 @@
+use bytes::BytesMut;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-use postgres::types::{FromSql, Type, WasNull};
+use postgres::types::{FromSql, IsNull, ToSql, Type, WasNull};
 use std::{
-	collections::HashMap, error::Error, fmt::{self, Display}, hash::{BuildHasher, Hash}
+	any::type_name, collections::HashMap, convert::TryFrom, error::Error, fmt::{self, Display}, hash::{BuildHasher, Hash}
 };
 
-use super::{Names, PostgresData};
+use super::{EscapeIdentifier, Names, PostgresData};
 use amadeus_types::{
 	Bson, Data, Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Enum, Group, IpAddr, Json, List, Time, TimeWithoutTimezone, Timezone, Url, Value, Webpage
 };
@@ -21,10 +22,24 @@ where
 	) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		T::decode(type_, buf).map(Box::new)
 	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		T::type_name(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		(**self).encode(buf)
+	}
+}
+
+/// The error returned when encoding a type that can't be written to Postgres yet.
+fn unsupported<T>() -> Box<dyn Error + Sync + Send> {
+	Into::into(format!(
+		"{} can't be written to Postgres. Tracking at https://github.com/constellation-rs/amadeus/issues/63",
+		type_name::<T>()
+	))
 }
 
 macro_rules! forward {
-	($($t:ty : $pt:ty),*) => (
+	($($t:ty : $pt:ty : $type_:ident),*) => (
 		$(
 			#[allow(clippy::use_self)]
 			impl PostgresData for $t {
@@ -38,25 +53,52 @@ macro_rules! forward {
 					#[allow(trivial_numeric_casts)]
 					<$pt as FromSql>::from_sql(type_, buf.ok_or_else(||Box::new(WasNull))?).map(|x|x as Self)
 				}
+				fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+					EscapeIdentifier(Type::$type_.name()).fmt(f)
+				}
+				fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+					#[allow(clippy::useless_conversion)]
+					let value = <$pt>::try_from(*self).map_err(|_| {
+						format!("{} is out of range for {}", self, Type::$type_)
+					})?;
+					<$pt as ToSql>::to_sql(&value, &Type::$type_, buf)
+				}
 			}
 		)*
 	);
 }
 forward!(
-	bool: bool,
-	u8: i8,
-	i8: i8,
-	u16: i16,
-	i16: i16,
-	u32: i32,
-	i32: i32,
-	u64: i64,
-	i64: i64,
-	f32: f32,
-	f64: f64,
-	String: String
+	bool: bool: BOOL,
+	u8: i8: CHAR,
+	i8: i8: CHAR,
+	u16: i16: INT2,
+	i16: i16: INT2,
+	u32: i32: INT4,
+	i32: i32: INT4,
+	u64: i64: INT8,
+	i64: i64: INT8,
+	f32: f32: FLOAT4,
+	f64: f64: FLOAT8
 );
 
+impl PostgresData for String {
+	fn query(f: &mut fmt::Formatter, name: Option<&Names<'_>>) -> fmt::Result {
+		name.unwrap().fmt(f)
+	}
+	fn decode(type_: &Type, buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
+		if !<String as FromSql>::accepts(type_) {
+			return Err(Into::into("invalid type"));
+		}
+		<String as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
+	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		EscapeIdentifier(Type::TEXT.name()).fmt(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		<&str as ToSql>::to_sql(&&**self, &Type::TEXT, buf)
+	}
+}
+
 impl<T> PostgresData for Option<T>
 where
 	T: PostgresData,
@@ -70,6 +112,15 @@ where
 			None => Ok(None),
 		}
 	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		T::type_name(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		match self {
+			Some(value) => value.encode(buf),
+			None => Ok(IsNull::Yes),
+		}
+	}
 }
 
 impl PostgresData for Bson {
@@ -79,6 +130,12 @@ impl PostgresData for Bson {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Json {
@@ -88,6 +145,12 @@ impl PostgresData for Json {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Enum {
@@ -97,6 +160,12 @@ impl PostgresData for Enum {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Url {
@@ -106,6 +175,12 @@ impl PostgresData for Url {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Webpage<'static> {
@@ -115,6 +190,12 @@ impl PostgresData for Webpage<'static> {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for IpAddr {
@@ -124,6 +205,12 @@ impl PostgresData for IpAddr {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Decimal {
@@ -133,6 +220,12 @@ impl PostgresData for Decimal {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Group {
@@ -142,6 +235,12 @@ impl PostgresData for Group {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl<T: Data> PostgresData for List<T>
@@ -155,6 +254,12 @@ where
 		// List<u8> is BYTEA
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl<K, V, S> PostgresData for HashMap<K, V, S>
@@ -169,6 +274,12 @@ where
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Date {
@@ -178,6 +289,12 @@ impl PostgresData for Date {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for DateWithoutTimezone {
@@ -191,6 +308,15 @@ impl PostgresData for DateWithoutTimezone {
 		<NaiveDate as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
 			.map(|date| Self::from_chrono(&date))
 	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		EscapeIdentifier(Type::DATE.name()).fmt(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		let value = self
+			.as_chrono()
+			.ok_or_else(|| Box::<dyn Error + Sync + Send>::from("value out of range"))?;
+		value.to_sql(&Type::DATE, buf)
+	}
 }
 
 impl PostgresData for Time {
@@ -200,6 +326,12 @@ impl PostgresData for Time {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for TimeWithoutTimezone {
@@ -213,6 +345,15 @@ impl PostgresData for TimeWithoutTimezone {
 		<NaiveTime as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
 			.map(|date| Self::from_chrono(&date))
 	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		EscapeIdentifier(Type::TIME.name()).fmt(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		let value = self
+			.as_chrono()
+			.ok_or_else(|| Box::<dyn Error + Sync + Send>::from("value out of range"))?;
+		value.to_sql(&Type::TIME, buf)
+	}
 }
 
 impl PostgresData for DateTime {
@@ -229,6 +370,17 @@ impl PostgresData for DateTime {
 		)
 		.map(|date| Self::from_chrono(&date))
 	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		EscapeIdentifier(Type::TIMESTAMPTZ.name()).fmt(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		let value = self
+			.as_chrono()
+			.ok_or_else(|| Box::<dyn Error + Sync + Send>::from("value out of range"))?;
+		value
+			.with_timezone(&chrono::Utc)
+			.to_sql(&Type::TIMESTAMPTZ, buf)
+	}
 }
 
 impl PostgresData for DateTimeWithoutTimezone {
@@ -242,6 +394,15 @@ impl PostgresData for DateTimeWithoutTimezone {
 		<NaiveDateTime as FromSql>::from_sql(type_, buf.ok_or_else(|| Box::new(WasNull))?)
 			.map(|date| Self::from_chrono(&date))
 	}
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result {
+		EscapeIdentifier(Type::TIMESTAMP.name()).fmt(f)
+	}
+	fn encode(&self, buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		let value = self
+			.as_chrono()
+			.ok_or_else(|| Box::<dyn Error + Sync + Send>::from("value out of range"))?;
+		value.to_sql(&Type::TIMESTAMP, buf)
+	}
 }
 
 impl PostgresData for Timezone {
@@ -251,6 +412,12 @@ impl PostgresData for Timezone {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 impl PostgresData for Value {
@@ -260,6 +427,12 @@ impl PostgresData for Value {
 	fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 		todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 	}
+	fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+		Err(fmt::Error)
+	}
+	fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		Err(unsupported::<Self>())
+	}
 }
 
 // Implement PostgresData for common array lengths.
@@ -278,6 +451,12 @@ macro_rules! array {
 			) -> Result<Self, Box<dyn Error + Sync + Send>> {
 				todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 			}
+			fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+				Err(fmt::Error)
+			}
+			fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+				Err(unsupported::<Self>())
+			}
 		}
 	)*};
 }
@@ -293,6 +472,12 @@ macro_rules! tuple {
 			fn decode(_type_: &Type, _buf: Option<&[u8]>) -> Result<Self, Box<dyn Error + Sync + Send>> {
 				todo!("Tracking at https://github.com/constellation-rs/amadeus/issues/63")
 			}
+			fn type_name(_f: &mut fmt::Formatter) -> fmt::Result {
+				Err(fmt::Error)
+			}
+			fn encode(&self, _buf: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+				Err(unsupported::<Self>())
+			}
 		}
 	);
 }
@@ -38,23 +38,30 @@ mod impls;
 #[doc(hidden)]
 pub use postgres as _internal;
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, Bytes, BytesMut};
 use educe::Educe;
-use futures::{ready, stream, FutureExt, Stream, StreamExt, TryStreamExt};
+use futures::{
+	future::LocalBoxFuture, ready, stream, FutureExt, SinkExt, Stream, StreamExt, TryStreamExt
+};
 use pin_project::pin_project;
-use postgres::{CopyOutStream, Error as InternalPostgresError};
+use postgres::{types::IsNull, Client, CopyInSink, CopyOutStream, Error as InternalPostgresError};
+use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use std::{
-	convert::TryFrom, error, fmt::{self, Debug, Display}, io, io::Cursor, marker::PhantomData, ops::Fn, path::PathBuf, pin::Pin, str, sync::Arc, task::{Context, Poll}, time::Duration
+	convert::TryFrom, error, fmt::{self, Debug, Display}, future::Future, io, io::Cursor, marker::PhantomData, mem, ops::Fn, path::PathBuf, pin::Pin, str, sync::Arc, task::{Context, Poll}, time::Duration
 };
 
 use amadeus_core::{
-	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, IoError}, Source
+	into_par_stream::IntoDistributedStream, par_sink::{
+		DistributedSink, ExtendReducer, ParallelSink, Reducer, ReducerProcessSend, ReducerSend
+	}, par_stream::{DistributedStream, Identity}, pipe::Sink, util::{DistParStream, IoError}, Destination, Source
 };
 
 const MAGIC: &[u8] = b"PGCOPY\n\xff\r\n\0";
 const HEADER_LEN: usize = MAGIC.len() + 4 + 4;
+/// The number of bytes each worker buffers before sending them to the server.
+const BUF_SIZE: usize = 1024 * 1024;
 
 pub trait PostgresData
 where
@@ -64,6 +71,25 @@ where
 	fn decode(
 		type_: &::postgres::types::Type, buf: Option<&[u8]>,
 	) -> Result<Self, Box<dyn std::error::Error + Sync + Send>>;
+	/// Write the SQL type of the column `Self` is stored in, for creating tables. Fails
+	/// if `Self` can't be written to Postgres.
+	fn type_name(f: &mut fmt::Formatter) -> fmt::Result;
+	/// Encode `Self` in the binary format, the counterpart to `decode`.
+	fn encode(
+		&self, buf: &mut BytesMut,
+	) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>>;
+}
+
+/// A struct that can be written to Postgres as a row, with each field stored in the
+/// column of the same name. This is implemented by `#[derive(Data)]`.
+pub trait PostgresRow: PostgresData {
+	/// Write the columns that rows of `Self` are stored in, followed by their types if
+	/// `types` is true. Fails if the type of a field can't be written to Postgres.
+	fn columns(f: &mut fmt::Formatter, types: bool) -> fmt::Result;
+	/// Encode `Self` as a tuple of the binary `COPY` format.
+	fn encode_row(
+		&self, buf: &mut BytesMut,
+	) -> Result<(), Box<dyn std::error::Error + Sync + Send>>;
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -294,6 +320,473 @@ where
 	}
 }
 
+/// Writes rows to a Postgres table with `COPY ... FROM STDIN (FORMAT BINARY)`.
+///
+/// Each worker copies its rows into its own unlogged staging table. Once they've all
+/// finished, if they all succeeded the staging tables are moved into `table` in a
+/// single transaction, so a failed job leaves `table` untouched. Only structs can be
+/// written, with each field stored in the column of the same name.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone, Debug)]
+#[serde(bound = "")]
+pub struct PostgresDestination<Row> {
+	params: ConnectParams,
+	table: PostgresTable,
+	create_table: bool,
+	truncate: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> PostgresDestination<Row>
+where
+	Row: PostgresRow,
+{
+	pub fn new(params: ConnectParams, table: PostgresTable) -> Self {
+		Self {
+			params,
+			table,
+			create_table: false,
+			truncate: false,
+			marker: PhantomData,
+		}
+	}
+	/// Create `table` with a column for each field of `Row` if it doesn't exist.
+	/// Defaults to `false`.
+	pub fn create_table(self, create_table: bool) -> Self {
+		Self {
+			create_table,
+			..self
+		}
+	}
+	/// Remove any rows already in `table` before writing. Defaults to `false`.
+	pub fn truncate(self, truncate: bool) -> Self {
+		Self { truncate, ..self }
+	}
+}
+
+impl<Row> Destination for PostgresDestination<Row>
+where
+	Row: PostgresRow,
+{
+	type Item = Row;
+	type Error = PostgresError;
+
+	type ParSink = PostgresSink<Row>;
+	type DistSink = PostgresSink<Row>;
+
+	fn par_sink(self) -> Self::ParSink {
+		PostgresSink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		PostgresSink(self)
+	}
+}
+
+#[derive(Educe)]
+#[educe(Clone, Debug)]
+pub struct PostgresSink<Row>(PostgresDestination<Row>);
+impl<Row> PostgresSink<Row> {
+	fn into_reducers(self) -> (PostgresReducer<Row>, PostgresCommitReducer<Row>) {
+		let destination = self.0;
+		let reduce_a = PostgresReducer {
+			params: destination.params.clone(),
+			schema: destination.table.schema.clone(),
+			marker: PhantomData,
+		};
+		let reduce_c = PostgresCommitReducer {
+			params: destination.params,
+			table: destination.table,
+			create_table: destination.create_table,
+			truncate: destination.truncate,
+			marker: PhantomData,
+		};
+		(reduce_a, reduce_c)
+	}
+}
+impl<Row> ParallelSink<Row> for PostgresSink<Row>
+where
+	Row: PostgresRow,
+{
+	type Done = Result<(), PostgresError>;
+	type Pipe = Identity;
+	type ReduceA = PostgresReducer<Row>;
+	type ReduceC = PostgresCommitReducer<Row>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (reduce_a, reduce_c) = self.into_reducers();
+		(Identity, reduce_a, reduce_c)
+	}
+}
+impl<Row> DistributedSink<Row> for PostgresSink<Row>
+where
+	Row: PostgresRow,
+{
+	type Done = Result<(), PostgresError>;
+	type Pipe = Identity;
+	type ReduceA = PostgresReducer<Row>;
+	type ReduceB = ExtendReducer<StagingTable, Vec<Result<String, PostgresError>>>;
+	type ReduceC = PostgresCommitReducer<Row>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (reduce_a, reduce_c) = self.into_reducers();
+		(Identity, reduce_a, ExtendReducer::default(), reduce_c)
+	}
+}
+
+/// The name of the staging table a worker wrote to, or `None` if it had no rows.
+type StagingTable = Option<Result<String, PostgresError>>;
+
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct PostgresReducer<Row> {
+	params: ConnectParams,
+	schema: Option<String>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> Reducer<Row> for PostgresReducer<Row>
+where
+	Row: PostgresRow,
+{
+	type Done = StagingTable;
+	type Async = PostgresReducerAsync<Row>;
+
+	fn into_async(self) -> Self::Async {
+		let table = PostgresTable::new(
+			self.schema,
+			format!("_amadeus_part_{:016x}", thread_rng().gen::<u64>()),
+		);
+		PostgresReducerAsync {
+			params: self.params,
+			table,
+			buf: BytesMut::new(),
+			copy: None,
+			started: false,
+			closed: false,
+			pending: None,
+			failed: None,
+			marker: PhantomData,
+		}
+	}
+}
+impl<Row> ReducerSend<Row> for PostgresReducer<Row>
+where
+	Row: PostgresRow,
+{
+	type Done = StagingTable;
+}
+
+/// A connection and the `COPY` in progress on it.
+type Copy = (Client, Pin<Box<CopyInSink<Bytes>>>);
+
+#[pin_project]
+pub struct PostgresReducerAsync<Row> {
+	params: ConnectParams,
+	table: PostgresTable,
+	buf: BytesMut,
+	copy: Option<Copy>,
+	started: bool,
+	closed: bool,
+	#[pin]
+	pending: Option<LocalBoxFuture<'static, Result<Copy, PostgresError>>>,
+	#[pin]
+	failed: Option<LocalBoxFuture<'static, StagingTable>>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> PostgresReducerAsync<Row>
+where
+	Row: PostgresRow,
+{
+	/// Send the buffered rows, creating the staging table and starting the `COPY` if
+	/// this is the first send, and finishing it if the stream has ended.
+	fn write(self: Pin<&mut Self>) {
+		let mut self_ = self.project();
+		let mut buf = mem::take(self_.buf);
+		let closed = *self_.closed;
+		if closed {
+			buf.extend_from_slice(&(-1_i16).to_be_bytes());
+		}
+		let copy = self_.copy.take();
+		let params = self_.params.clone();
+		let table = self_.table.clone();
+		self_.pending.set(Some(Box::pin(async move {
+			let mut copy = match copy {
+				Some(copy) => copy,
+				None => {
+					let (columns_types, columns) = (columns::<Row>(true)?, columns::<Row>(false)?);
+					let client = connect(params).await?;
+					client
+						.batch_execute(&format!(
+							"CREATE UNLOGGED TABLE {} ({})",
+							table, columns_types
+						))
+						.await?;
+					let query = format!("COPY {} ({}) FROM STDIN (FORMAT BINARY)", table, columns);
+					let sink = client.copy_in(query.as_str()).await?;
+					(client, Box::pin(sink))
+				}
+			};
+			copy.1.send(buf.freeze()).await?;
+			if closed {
+				let _ = copy.1.as_mut().finish().await?;
+			}
+			Ok(copy)
+		})));
+	}
+	/// Give up on the staging table, dropping whatever has been written to it.
+	fn fail(self: Pin<&mut Self>, err: PostgresError) {
+		let mut self_ = self.project();
+		let _ = self_.copy.take();
+		let params = self_.params.clone();
+		let table = self_.table.clone();
+		self_.failed.set(Some(Box::pin(async move {
+			drop_tables(params, vec![table]).await;
+			Some(Err(err))
+		})));
+	}
+}
+impl<Row> Sink<Row> for PostgresReducerAsync<Row>
+where
+	Row: PostgresRow,
+{
+	type Done = StagingTable;
+
+	fn poll_forward(
+		mut self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Row>>,
+	) -> Poll<Self::Done> {
+		loop {
+			let mut self_ = self.as_mut().project();
+			if let Some(failed) = self_.failed.as_pin_mut() {
+				return failed.poll(cx);
+			}
+			if let Some(pending) = self_.pending.as_mut().as_pin_mut() {
+				let copy = ready!(pending.poll(cx));
+				self_.pending.set(None);
+				match copy {
+					Ok(copy) => *self_.copy = Some(copy),
+					Err(err) => {
+						self.as_mut().fail(err);
+						continue;
+					}
+				}
+			}
+			if *self_.closed {
+				return Poll::Ready(Some(Ok(self_.table.table.clone())));
+			}
+			match ready!(stream.as_mut().poll_next(cx)) {
+				Some(row) => {
+					if !*self_.started {
+						*self_.started = true;
+						self_.buf.extend_from_slice(MAGIC);
+						self_.buf.extend_from_slice(&[0; 8]);
+					}
+					if let Err(err) = row.encode_row(self_.buf) {
+						self.as_mut().fail(PostgresError::Postgres(err.to_string()));
+					} else if self_.buf.len() >= BUF_SIZE {
+						self.as_mut().write();
+					}
+				}
+				None => {
+					if !*self_.started {
+						return Poll::Ready(None);
+					}
+					*self_.closed = true;
+					self.as_mut().write();
+				}
+			}
+		}
+	}
+}
+
+/// Moves the staging tables written by a [`PostgresDestination`]'s workers into the
+/// destination table, or drops them and returns the first error if any worker failed.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct PostgresCommitReducer<Row> {
+	params: ConnectParams,
+	table: PostgresTable,
+	create_table: bool,
+	truncate: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row, Item> Reducer<Item> for PostgresCommitReducer<Row>
+where
+	Row: PostgresRow,
+	Item: IntoIterator<Item = Result<String, PostgresError>>,
+{
+	type Done = Result<(), PostgresError>;
+	type Async = PostgresCommitReducerAsync<Row, Item>;
+
+	fn into_async(self) -> Self::Async {
+		PostgresCommitReducerAsync {
+			params: self.params,
+			table: self.table,
+			create_table: self.create_table,
+			truncate: self.truncate,
+			tables: Vec::new(),
+			error: None,
+			pending: None,
+			marker: PhantomData,
+		}
+	}
+}
+impl<Row, Item> ReducerProcessSend<Item> for PostgresCommitReducer<Row>
+where
+	Row: PostgresRow,
+	Item: IntoIterator<Item = Result<String, PostgresError>>,
+{
+	type Done = Result<(), PostgresError>;
+}
+impl<Row, Item> ReducerSend<Item> for PostgresCommitReducer<Row>
+where
+	Row: PostgresRow,
+	Item: IntoIterator<Item = Result<String, PostgresError>>,
+{
+	type Done = Result<(), PostgresError>;
+}
+
+#[pin_project]
+pub struct PostgresCommitReducerAsync<Row, Item> {
+	params: ConnectParams,
+	table: PostgresTable,
+	create_table: bool,
+	truncate: bool,
+	tables: Vec<PostgresTable>,
+	error: Option<PostgresError>,
+	#[pin]
+	pending: Option<LocalBoxFuture<'static, Result<(), PostgresError>>>,
+	marker: PhantomData<fn() -> (Row, Item)>,
+}
+impl<Row, Item> Sink<Item> for PostgresCommitReducerAsync<Row, Item>
+where
+	Row: PostgresRow,
+	Item: IntoIterator<Item = Result<String, PostgresError>>,
+{
+	type Done = Result<(), PostgresError>;
+
+	#[inline]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		let mut self_ = self.project();
+		if self_.pending.is_none() {
+			while let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+				for table in item {
+					match table {
+						Ok(table) => self_
+							.tables
+							.push(PostgresTable::new(self_.table.schema.clone(), table)),
+						Err(err) => {
+							if self_.error.is_none() {
+								*self_.error = Some(err);
+							}
+						}
+					}
+				}
+			}
+			let params = self_.params.clone();
+			let table = self_.table.clone();
+			let create_table = *self_.create_table;
+			let truncate = *self_.truncate;
+			let tables = mem::take(self_.tables);
+			let error = self_.error.take();
+			*self_.pending = Some(Box::pin(async move {
+				let result = match error {
+					None => {
+						commit::<Row>(params.clone(), &table, create_table, truncate, &tables).await
+					}
+					Some(err) => Err(err),
+				};
+				if result.is_err() {
+					drop_tables(params, tables).await;
+				}
+				result
+			}));
+		}
+		self_.pending.as_mut().as_pin_mut().unwrap().poll(cx)
+	}
+}
+
+/// Move the rows of `tables` into `table` and drop them, in a single transaction.
+async fn commit<Row>(
+	params: ConnectParams, table: &PostgresTable, create_table: bool, truncate: bool,
+	tables: &[PostgresTable],
+) -> Result<(), PostgresError>
+where
+	Row: PostgresRow,
+{
+	let (columns_types, columns) = (columns::<Row>(true)?, columns::<Row>(false)?);
+	let mut client = connect(params).await?;
+	let transaction = client.transaction().await?;
+	if create_table {
+		transaction
+			.batch_execute(&format!(
+				"CREATE TABLE IF NOT EXISTS {} ({})",
+				table, columns_types
+			))
+			.await?;
+	}
+	if truncate {
+		transaction
+			.batch_execute(&format!("TRUNCATE {}", table))
+			.await?;
+	}
+	for staging in tables {
+		transaction
+			.batch_execute(&format!(
+				"INSERT INTO {} ({}) SELECT {} FROM {}; DROP TABLE {}",
+				table, columns, columns, staging, staging
+			))
+			.await?;
+	}
+	transaction.commit().await?;
+	Ok(())
+}
+
+/// The columns that rows of `Row` are stored in, followed by their types if `types` is
+/// true.
+fn columns<Row>(types: bool) -> Result<String, PostgresError>
+where
+	Row: PostgresRow,
+{
+	let mut columns = String::new();
+	fmt::write(
+		&mut columns,
+		format_args!("{}", DisplayFmt::new(|f| Row::columns(f, types))),
+	)
+	.map_err(|fmt::Error| {
+		PostgresError::Postgres(format!(
+			"{} has a field of a type that can't be written to Postgres",
+			std::any::type_name::<Row>()
+		))
+	})?;
+	Ok(columns)
+}
+
+/// Drop `tables` if they exist, ignoring any errors.
+async fn drop_tables(params: ConnectParams, tables: Vec<PostgresTable>) {
+	if tables.is_empty() {
+		return;
+	}
+	if let Ok(client) = connect(params).await {
+		for table in tables {
+			let _ = client
+				.batch_execute(&format!("DROP TABLE IF EXISTS {}", table))
+				.await;
+		}
+	}
+}
+
+async fn connect(params: ConnectParams) -> Result<Client, PostgresError> {
+	let (client, connection) = postgres::config::Config::from(params)
+		.connect(postgres::tls::NoTls)
+		.await?;
+	let _ = tokio::spawn(async move {
+		let _ = connection.await;
+	});
+	Ok(client)
+}
+
 /// A stream of rows deserialized from the PostgreSQL binary copy format.
 #[pin_project]
 pub struct BinaryCopyOutStream {
@@ -420,6 +913,22 @@ where
 	T::decode(type_, value)
 }
 
+pub fn write_value<T>(
+	value: &T, buf: &mut BytesMut,
+) -> Result<(), Box<dyn std::error::Error + Sync + Send>>
+where
+	T: PostgresData,
+{
+	let start = buf.len();
+	buf.extend_from_slice(&[0; 4]);
+	let len = match value.encode(buf)? {
+		IsNull::Yes => -1,
+		IsNull::No => i32::try_from(buf.len() - start - 4)?,
+	};
+	buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+	Ok(())
+}
+
 // https://www.postgresql.org/docs/11/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS
 struct EscapeIdentifier<T>(T);
 impl<T: Display> Display for EscapeIdentifier<T> {
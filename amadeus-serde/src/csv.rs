@@ -1,22 +1,21 @@
-use csv::Error as InternalCsvError;
+use csv::{ByteRecord, Error as InternalCsvError};
 use educe::Educe;
-use futures::{pin_mut, stream, AsyncReadExt, FutureExt, Stream, StreamExt};
+use futures::{FutureExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use std::{
-	error, fmt::{self, Display}, io::Cursor, marker::PhantomData
+	cell::RefCell, collections::VecDeque, convert::TryFrom, error, fmt::{self, Display}, io, marker::PhantomData, mem, rc::Rc
 };
 
 use amadeus_core::{
-	file::{File, Page, Partition}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	file::{
+		existing_file, File, OutputDirectory, Page, PartFileFormat, PartFileSink, Partition, PathBuf, WriteMode
+	}, into_par_stream::IntoDistributedStream, par_sink::RecordError, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Destination, Source
 };
 
-use super::{SerdeData, SerdeDeserializeGroup};
-
-// #[doc(inline)]
-// pub type Trim = csv::Trim;
-// #[doc(inline)]
-// pub type Terminator = csv::Terminator;
+use super::{
+	reader::{DecodeStream, Decoder}, SerdeData, SerdeDeserializeGroup, SerdeSerialize
+};
 
 // open (assume exists,
 
@@ -42,6 +41,162 @@ use super::{SerdeData, SerdeDeserializeGroup};
 
 // trait File
 
+/// Whitespace trimming for CSV fields. See [`csv::Trim`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Trim {
+	/// Preserves whitespace.
+	None,
+	/// Trims whitespace from headers.
+	Headers,
+	/// Trims whitespace from fields, but not headers.
+	Fields,
+	/// Trims whitespace from fields and headers.
+	All,
+}
+impl From<Trim> for csv::Trim {
+	fn from(trim: Trim) -> Self {
+		match trim {
+			Trim::None => Self::None,
+			Trim::Headers => Self::Headers,
+			Trim::Fields => Self::Fields,
+			Trim::All => Self::All,
+		}
+	}
+}
+
+/// The record terminator of a CSV file. See [`csv::Terminator`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Terminator {
+	/// Any of `\r`, `\n` or `\r\n`.
+	CRLF,
+	/// The given byte.
+	Any(u8),
+}
+impl From<Terminator> for csv::Terminator {
+	fn from(terminator: Terminator) -> Self {
+		match terminator {
+			Terminator::CRLF => Self::CRLF,
+			Terminator::Any(byte) => Self::Any(byte),
+		}
+	}
+}
+
+/// The dialect of the files read by a [`Csv`]. The defaults match
+/// [`csv::ReaderBuilder`]'s, except that files are expected not to have headers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct CsvBuilder {
+	delimiter: u8,
+	has_headers: bool,
+	flexible: bool,
+	trim: Trim,
+	terminator: Terminator,
+	quote: u8,
+	escape: Option<u8>,
+	double_quote: bool,
+	quoting: bool,
+	comment: Option<u8>,
+}
+impl CsvBuilder {
+	pub fn new() -> Self {
+		Self {
+			delimiter: b',',
+			has_headers: false,
+			flexible: false,
+			trim: Trim::None,
+			terminator: Terminator::CRLF,
+			quote: b'"',
+			escape: None,
+			double_quote: true,
+			quoting: true,
+			comment: None,
+		}
+	}
+	/// The field delimiter. Defaults to `b','`.
+	pub fn delimiter(self, delimiter: u8) -> Self {
+		Self { delimiter, ..self }
+	}
+	/// Whether each file starts with a header row. If so, the fields of structs are
+	/// matched to columns by name rather than position. Defaults to `false`.
+	pub fn has_headers(self, has_headers: bool) -> Self {
+		Self {
+			has_headers,
+			..self
+		}
+	}
+	/// Whether records may have differing numbers of fields. Defaults to `false`.
+	pub fn flexible(self, flexible: bool) -> Self {
+		Self { flexible, ..self }
+	}
+	/// Whether to trim whitespace around fields and headers. Defaults to [`Trim::None`].
+	pub fn trim(self, trim: Trim) -> Self {
+		Self { trim, ..self }
+	}
+	/// The record terminator. Defaults to [`Terminator::CRLF`].
+	pub fn terminator(self, terminator: Terminator) -> Self {
+		Self { terminator, ..self }
+	}
+	/// The quote character. Defaults to `b'"'`.
+	pub fn quote(self, quote: u8) -> Self {
+		Self { quote, ..self }
+	}
+	/// The escape character for quotes within quoted fields, if quotes aren't escaped
+	/// by doubling them. Defaults to `None`.
+	pub fn escape(self, escape: Option<u8>) -> Self {
+		Self { escape, ..self }
+	}
+	/// Whether a doubled quote within a quoted field is an escaped quote. Defaults to
+	/// `true`.
+	pub fn double_quote(self, double_quote: bool) -> Self {
+		Self {
+			double_quote,
+			..self
+		}
+	}
+	/// Whether quotes are treated specially. Defaults to `true`.
+	pub fn quoting(self, quoting: bool) -> Self {
+		Self { quoting, ..self }
+	}
+	/// The comment character: lines starting with it are skipped. Defaults to `None`.
+	pub fn comment(self, comment: Option<u8>) -> Self {
+		Self { comment, ..self }
+	}
+
+	pub async fn build<F, Row>(self, file: F) -> Result<Csv<F, Row>, <Csv<F, Row> as Source>::Error>
+	where
+		F: File,
+		Row: SerdeData,
+	{
+		Ok(Csv {
+			partitions: file.partitions().await.map_err(CsvError::File)?,
+			builder: self,
+			marker: PhantomData,
+		})
+	}
+
+	/// A reader that splits records, leaving headers, record lengths and trimming to
+	/// [`CsvDecoder`] as records can span the chunks it's given.
+	fn reader_builder(&self) -> csv::ReaderBuilder {
+		let mut builder = csv::ReaderBuilder::new();
+		let _ = builder
+			.delimiter(self.delimiter)
+			.has_headers(false)
+			.flexible(true)
+			.terminator(self.terminator.into())
+			.quote(self.quote)
+			.escape(self.escape)
+			.double_quote(self.double_quote)
+			.quoting(self.quoting)
+			.comment(self.comment);
+		builder
+	}
+}
+impl Default for CsvBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 #[derive(Educe)]
 #[educe(Clone, Debug)]
 pub struct Csv<File, Row>
@@ -49,17 +204,8 @@ where
 	File: amadeus_core::file::File,
 	Row: SerdeData,
 {
-	// delimiter: u8,
-	// has_headers: bool,
-	// flexible: bool,
-	// trim: Trim,
-	// terminator: Terminator,
-	// quote: u8,
-	// escape: Option<u8>,
-	// double_quote: bool,
-	// quoting: bool,
-	// comment: Option<u8>,
 	partitions: Vec<File::Partition>,
+	builder: CsvBuilder,
 	marker: PhantomData<fn() -> Row>,
 }
 impl<F, Row> Csv<F, Row>
@@ -67,11 +213,9 @@ where
 	F: File,
 	Row: SerdeData,
 {
+	/// Read `file` with the default dialect. Use [`CsvBuilder`] to configure it.
 	pub async fn new(file: F) -> Result<Self, <Self as Source>::Error> {
-		Ok(Self {
-			partitions: file.partitions().await.map_err(CsvError::File)?,
-			marker: PhantomData,
-		})
+		CsvBuilder::new().build(file).await
 	}
 	// pub fn open<Row>(files: Vec<PathBuf>) -> Csv<Row> {}
 	// pub fn create<Row>(files: Vec<PathBuf>) -> Csv<Row> {}
@@ -84,44 +228,18 @@ type Output<P, Row: SerdeData, E> = std::pin::Pin<Box<dyn Stream<Item = Result<R
 type Output<P: Partition, Row: SerdeData, E> = impl Stream<Item = Result<Row, Error<P, E>>>;
 
 FnMutNamed! {
-	pub type Closure<P, Row, E> = |self|partition=> P| -> Output<P, Row, E>
+	pub type Closure<P, Row, E> = |self, builder: CsvBuilder|partition=> P| -> Output<P, Row, E>
 	where
 		P: Partition,
 		Row: SerdeData,
 		E: 'static
 	{
+		let builder = self.builder.clone();
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
-				Ok(stream::iter(
-					partition
-						.pages()
-						.await
-						.map_err(CsvError::Partition)?
-						.into_iter(),
-				)
-				.flat_map(|page| {
-					async move {
-						let mut buf = Vec::with_capacity(10 * 1024 * 1024);
-						let reader = Page::reader(page);
-						pin_mut!(reader);
-						let _ = reader
-							.read_to_end(&mut buf)
-							.await
-							.map_err(InternalCsvError::from)?;
-						Ok(stream::iter(
-							csv::ReaderBuilder::new()
-								.has_headers(false)
-								.from_reader(Cursor::new(buf))
-								.into_deserialize()
-								.map(|x: Result<SerdeDeserializeGroup<Row>, InternalCsvError>| {
-									Ok(x?.0)
-								}),
-						))
-					}
-					.map(ResultExpandIter::new)
-					.flatten_stream()
-				})
-				.map(|row: Result<Result<Row, InternalCsvError>, Error<P, E>>| Ok(row??)))
+				let pages = partition.pages().await.map_err(CsvError::Partition)?;
+				Ok(DecodeStream::new(pages, CsvDecoder::new(builder))
+					.map(|row: Result<Row, InternalCsvError>| Ok(row?)))
 			}
 			.map(ResultExpandIter::new)
 			.flatten_stream()
@@ -132,6 +250,101 @@ FnMutNamed! {
 	}
 }
 
+/// Parses CSV incrementally, a chunk at a time. See [`DecodeStream`].
+struct CsvDecoder<Row> {
+	builder: CsvBuilder,
+	headers: Option<ByteRecord>,
+	len: Option<usize>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> CsvDecoder<Row>
+where
+	Row: SerdeData,
+{
+	fn new(builder: CsvBuilder) -> Self {
+		Self {
+			builder,
+			headers: None,
+			len: None,
+			marker: PhantomData,
+		}
+	}
+	fn record(&mut self, mut record: ByteRecord) -> Option<Result<Row, InternalCsvError>> {
+		let (trim_headers, trim_fields) = match self.builder.trim {
+			Trim::None => (false, false),
+			Trim::Headers => (true, false),
+			Trim::Fields => (false, true),
+			Trim::All => (true, true),
+		};
+		if self.builder.has_headers && self.headers.is_none() {
+			if trim_headers {
+				record.trim();
+			}
+			self.len = Some(record.len());
+			self.headers = Some(record);
+			return None;
+		}
+		if trim_fields {
+			record.trim();
+		}
+		if !self.builder.flexible {
+			let len = *self.len.get_or_insert(record.len());
+			if len != record.len() {
+				return Some(Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!(
+						"found record with {} fields, but the previous record has {} fields",
+						record.len(),
+						len
+					),
+				)
+				.into()));
+			}
+		}
+		Some(
+			record
+				.deserialize(self.headers.as_ref())
+				.map(|row: SerdeDeserializeGroup<Row>| row.0),
+		)
+	}
+}
+impl<Row> Decoder for CsvDecoder<Row>
+where
+	Row: SerdeData,
+{
+	type Item = Row;
+	type Error = InternalCsvError;
+
+	fn decode(
+		&mut self, buf: &[u8], eof: bool, items: &mut VecDeque<Result<Row, InternalCsvError>>,
+	) -> usize {
+		let mut reader = self.builder.reader_builder().from_reader(buf);
+		let mut record = ByteRecord::new();
+		let mut consumed = 0;
+		loop {
+			let read = reader.read_byte_record(&mut record);
+			let end = usize::try_from(reader.position().byte()).unwrap();
+			match read {
+				// Only comments or blank lines remain, but a comment might continue
+				Ok(false) => return if eof { buf.len() } else { consumed },
+				// The record might continue in the next chunk
+				Ok(true) if !eof && end >= buf.len() => return consumed,
+				Ok(true) => items.extend(self.record(mem::take(&mut record))),
+				Err(err) => {
+					items.push_back(Err(err));
+					if end == consumed {
+						return if eof { buf.len() } else { consumed };
+					}
+				}
+			}
+			consumed = end;
+		}
+	}
+	fn io_error(err: io::Error) -> InternalCsvError {
+		err.into()
+	}
+}
+
 impl<F, Row> Source for Csv<F, Row>
 where
 	F: File,
@@ -160,24 +373,155 @@ where
 	}
 	#[allow(clippy::let_and_return)]
 	fn dist_stream(self) -> Self::DistStream {
-		self.partitions.into_dist_stream().flat_map(Closure::new())
+		self.partitions
+			.into_dist_stream()
+			.flat_map(Closure::new(self.builder))
+	}
+}
+
+/// Writes `Row`s as CSV files to an [`OutputDirectory`], a part file per worker.
+#[derive(Educe)]
+#[educe(Clone, Debug)]
+pub struct CsvDestination<D, Row>
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	directory: D,
+	has_headers: bool,
+	mode: WriteMode,
+	existing: Option<PathBuf>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row> CsvDestination<D, Row>
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	/// Write to `directory`, checking whether it already contains files.
+	pub async fn new(directory: D) -> Result<Self, <Self as Destination>::Error> {
+		let existing = existing_file(&directory).await?;
+		Ok(Self {
+			directory,
+			existing,
+			has_headers: false,
+			mode: WriteMode::default(),
+			marker: PhantomData,
+		})
+	}
+	/// Whether to start each file with a header row of the field names. Defaults to
+	/// `false`, matching [`CsvBuilder`].
+	pub fn has_headers(self, has_headers: bool) -> Self {
+		Self {
+			has_headers,
+			..self
+		}
+	}
+	/// What to do if `directory` already contains files. Defaults to
+	/// [`WriteMode::FailIfExists`].
+	pub fn mode(self, mode: WriteMode) -> Self {
+		Self { mode, ..self }
+	}
+}
+impl<D, Row> Destination for CsvDestination<D, Row>
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	type Item = Row;
+	type Error = CsvDestinationError<D::Error>;
+
+	type ParSink = PartFileSink<D, Row, CsvFormat>;
+	type DistSink = PartFileSink<D, Row, CsvFormat>;
+
+	fn par_sink(self) -> Self::ParSink {
+		let format = CsvFormat {
+			has_headers: self.has_headers,
+		};
+		PartFileSink::new(self.directory, format, self.mode, self.existing)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		self.par_sink()
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CsvFormat {
+	has_headers: bool,
+}
+impl<D, Row> PartFileFormat<D, Row> for CsvFormat
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	type Writer = CsvWriter;
+	type Error = CsvDestinationError<D::Error>;
+
+	const EXTENSION: &'static str = "csv";
+
+	fn writer(&self) -> Result<Self::Writer, Self::Error> {
+		let buffer = SharedBuffer::default();
+		let writer = csv::WriterBuilder::new()
+			.has_headers(self.has_headers)
+			.from_writer(buffer.clone());
+		Ok(CsvWriter(writer, buffer))
+	}
+	fn write(
+		&self, writer: &mut Self::Writer, row: Row, buf: &mut Vec<u8>,
+	) -> Result<(), Self::Error> {
+		writer
+			.0
+			.serialize(SerdeSerialize(&row))
+			.map_err(CsvDestinationError::Csv)?;
+		writer
+			.0
+			.flush()
+			.map_err(|err| CsvDestinationError::Csv(err.into()))?;
+		buf.extend_from_slice(&writer.1.take());
+		Ok(())
+	}
+	fn close(&self, _writer: Self::Writer, _buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+/// A [`csv::Writer`], along with the buffer it writes to.
+pub struct CsvWriter(csv::Writer<SharedBuffer>, SharedBuffer);
+
+/// An [`io::Write`] whose contents can be taken while the [`csv::Writer`] holds it.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+impl SharedBuffer {
+	fn take(&self) -> Vec<u8> {
+		mem::take(&mut *self.0.borrow_mut())
+	}
+}
+impl io::Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.borrow_mut().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
 	}
 }
 
 mod csverror {
-	use serde::{Deserializer, Serializer};
+	use serde::{Deserialize, Deserializer, Serializer};
+
+	use super::InternalCsvError;
 
-	pub(crate) fn serialize<T, S>(_t: &T, _serializer: S) -> Result<S::Ok, S::Error>
+	pub(crate) fn serialize<S>(t: &InternalCsvError, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: Serializer,
 	{
-		unimplemented!()
+		serializer.collect_str(t)
 	}
-	pub(crate) fn deserialize<'de, T, D>(_deserializer: D) -> Result<T, D::Error>
+	pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<InternalCsvError, D::Error>
 	where
 		D: Deserializer<'de>,
 	{
-		unimplemented!()
+		String::deserialize(deserializer).map(serde::ser::Error::custom)
 	}
 
 	// TODO
@@ -296,8 +640,61 @@ where
 		}
 	}
 }
+impl<A, B, C> RecordError for CsvError<A, B, C>
+where
+	A: Display,
+	B: Display,
+	C: Display,
+{
+}
 impl<A, B, C> From<InternalCsvError> for CsvError<A, B, C> {
 	fn from(err: InternalCsvError) -> Self {
 		Self::Csv(err)
 	}
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum CsvDestinationError<E> {
+	Directory(E),
+	Csv(#[serde(with = "csverror")] InternalCsvError),
+}
+impl<E> Clone for CsvDestinationError<E>
+where
+	E: Clone,
+{
+	fn clone(&self) -> Self {
+		match self {
+			Self::Directory(err) => Self::Directory(err.clone()),
+			Self::Csv(err) => Self::Csv(serde::ser::Error::custom(err)),
+		}
+	}
+}
+impl<E> PartialEq for CsvDestinationError<E>
+where
+	E: PartialEq,
+{
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Self::Directory(a), Self::Directory(b)) => a.eq(b),
+			(Self::Csv(a), Self::Csv(b)) => a.to_string() == b.to_string(),
+			_ => false,
+		}
+	}
+}
+impl<E> error::Error for CsvDestinationError<E> where E: error::Error {}
+impl<E> Display for CsvDestinationError<E>
+where
+	E: Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Directory(err) => Display::fmt(err, f),
+			Self::Csv(err) => Display::fmt(err, f),
+		}
+	}
+}
+impl<E> From<E> for CsvDestinationError<E> {
+	fn from(err: E) -> Self {
+		Self::Directory(err)
+	}
+}
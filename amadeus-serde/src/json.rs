@@ -1,17 +1,21 @@
 use educe::Educe;
-use futures::{pin_mut, stream, AsyncReadExt, FutureExt, Stream, StreamExt};
+use futures::{FutureExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
 use serde_json::Error as InternalJsonError;
 use std::{
-	error, fmt::{self, Debug, Display}, io::{self, Cursor}, marker::PhantomData
+	collections::VecDeque, error, fmt::{self, Debug, Display}, io, marker::PhantomData
 };
 
 use amadeus_core::{
-	file::{File, Page, Partition}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	file::{
+		existing_file, File, OutputDirectory, Page, PartFileFormat, PartFileSink, Partition, PathBuf, WriteMode
+	}, into_par_stream::IntoDistributedStream, par_sink::RecordError, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Destination, Source
 };
 
-use super::{SerdeData, SerdeDeserialize};
+use super::{
+	reader::{DecodeStream, Decoder}, SerdeData, SerdeDeserialize, SerdeSerialize
+};
 
 #[derive(Educe)]
 #[educe(Clone, Debug)]
@@ -51,31 +55,9 @@ FnMutNamed! {
 	{
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
-				Ok(stream::iter(
-					partition
-						.pages()
-						.await
-						.map_err(JsonError::Partition)?
-						.into_iter(),
-				)
-				.flat_map(|page| {
-					async move {
-						let mut buf = Vec::with_capacity(10 * 1024 * 1024);
-						let reader = Page::reader(page);
-						pin_mut!(reader);
-						let buf = PassError::new(
-							reader.read_to_end(&mut buf).await.map(|_| Cursor::new(buf)),
-						);
-						Ok(stream::iter(
-							serde_json::Deserializer::from_reader(buf).into_iter().map(
-								|x: Result<SerdeDeserialize<Row>, InternalJsonError>| Ok(x?.0),
-							),
-						))
-					}
-					.map(ResultExpandIter::new)
-					.flatten_stream()
-				})
-				.map(|row: Result<Result<Row, InternalJsonError>, Error<P, E>>| Ok(row??)))
+				let pages = partition.pages().await.map_err(JsonError::Partition)?;
+				Ok(DecodeStream::new(pages, JsonDecoder::new())
+					.map(|row: Result<Row, InternalJsonError>| Ok(row?)))
 			}
 			.map(ResultExpandIter::new)
 			.flatten_stream()
@@ -86,6 +68,62 @@ FnMutNamed! {
 	}
 }
 
+/// Parses a sequence of JSON values incrementally, a chunk at a time. See
+/// [`DecodeStream`].
+struct JsonDecoder<Row> {
+	failed: bool,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<Row> JsonDecoder<Row> {
+	fn new() -> Self {
+		Self {
+			failed: false,
+			marker: PhantomData,
+		}
+	}
+}
+impl<Row> Decoder for JsonDecoder<Row>
+where
+	Row: SerdeData,
+{
+	type Item = Row;
+	type Error = InternalJsonError;
+
+	fn decode(
+		&mut self, buf: &[u8], eof: bool, items: &mut VecDeque<Result<Row, InternalJsonError>>,
+	) -> usize {
+		// Like serde_json's StreamDeserializer, stop at the first error
+		if self.failed {
+			return buf.len();
+		}
+		let mut stream = serde_json::Deserializer::from_slice(buf).into_iter();
+		let mut consumed = 0;
+		loop {
+			match stream.next() {
+				None => return buf.len(),
+				Some(Ok(SerdeDeserialize(row))) => {
+					let end = stream.byte_offset();
+					// A number at the end of the chunk might continue in the next
+					if !eof && end == buf.len() {
+						return consumed;
+					}
+					items.push_back(Ok(row));
+					consumed = end;
+				}
+				Some(Err(err)) if !eof && err.is_eof() => return consumed,
+				Some(Err(err)) => {
+					items.push_back(Err(err));
+					self.failed = true;
+					return buf.len();
+				}
+			}
+		}
+	}
+	fn io_error(err: io::Error) -> InternalJsonError {
+		InternalJsonError::io(err)
+	}
+}
+
 impl<F, Row> Source for Json<F, Row>
 where
 	F: File,
@@ -118,20 +156,104 @@ where
 	}
 }
 
+/// Writes `Row`s as newline-delimited JSON files to an [`OutputDirectory`], a part
+/// file per worker.
+#[derive(Educe)]
+#[educe(Clone, Debug)]
+pub struct JsonDestination<D, Row>
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	directory: D,
+	mode: WriteMode,
+	existing: Option<PathBuf>,
+	marker: PhantomData<fn() -> Row>,
+}
+impl<D, Row> JsonDestination<D, Row>
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	/// Write to `directory`, checking whether it already contains files.
+	pub async fn new(directory: D) -> Result<Self, <Self as Destination>::Error> {
+		let existing = existing_file(&directory).await?;
+		Ok(Self {
+			directory,
+			existing,
+			mode: WriteMode::default(),
+			marker: PhantomData,
+		})
+	}
+	/// What to do if `directory` already contains files. Defaults to
+	/// [`WriteMode::FailIfExists`].
+	pub fn mode(self, mode: WriteMode) -> Self {
+		Self { mode, ..self }
+	}
+}
+impl<D, Row> Destination for JsonDestination<D, Row>
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	type Item = Row;
+	type Error = JsonDestinationError<D::Error>;
+
+	type ParSink = PartFileSink<D, Row, JsonFormat>;
+	type DistSink = PartFileSink<D, Row, JsonFormat>;
+
+	fn par_sink(self) -> Self::ParSink {
+		PartFileSink::new(self.directory, JsonFormat, self.mode, self.existing)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		self.par_sink()
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JsonFormat;
+impl<D, Row> PartFileFormat<D, Row> for JsonFormat
+where
+	D: OutputDirectory,
+	Row: SerdeData,
+{
+	type Writer = ();
+	type Error = JsonDestinationError<D::Error>;
+
+	const EXTENSION: &'static str = "json";
+
+	fn writer(&self) -> Result<Self::Writer, Self::Error> {
+		Ok(())
+	}
+	fn write(
+		&self, _writer: &mut Self::Writer, row: Row, buf: &mut Vec<u8>,
+	) -> Result<(), Self::Error> {
+		serde_json::to_writer(&mut *buf, &SerdeSerialize(&row))
+			.map_err(JsonDestinationError::Json)?;
+		buf.push(b'\n');
+		Ok(())
+	}
+	fn close(&self, _writer: Self::Writer, _buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
 mod jsonerror {
-	use serde::{Deserializer, Serializer};
+	use serde::{Deserialize, Deserializer, Serializer};
+
+	use super::InternalJsonError;
 
-	pub(crate) fn serialize<T, S>(_t: &T, _serializer: S) -> Result<S::Ok, S::Error>
+	pub(crate) fn serialize<S>(t: &InternalJsonError, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: Serializer,
 	{
-		unimplemented!()
+		serializer.collect_str(t)
 	}
-	pub(crate) fn deserialize<'de, T, D>(_deserializer: D) -> Result<T, D::Error>
+	pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<InternalJsonError, D::Error>
 	where
 		D: Deserializer<'de>,
 	{
-		unimplemented!()
+		String::deserialize(deserializer).map(serde::de::Error::custom)
 	}
 }
 
@@ -195,26 +317,61 @@ where
 		}
 	}
 }
+impl<A, B, C> RecordError for JsonError<A, B, C>
+where
+	A: Display,
+	B: Display,
+	C: Display,
+{
+}
 impl<A, B, C> From<InternalJsonError> for JsonError<A, B, C> {
 	fn from(err: InternalJsonError) -> Self {
 		Self::Json(err)
 	}
 }
 
-struct PassError<R>(Result<R, Option<io::Error>>);
-impl<R> PassError<R> {
-	fn new(r: Result<R, io::Error>) -> Self {
-		Self(r.map_err(Some))
+#[derive(Serialize, Deserialize, Debug)]
+pub enum JsonDestinationError<E> {
+	Directory(E),
+	Json(#[serde(with = "jsonerror")] InternalJsonError),
+}
+impl<E> Clone for JsonDestinationError<E>
+where
+	E: Clone,
+{
+	fn clone(&self) -> Self {
+		match self {
+			Self::Directory(err) => Self::Directory(err.clone()),
+			Self::Json(err) => Self::Json(serde::de::Error::custom(err)),
+		}
 	}
 }
-impl<R> io::Read for PassError<R>
+impl<E> PartialEq for JsonDestinationError<E>
 where
-	R: io::Read,
+	E: PartialEq,
 {
-	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-		match &mut self.0 {
-			Ok(r) => r.read(buf),
-			Err(r) => Err(r.take().unwrap()),
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Self::Directory(a), Self::Directory(b)) => a.eq(b),
+			(Self::Json(a), Self::Json(b)) => a.to_string() == b.to_string(),
+			_ => false,
 		}
 	}
 }
+impl<E> error::Error for JsonDestinationError<E> where E: error::Error {}
+impl<E> Display for JsonDestinationError<E>
+where
+	E: Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Directory(err) => Display::fmt(err, f),
+			Self::Json(err) => Display::fmt(err, f),
+		}
+	}
+}
+impl<E> From<E> for JsonDestinationError<E> {
+	fn from(err: E) -> Self {
+		Self::Directory(err)
+	}
+}
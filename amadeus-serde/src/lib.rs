@@ -33,6 +33,7 @@
 mod csv;
 mod impls;
 mod json;
+mod reader;
 
 #[doc(hidden)]
 pub use serde as _internal;
@@ -42,7 +43,7 @@ use serde::{Deserializer, Serializer};
 use std::fmt::Debug;
 
 pub use self::{
-	csv::*, json::{Json, JsonError}
+	csv::*, json::{Json, JsonDestination, JsonDestinationError, JsonError, JsonFormat}
 };
 
 pub trait SerdeData
@@ -0,0 +1,128 @@
+//! Incrementally parsing rows out of a partition's pages, shared by the CSV and JSON
+//! sources.
+
+use futures::{io::AsyncRead, Stream};
+use pin_project::pin_project;
+use std::{
+	collections::VecDeque, io, pin::Pin, task::{Context, Poll}
+};
+
+use amadeus_core::file::{Page, Reader};
+
+/// The number of bytes read from a page at a time.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A parser that can be fed its input a chunk at a time.
+pub(crate) trait Decoder {
+	type Item;
+	type Error;
+
+	/// Parse the complete items at the start of `buf`, pushing them to `items`, and
+	/// return the number of bytes consumed. Bytes that might be the start of an item
+	/// continuing in the next chunk are left unconsumed, unless `eof` is true, in which
+	/// case `buf` is the remainder of the input.
+	fn decode(
+		&mut self, buf: &[u8], eof: bool, items: &mut VecDeque<Result<Self::Item, Self::Error>>,
+	) -> usize;
+	fn io_error(err: io::Error) -> Self::Error;
+}
+
+/// Reads the pages of a partition one after another, yielding the items decoded from
+/// them as they arrive. Items may span pages, and only the bytes of the item being
+/// decoded are kept in memory.
+#[pin_project]
+pub(crate) struct DecodeStream<I, D>
+where
+	I: Iterator,
+	I::Item: Page,
+	D: Decoder,
+{
+	pages: I,
+	reader: Option<Pin<Box<Reader<I::Item>>>>,
+	decoder: D,
+	buf: Vec<u8>,
+	items: VecDeque<Result<D::Item, D::Error>>,
+	done: bool,
+}
+impl<I, D> DecodeStream<I, D>
+where
+	I: Iterator,
+	I::Item: Page,
+	D: Decoder,
+{
+	pub(crate) fn new<P>(pages: P, decoder: D) -> Self
+	where
+		P: IntoIterator<IntoIter = I, Item = I::Item>,
+	{
+		Self {
+			pages: pages.into_iter(),
+			reader: None,
+			decoder,
+			buf: Vec::new(),
+			items: VecDeque::new(),
+			done: false,
+		}
+	}
+}
+impl<I, D> Stream for DecodeStream<I, D>
+where
+	I: Iterator,
+	I::Item: Page,
+	D: Decoder,
+{
+	type Item = Result<D::Item, D::Error>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		loop {
+			if let Some(item) = self_.items.pop_front() {
+				return Poll::Ready(Some(item));
+			}
+			if *self_.done {
+				return Poll::Ready(None);
+			}
+			if self_.reader.is_none() {
+				match self_.pages.next() {
+					Some(page) => *self_.reader = Some(Box::pin(page.reader())),
+					None => {
+						let _ = self_.decoder.decode(self_.buf, true, self_.items);
+						self_.buf.clear();
+						*self_.done = true;
+						continue;
+					}
+				}
+			}
+			let len = self_.buf.len();
+			self_.buf.resize(len + CHUNK_SIZE, 0);
+			let read = self_
+				.reader
+				.as_mut()
+				.unwrap()
+				.as_mut()
+				.poll_read(cx, &mut self_.buf[len..]);
+			let read = match read {
+				Poll::Ready(read) => read,
+				Poll::Pending => {
+					self_.buf.truncate(len);
+					return Poll::Pending;
+				}
+			};
+			match read {
+				Ok(0) => {
+					self_.buf.truncate(len);
+					*self_.reader = None;
+				}
+				Ok(read) => {
+					self_.buf.truncate(len + read);
+					let consumed = self_.decoder.decode(self_.buf, false, self_.items);
+					let _ = self_.buf.drain(..consumed);
+				}
+				Err(err) => {
+					self_.buf.truncate(len);
+					self_.items.push_back(Err(D::io_error(err)));
+					*self_.done = true;
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, f64::consts::PI, iter, ops};
+
+use super::{f64_to_usize, u64_to_f64};
+use crate::traits::{New, UnionAssign};
+
+/// Centroids are stored clustered as if the compression were this many times higher,
+/// and only clustered at the given compression when queried. Centroids can't be split
+/// once formed, so this keeps the accuracy of digests merged from disjoint parts of a
+/// stream, whose centroids may span values only seen by other digests.
+const STORED_COMPRESSION: f64 = 4.0;
+
+/// A [t-digest](https://github.com/tdunning/t-digest), which estimates the quantiles of a
+/// stream of values. It uses `O(compression)` space, with the estimates most accurate at
+/// the extremes, i.e. p1 and p99 are more accurate than p50.
+///
+/// Digests of disjoint streams can be merged with [`UnionAssign`] to give a digest of
+/// the whole stream.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TDigest {
+	compression: f64,
+	centroids: Vec<Centroid>,
+	buffer: Vec<Centroid>,
+	count: u64,
+	min: f64,
+	max: f64,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+struct Centroid {
+	mean: f64,
+	weight: f64,
+}
+
+impl TDigest {
+	/// Create an empty `TDigest` data structure with the specified compression. Higher
+	/// compression is more accurate but uses more space; `100.0` is typical.
+	pub fn new(compression: f64) -> Self {
+		assert!(compression >= 1.0);
+		Self {
+			compression,
+			centroids: Vec::new(),
+			buffer: Vec::new(),
+			count: 0,
+			min: f64::INFINITY,
+			max: f64::NEG_INFINITY,
+		}
+	}
+
+	/// Create an empty `TDigest` data structure, copying the compression from `digest`.
+	pub fn new_from(digest: &Self) -> Self {
+		Self::new(digest.compression)
+	}
+
+	/// "Visit" a value. `NaN`s are ignored.
+	#[inline]
+	pub fn push(&mut self, value: f64) {
+		if value.is_nan() {
+			return;
+		}
+		self.count += 1;
+		self.min = self.min.min(value);
+		self.max = self.max.max(value);
+		self.buffer.push(Centroid {
+			mean: value,
+			weight: 1.0,
+		});
+		if self.buffer.len() >= self.buffer_capacity() {
+			self.compress();
+		}
+	}
+
+	/// Merge another `TDigest` into this one, re-clustering the centroids of both.
+	pub fn union(&mut self, src: &Self) {
+		self.count += src.count;
+		self.min = self.min.min(src.min);
+		self.max = self.max.max(src.max);
+		self.buffer.extend(&src.centroids);
+		self.buffer.extend(&src.buffer);
+		self.compress();
+	}
+
+	/// Retrieve an estimate of the `q`th quantile of the stream, where `q` is between
+	/// `0.0` and `1.0`. Returns `None` if no values have been visited.
+	pub fn quantile(&self, q: f64) -> Option<f64> {
+		assert!((0.0..=1.0).contains(&q));
+		if self.count == 0 {
+			return None;
+		}
+		let centroids = self.merged(self.compression);
+		let total = u64_to_f64(self.count);
+		let target = q * total;
+
+		// interpolate between the centers of adjacent centroids, using min and max as
+		// the outermost points
+		let first = centroids[0];
+		if target < first.weight / 2.0 {
+			return Some(self.min + (first.mean - self.min) * target / (first.weight / 2.0));
+		}
+		let mut before = 0.0;
+		for pair in centroids.windows(2) {
+			let (a, b) = (pair[0], pair[1]);
+			let a_center = before + a.weight / 2.0;
+			let b_center = before + a.weight + b.weight / 2.0;
+			if target < b_center {
+				let value =
+					a.mean + (b.mean - a.mean) * (target - a_center) / (b_center - a_center);
+				return Some(value.max(self.min).min(self.max));
+			}
+			before += a.weight;
+		}
+		let last = centroids[centroids.len() - 1];
+		let last_center = total - last.weight / 2.0;
+		if target <= last_center {
+			return Some(last.mean);
+		}
+		Some(last.mean + (self.max - last.mean) * (target - last_center) / (last.weight / 2.0))
+	}
+
+	/// Retrieve the number of values visited.
+	pub fn len(&self) -> u64 {
+		self.count
+	}
+
+	/// Returns `true` if no values have been visited.
+	pub fn is_empty(&self) -> bool {
+		self.count == 0
+	}
+
+	/// Retrieve the smallest value visited.
+	pub fn min(&self) -> Option<f64> {
+		if self.count != 0 {
+			Some(self.min)
+		} else {
+			None
+		}
+	}
+
+	/// Retrieve the largest value visited.
+	pub fn max(&self) -> Option<f64> {
+		if self.count != 0 {
+			Some(self.max)
+		} else {
+			None
+		}
+	}
+
+	/// Clears the `TDigest` data structure, as if it was new.
+	pub fn clear(&mut self) {
+		*self = Self::new(self.compression);
+	}
+
+	fn buffer_capacity(&self) -> usize {
+		f64_to_usize((self.compression * 5.0).ceil())
+	}
+
+	fn compress(&mut self) {
+		self.centroids = self.merged(self.compression * STORED_COMPRESSION);
+		self.buffer.clear();
+	}
+
+	/// The centroids and buffered values, merged per the k1 scale function at the given
+	/// compression such that each centroid spans at most one unit of `k`.
+	fn merged(&self, compression: f64) -> Vec<Centroid> {
+		let mut all: Vec<Centroid> = Vec::with_capacity(self.centroids.len() + self.buffer.len());
+		all.extend(&self.centroids);
+		all.extend(&self.buffer);
+		all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+		let total: f64 = all.iter().map(|centroid| centroid.weight).sum();
+
+		let mut merged = Vec::with_capacity(self.buffer_capacity());
+		let mut all = all.into_iter();
+		let mut current = match all.next() {
+			Some(centroid) => centroid,
+			None => return merged,
+		};
+		let mut before = 0.0;
+		let mut limit = total * k_inv(compression, k(compression, 0.0) + 1.0);
+		for centroid in all {
+			if before + current.weight + centroid.weight <= limit {
+				current.weight += centroid.weight;
+				current.mean += (centroid.mean - current.mean) * centroid.weight / current.weight;
+			} else {
+				before += current.weight;
+				limit = total * k_inv(compression, k(compression, before / total) + 1.0);
+				merged.push(current);
+				current = centroid;
+			}
+		}
+		merged.push(current);
+		merged
+	}
+}
+
+fn k(compression: f64, q: f64) -> f64 {
+	compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+}
+fn k_inv(compression: f64, k: f64) -> f64 {
+	if k >= compression / 4.0 {
+		1.0
+	} else {
+		((2.0 * PI * k / compression).sin() + 1.0) / 2.0
+	}
+}
+impl New for TDigest {
+	type Config = f64;
+	fn new(config: &Self::Config) -> Self {
+		Self::new(*config)
+	}
+}
+impl<'a> UnionAssign<&'a TDigest> for TDigest {
+	fn union_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+impl<'a> ops::AddAssign<&'a f64> for TDigest {
+	fn add_assign(&mut self, rhs: &'a f64) {
+		self.push(*rhs);
+	}
+}
+impl<'a> ops::AddAssign<&'a Self> for TDigest {
+	fn add_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs);
+	}
+}
+impl iter::Sum<TDigest> for Option<TDigest> {
+	fn sum<I>(mut iter: I) -> Self
+	where
+		I: Iterator<Item = TDigest>,
+	{
+		let mut total = iter.next()?;
+		for digest in iter {
+			total.union(&digest);
+		}
+		Some(total)
+	}
+}
+impl ops::Add for TDigest {
+	type Output = Self;
+
+	fn add(mut self, other: Self) -> Self {
+		self.union(&other);
+		self
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::TDigest;
+
+	#[test]
+	fn uniform() {
+		let mut digest = TDigest::new(100.0);
+		for i in 0..100_000 {
+			digest.push(f64::from(i));
+		}
+		assert_eq!(digest.len(), 100_000);
+		assert_eq!(digest.quantile(0.0), Some(0.0));
+		assert_eq!(digest.quantile(1.0), Some(99_999.0));
+		for &q in &[0.01, 0.1, 0.5, 0.9, 0.99, 0.999] {
+			let estimate = digest.quantile(q).unwrap();
+			assert!(
+				(estimate - q * 100_000.0).abs() < 100_000.0 * 0.005,
+				"{} {}",
+				q,
+				estimate
+			);
+		}
+	}
+
+	#[test]
+	fn union() {
+		let mut a = TDigest::new(100.0);
+		let mut b = TDigest::new(100.0);
+		for i in 0..50_000 {
+			a.push(f64::from(i * 2));
+			b.push(f64::from(i * 2 + 1));
+		}
+		a += &b;
+		assert_eq!(a.len(), 100_000);
+		assert_eq!(a.min(), Some(0.0));
+		assert_eq!(a.max(), Some(99_999.0));
+		let estimate = a.quantile(0.5).unwrap();
+		assert!((estimate - 50_000.0).abs() < 500.0, "{}", estimate);
+	}
+
+	#[test]
+	fn union_disjoint() {
+		// Digests of consecutive runs of values, merged in various orders
+		let digests = (0..100)
+			.map(|i| {
+				let mut digest = TDigest::new(100.0);
+				for j in 0..100 {
+					digest.push(f64::from(i * 100 + j));
+				}
+				digest
+			})
+			.collect::<Vec<_>>();
+		for &stride in &[1, 3, 7, 11, 37, 41, 59, 99] {
+			let mut total = TDigest::new(100.0);
+			for i in 0..100 {
+				total += &digests[i * stride % 100];
+			}
+			assert_eq!(total.len(), 10_000);
+			for &(q, value, error) in &[(0.5, 5_000.0, 50.0), (0.99, 9_900.0, 10.0)] {
+				let estimate = total.quantile(q).unwrap();
+				assert!(
+					(estimate - value).abs() < error,
+					"{} {} {}",
+					stride,
+					q,
+					estimate
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn empty() {
+		let mut digest = TDigest::new(100.0);
+		assert_eq!(digest.quantile(0.5), None);
+		digest.push(f64::NAN);
+		assert!(digest.is_empty());
+		digest.push(1.0);
+		assert_eq!(digest.quantile(0.5), Some(1.0));
+	}
+}
@@ -2,6 +2,7 @@
 
 #![allow(clippy::trivially_copy_pass_by_ref)]
 
+use amadeus_core::par_sink::Timestamp;
 use chrono::{
 	offset::{Offset, TimeZone}, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc
 };
@@ -14,6 +15,8 @@ use super::AmadeusOrd;
 
 const JULIAN_DAY_OF_EPOCH: i64 = 2_440_588;
 const GREGORIAN_DAY_OF_EPOCH: i64 = 719_163;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
 
 const TODO: &str = "not implemented yet";
 
@@ -582,6 +585,15 @@ impl AmadeusOrd for DateTime {
 		Ord::cmp(self, other)
 	}
 }
+/// Times converted from nanoseconds, like the starts of windows, are in UTC.
+impl Timestamp for DateTime {
+	fn as_nanos(&self) -> i64 {
+		self.date_time.as_nanos()
+	}
+	fn from_nanos(nanos: i64) -> Self {
+		DateTimeWithoutTimezone::from_nanos(nanos).with_timezone(Timezone::UTC)
+	}
+}
 /// Corresponds to RFC 3339 and ISO 8601 string `%Y-%m-%dT%H:%M:%S%.9f%:z`
 impl Display for DateTime {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -983,6 +995,32 @@ impl AmadeusOrd for DateTimeWithoutTimezone {
 		Ord::cmp(self, other)
 	}
 }
+impl Timestamp for DateTimeWithoutTimezone {
+	fn as_nanos(&self) -> i64 {
+		let seconds = i64::from(self.hour()) * 3600
+			+ i64::from(self.minute()) * 60
+			+ i64::from(self.second());
+		self.date
+			.as_days()
+			.checked_mul(SECONDS_PER_DAY * NANOS_PER_SECOND)
+			.and_then(|nanos| nanos.checked_add(seconds * NANOS_PER_SECOND))
+			.and_then(|nanos| nanos.checked_add(i64::from(self.nanosecond())))
+			.expect("date time is out of range for nanoseconds")
+	}
+	fn from_nanos(nanos: i64) -> Self {
+		let days = nanos.div_euclid(SECONDS_PER_DAY * NANOS_PER_SECOND);
+		let nanos = nanos.rem_euclid(SECONDS_PER_DAY * NANOS_PER_SECOND);
+		Self::from_date_time(
+			DateWithoutTimezone::from_days(days).unwrap(),
+			TimeWithoutTimezone::from_seconds(
+				(nanos / NANOS_PER_SECOND).try_into().unwrap(),
+				(nanos % NANOS_PER_SECOND).try_into().unwrap(),
+			)
+			.unwrap(),
+		)
+		.unwrap()
+	}
+}
 impl Display for DateTimeWithoutTimezone {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		self.as_chrono().expect(TODO).fmt(f)
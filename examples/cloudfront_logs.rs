@@ -11,6 +11,8 @@
 //! cargo run --example cloudfront_logs --release
 //! ```
 
+use std::time::Duration;
+
 use amadeus::prelude::*;
 
 #[allow(unreachable_code)]
@@ -27,22 +29,28 @@ async fn main() {
 	.await
 	.unwrap();
 
-	let (sample, histogram) = rows
+	let (sample, (histogram, sizes)) = rows
 		.par_stream()
 		.map(Result::unwrap)
 		.fork(
 			pool,
 			Identity.sample_unstable(10),
-			Identity
-				.map(|row: &CloudfrontRow| (row.time.truncate_minutes(60), ()))
-				.group_by(Identity.count()),
+			(
+				Identity
+					.map(|row: &CloudfrontRow| row.time)
+					.tumbling_window(
+						|time: &DateTime| *time,
+						Duration::from_secs(60 * 60),
+						Identity.count(),
+					),
+				Identity
+					.map(|row: &CloudfrontRow| row.response_bytes as f64)
+					.quantiles(&[0.5, 0.95, 0.99]),
+			),
 		)
 		.await;
 
-	let mut histogram = histogram.into_iter().collect::<Vec<_>>();
-	histogram.sort();
-
-	assert_eq!(histogram.iter().map(|(_, c)| c).sum::<usize>(), 207_928);
+	assert_eq!(histogram.values().sum::<usize>(), 207_928);
 
 	println!("sample: {:#?}", sample);
 	println!(
@@ -53,4 +61,8 @@ async fn main() {
 			.collect::<Vec<_>>()
 			.join("\n    ")
 	);
+	println!(
+		"response bytes: p50 {:?}, p95 {:?}, p99 {:?}",
+		sizes[0], sizes[1], sizes[2]
+	);
 }
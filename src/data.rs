@@ -3,6 +3,8 @@ use std::{
 	borrow::Cow, cmp::Ordering, collections::HashMap, fmt::Debug, hash::{BuildHasher, Hash, Hasher}, mem
 };
 
+use amadeus_core::par_sink::RecordError;
+
 #[cfg(feature = "parquet")]
 use amadeus_parquet::ParquetData;
 #[cfg(feature = "postgres")]
@@ -251,6 +253,25 @@ pub struct CloudfrontRow {
 	pub fle_status: Option<String>,
 	pub fle_encrypted_fields: Option<String>,
 }
+/// A record that couldn't be parsed, and why, as can be written to a dead-letter
+/// destination with [`split_errors`](crate::par_pipe::ParallelPipe::split_errors).
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct DeadLetter {
+	pub record: Option<String>,
+	pub error: String,
+}
+impl DeadLetter {
+	pub fn new<E: RecordError + ?Sized>(error: &E) -> Self {
+		Self {
+			record: error.record().map(ToOwned::to_owned),
+			error: error.to_string(),
+		}
+	}
+}
+
 #[cfg(feature = "aws")]
 impl From<amadeus_aws::CloudfrontRow> for CloudfrontRow {
 	fn from(from: amadeus_aws::CloudfrontRow) -> Self {
@@ -65,8 +65,8 @@ pub mod dist {
 		#[doc(no_inline)]
 		pub use crate::{
 			data::{
-				Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
-			}, par_pipe::DistributedPipe, par_stream::Identity, pool::ThreadPool, source::*, Data, DistributedStream, FromDistributedStream, IntoDistributedStream, IteratorExt, List, Value
+				Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, DeadLetter, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
+			}, par_pipe::DistributedPipe, par_stream::{Cancel, Cancelled, Identity, JoinKind, Progress}, pool::{Checkpoint, RetryPolicy, ThreadPool}, source::*, Data, DistributedStream, FromDistributedStream, IntoDistributedStream, IteratorExt, List, Value
 		};
 		#[doc(no_inline)]
 		pub use serde_closure::{Fn, FnMut, FnOnce};
@@ -84,8 +84,8 @@ pub mod prelude {
 	#[doc(no_inline)]
 	pub use crate::{
 		data::{
-			Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
-		}, par_pipe::ParallelPipe, par_stream::Identity, pool::ThreadPool, source::*, Data, FromParallelStream, IntoParallelStream, IteratorExt, List, ParallelStream, Value
+			Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, DeadLetter, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
+		}, par_pipe::ParallelPipe, par_stream::{Cancel, Cancelled, Identity, JoinKind, Progress}, pool::{Checkpoint, RetryPolicy, ThreadPool}, source::*, Data, FromParallelStream, IntoParallelStream, IteratorExt, List, ParallelStream, Value
 	};
 }
 
@@ -7,6 +7,7 @@ use futures::future::{BoxFuture, TryFutureExt};
 use serde_closure::traits;
 use std::{error::Error, future::Future};
 
+pub use amadeus_core::pool::{Checkpoint, RetryPolicy};
 #[cfg(feature = "constellation")]
 pub use process::ProcessPool;
 pub use thread::ThreadPool;
@@ -25,6 +26,12 @@ impl ProcessPoolTrait for ProcessPool {
 	fn processes(&self) -> usize {
 		ProcessPool::processes(self)
 	}
+	fn retry_policy(&self) -> RetryPolicy {
+		ProcessPool::retry_policy(self)
+	}
+	fn checkpoint(&self) -> Option<Checkpoint> {
+		ProcessPool::checkpoint(self)
+	}
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'static,
@@ -44,6 +51,8 @@ impl ProcessPoolTrait for ProcessPool {
 	}
 }
 
+// The default retry policy is kept here: a distributed stream's tasks are run as one
+// task of this "process", and retried individually on the thread pool it's passed.
 #[cfg_attr(not(nightly), serde_closure::desugar)]
 impl ProcessPoolTrait for ThreadPool {
 	type ThreadPool = Self;
@@ -51,6 +60,9 @@ impl ProcessPoolTrait for ThreadPool {
 	fn processes(&self) -> usize {
 		1
 	}
+	fn checkpoint(&self) -> Option<Checkpoint> {
+		ThreadPool::checkpoint(self)
+	}
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'static,
@@ -78,6 +90,9 @@ impl ThreadPoolTrait for ThreadPool {
 	fn threads(&self) -> usize {
 		ThreadPool::threads(self)
 	}
+	fn retry_policy(&self) -> RetryPolicy {
+		ThreadPool::retry_policy(self)
+	}
 	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
 	where
 		F: FnOnce() -> Fut + Send + 'static,
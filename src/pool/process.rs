@@ -1,12 +1,21 @@
 use constellation::{spawn, Receiver, Resources, Sender, SpawnError};
-use futures::{future::LocalBoxFuture, FutureExt};
+use futures::{
+	future::{self, LocalBoxFuture}, FutureExt
+};
+use serde::{Deserialize, Serialize};
 use serde_closure::{traits, FnOnce};
 use serde_traitobject as st;
 use std::{
-	any, collections::VecDeque, fmt, future::Future, mem, panic::{self, RefUnwindSafe, UnwindSafe}, sync::{Arc, Mutex}
+	any, collections::VecDeque, fmt, future::Future, mem, panic::{self, RefUnwindSafe, UnwindSafe}, sync::{
+		atomic::{AtomicBool, Ordering}, Arc, Mutex, Weak
+	}, thread
 };
 
-use amadeus_core::pool::ProcessSend;
+use amadeus_core::{
+	par_stream::{
+		cancel_forwarded, forward_cancels, progress_reported, take_progress_reports, ForwardCancel, ProgressReport
+	}, pool::{Checkpoint, ProcessSend, RetryPolicy}
+};
 
 use super::{
 	util::{assert_sync_and_send, OnDrop, Panicked, RoundRobin, Synchronize}, ThreadPool
@@ -15,6 +24,18 @@ use super::{
 #[cfg_attr(not(nightly), serde_closure::desugar)]
 type Request = st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send>;
 type Response = Box<dyn st::Any + Send>;
+/// A response, sent back with the metrics of the tasks it ran for the `Progress`
+/// handles they report to.
+type Reply = (Result<Response, Panicked>, Vec<ProgressReport>);
+
+/// A message to a process of the pool.
+#[derive(Serialize, Deserialize)]
+enum Message {
+	/// Work to run, whose response is sent back in order.
+	Work(Request),
+	/// Cancel the tasks in progress with the `Cancel` handle of this id.
+	Cancel(u64),
+}
 
 mod future_ext {
 	use futures::{future::Future, pin_mut};
@@ -61,10 +82,12 @@ use future_ext::FutureExt1;
 
 #[derive(Debug)]
 struct Process {
-	sender: Sender<Option<Request>>,
-	receiver: Receiver<Result<Response, Panicked>>,
+	sender: Sender<Option<Message>>,
+	receiver: Receiver<Reply>,
 	inner: Mutex<ProcessInner>,
 	synchronize: Synchronize,
+	/// Set once the process is found to have exited, so no more work is sent to it.
+	exited: AtomicBool,
 }
 struct ProcessInner {
 	queue: VecDeque<Queued<Result<Response, Panicked>>>,
@@ -128,22 +151,39 @@ impl ProcessPoolInner {
 						.build()
 						.unwrap()
 						.block_on(async {
-							let receiver = Receiver::<Option<Request>>::new(parent);
-							let sender = Sender::<Result<Response, Panicked>>::new(parent);
+							let receiver = Receiver::<Option<Message>>::new(parent);
+							let sender = Sender::<Reply>::new(parent);
 
 							let thread_pool = ThreadPool::new(threads, tasks).unwrap();
 
-							while let Some(work) = receiver.recv().await.unwrap() {
-								let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-									work.into_box().call_once_box((&thread_pool,))
-								}));
-								let ret = match ret {
-									Ok(t) => panic::AssertUnwindSafe(t).catch_unwind().await,
-									Err(e) => Err(e),
+							// Work is run a request at a time, while cancellations are applied
+							// as they arrive so they reach the request in progress
+							let (work_sender, work_receiver) = async_channel::unbounded();
+							let receive = async move {
+								while let Some(message) = receiver.recv().await.unwrap() {
+									match message {
+										Message::Work(work) => {
+											let _ = work_sender.send(work).await;
+										}
+										Message::Cancel(id) => cancel_forwarded(id),
+									}
+								}
+							};
+							let run = async {
+								while let Ok(work) = work_receiver.recv().await {
+									let work: Request = work;
+									let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+										work.into_box().call_once_box((&thread_pool,))
+									}));
+									let ret = match ret {
+										Ok(t) => panic::AssertUnwindSafe(t).catch_unwind().await,
+										Err(e) => Err(e),
+									}
+									.map_err(Panicked::from);
+									sender.send((ret, take_progress_reports())).await;
 								}
-								.map_err(Panicked::from);
-								sender.send(ret).await;
-							}
+							};
+							let ((), ()) = future::join(receive, run).await;
 						})
 				}),
 			)
@@ -171,6 +211,7 @@ impl ProcessPoolInner {
 					tail,
 				}),
 				synchronize: Synchronize::new(),
+				exited: AtomicBool::new(false),
 			})
 		}
 		let i = RoundRobin::new(0, processes_vec.len());
@@ -182,22 +223,33 @@ impl ProcessPoolInner {
 	fn processes(&self) -> usize {
 		self.processes.len()
 	}
+	/// The next process in turn that hasn't exited, if there are any.
+	fn process(&self) -> Result<&Process, Panicked> {
+		(0..self.processes.len())
+			.map(|_| &self.processes[self.i.get()])
+			.find(|process| !process.exited.load(Ordering::Relaxed))
+			.ok_or_else(|| Panicked::new(String::from("all processes exited")))
+	}
+	fn running(&self) -> impl Iterator<Item = &Process> {
+		self.processes
+			.iter()
+			.filter(|process| !process.exited.load(Ordering::Relaxed))
+	}
 	async fn spawn<F, Fut, T>(&self, work: F) -> Result<T, Panicked>
 	where
 		F: for<'a> traits::FnOnce<(&'a ThreadPool,), Output = Fut> + ProcessSend + 'static,
 		Fut: Future<Output = T> + 'static,
 		T: ProcessSend + 'static,
 	{
-		let process_index = self.i.get();
-		let process = &self.processes[process_index];
-		let x = process
-			.sender
-			.send(Some(st::Box::new(FnOnce!(move |thread_pool: &_| {
+		let process = self.process()?;
+		let x = process.sender.send(Some(Message::Work(st::Box::new(FnOnce!(
+			move |thread_pool: &_| {
 				let work: F = work;
 				work.call_once((thread_pool,))
 					.map(|res| Box::new(res) as Response)
 					.boxed_local()
-			})) as Request));
+			}
+		)) as Request)));
 		x.await;
 		let index;
 		{
@@ -225,7 +277,14 @@ impl ProcessPoolInner {
 						return;
 					}
 					let z = process.receiver.recv().await;
-					let t = z.unwrap();
+					let (t, reports) = z.unwrap_or_else(|_| {
+						process.exited.store(true, Ordering::Relaxed);
+						(
+							Err(Panicked::new(String::from("process exited"))),
+							Vec::new(),
+						)
+					});
+					progress_reported(reports);
 					let mut process_inner_lock = process.inner.lock().unwrap();
 					let offset = process_inner_lock.received - process_inner_lock.tail;
 					process_inner_lock.queue[offset].received(t);
@@ -252,8 +311,7 @@ impl ProcessPoolInner {
 		Fut: Future<Output = T> + 'a,
 		T: ProcessSend + 'a,
 	{
-		let process_index = self.i.get();
-		let process = &self.processes[process_index];
+		let process = self.process()?;
 		let request = st::Box::new(FnOnce!(move |thread_pool: &_| {
 			let work: F = work;
 			work.call_once((thread_pool,))
@@ -267,7 +325,7 @@ impl ProcessPoolInner {
 			st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'a, Response> + Send>,
 			st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send>,
 		>(request);
-		let x = process.sender.send(Some(request));
+		let x = process.sender.send(Some(Message::Work(request)));
 		x.await;
 		let index;
 		{
@@ -295,7 +353,14 @@ impl ProcessPoolInner {
 						return;
 					}
 					let z = process.receiver.recv().await;
-					let t = z.unwrap();
+					let (t, reports) = z.unwrap_or_else(|_| {
+						process.exited.store(true, Ordering::Relaxed);
+						(
+							Err(Panicked::new(String::from("process exited"))),
+							Vec::new(),
+						)
+					});
+					progress_reported(reports);
 					let mut process_inner_lock = process.inner.lock().unwrap();
 					let offset = process_inner_lock.received - process_inner_lock.tail;
 					process_inner_lock.queue[offset].received(t);
@@ -321,9 +386,19 @@ impl ProcessPoolInner {
 		})
 	}
 }
+impl ForwardCancel for ProcessPoolInner {
+	fn forward_cancel(self: Arc<Self>, id: u64) {
+		// Sent from a thread of its own, as a send blocks while the process is busy
+		let _ = thread::spawn(move || {
+			for Process { sender, .. } in self.running() {
+				sender.send(Some(Message::Cancel(id))).block();
+			}
+		});
+	}
+}
 impl Drop for ProcessPoolInner {
 	fn drop(&mut self) {
-		for Process { sender, .. } in &self.processes {
+		for Process { sender, .. } in self.running() {
 			// TODO: select, incl recv
 			sender.send(None).block();
 		}
@@ -331,19 +406,50 @@ impl Drop for ProcessPoolInner {
 }
 
 #[derive(Debug)]
-pub struct ProcessPool(Arc<ProcessPoolInner>);
+pub struct ProcessPool {
+	inner: Arc<ProcessPoolInner>,
+	retry: RetryPolicy,
+	checkpoint: Option<Checkpoint>,
+}
 #[cfg_attr(not(nightly), serde_closure::desugar)]
 impl ProcessPool {
 	pub fn new(
 		processes: Option<usize>, threads: Option<usize>, tasks: Option<usize>,
 		resources: Resources,
 	) -> Result<Self, SpawnError> {
-		Ok(Self(Arc::new(ProcessPoolInner::new(
-			processes, threads, tasks, resources,
-		)?)))
+		let inner = Arc::new(ProcessPoolInner::new(processes, threads, tasks, resources)?);
+		let forward: Weak<dyn ForwardCancel> = Arc::downgrade(&inner);
+		forward_cancels(forward);
+		Ok(Self {
+			inner,
+			retry: RetryPolicy::new(),
+			checkpoint: None,
+		})
+	}
+	/// Retry and speculatively re-execute the tasks of distributed streams run on this
+	/// pool according to `policy`, re-queueing them on other processes.
+	pub fn with_retry(self, policy: RetryPolicy) -> Self {
+		Self {
+			retry: policy,
+			..self
+		}
+	}
+	/// Record the partial results of the finished tasks of distributed streams run on
+	/// this pool in `checkpoint`, and skip the tasks already recorded there.
+	pub fn with_checkpoint(self, checkpoint: Checkpoint) -> Self {
+		Self {
+			checkpoint: Some(checkpoint),
+			..self
+		}
 	}
 	pub fn processes(&self) -> usize {
-		self.0.processes()
+		self.inner.processes()
+	}
+	pub fn retry_policy(&self) -> RetryPolicy {
+		self.retry.clone()
+	}
+	pub fn checkpoint(&self) -> Option<Checkpoint> {
+		self.checkpoint.clone()
 	}
 	pub fn spawn<F, Fut, T>(&self, work: F) -> impl Future<Output = Result<T, Panicked>> + Send
 	where
@@ -351,7 +457,7 @@ impl ProcessPool {
 		Fut: Future<Output = T> + 'static,
 		T: ProcessSend + 'static,
 	{
-		let inner = self.0.clone();
+		let inner = self.inner.clone();
 		async move { inner.spawn(work).await }
 	}
 	#[allow(unsafe_code)]
@@ -363,7 +469,7 @@ impl ProcessPool {
 		Fut: Future<Output = T> + 'a,
 		T: ProcessSend + 'a,
 	{
-		let inner = self.0.clone();
+		let inner = self.inner.clone();
 		async move { inner.spawn_unchecked(work).await }
 	}
 }
@@ -374,7 +480,11 @@ impl Clone for ProcessPool {
 	///
 	/// We could for example submit jobs from multiple threads concurrently.
 	fn clone(&self) -> Self {
-		Self(self.0.clone())
+		Self {
+			inner: self.inner.clone(),
+			retry: self.retry.clone(),
+			checkpoint: self.checkpoint.clone(),
+		}
 	}
 }
 
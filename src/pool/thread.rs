@@ -11,6 +11,8 @@ use {
 	futures::{future, FutureExt}, std::panic::AssertUnwindSafe
 };
 
+use amadeus_core::pool::{Checkpoint, RetryPolicy};
+
 use super::util::{assert_sync_and_send, Panicked};
 
 const DEFAULT_TASKS_PER_CORE: usize = 100;
@@ -24,7 +26,11 @@ struct ThreadPoolInner {
 }
 
 #[derive(Debug)]
-pub struct ThreadPool(Arc<ThreadPoolInner>);
+pub struct ThreadPool {
+	inner: Arc<ThreadPoolInner>,
+	retry: RetryPolicy,
+	checkpoint: Option<Checkpoint>,
+}
 impl ThreadPool {
 	pub fn new(threads: Option<usize>, tasks: Option<usize>) -> io::Result<Self> {
 		let threads = if let Some(threads) = threads {
@@ -38,15 +44,44 @@ impl ThreadPool {
 		let tasks = tasks.unwrap_or(DEFAULT_TASKS_PER_CORE);
 		#[cfg(not(target_arch = "wasm32"))]
 		let pool = Pool::new(threads);
-		Ok(ThreadPool(Arc::new(ThreadPoolInner {
-			threads,
-			tasks,
-			#[cfg(not(target_arch = "wasm32"))]
-			pool,
-		})))
+		Ok(ThreadPool {
+			inner: Arc::new(ThreadPoolInner {
+				threads,
+				tasks,
+				#[cfg(not(target_arch = "wasm32"))]
+				pool,
+			}),
+			retry: RetryPolicy::new(),
+			checkpoint: None,
+		})
+	}
+	/// Retry and speculatively re-execute the tasks of distributed streams run on this
+	/// pool according to `policy`.
+	///
+	/// The tasks of parallel streams can't be copied, so aren't retried or re-executed;
+	/// see [`RetryPolicy`].
+	pub fn with_retry(self, policy: RetryPolicy) -> Self {
+		Self {
+			retry: policy,
+			..self
+		}
+	}
+	/// Record the partial results of the finished tasks of distributed streams run on
+	/// this pool in `checkpoint`, and skip the tasks already recorded there.
+	pub fn with_checkpoint(self, checkpoint: Checkpoint) -> Self {
+		Self {
+			checkpoint: Some(checkpoint),
+			..self
+		}
 	}
 	pub fn threads(&self) -> usize {
-		self.0.threads * self.0.tasks
+		self.inner.threads * self.inner.tasks
+	}
+	pub fn retry_policy(&self) -> RetryPolicy {
+		self.retry.clone()
+	}
+	pub fn checkpoint(&self) -> Option<Checkpoint> {
+		self.checkpoint.clone()
 	}
 	pub fn spawn<F, Fut, T>(&self, task: F) -> impl Future<Output = Result<T, Panicked>> + Send
 	where
@@ -56,7 +91,7 @@ impl ThreadPool {
 	{
 		#[cfg(not(target_arch = "wasm32"))]
 		return self
-			.0
+			.inner
 			.pool
 			.spawn_pinned(task)
 			.map_err(JoinError::into_panic)
@@ -83,7 +118,7 @@ impl ThreadPool {
 	{
 		#[cfg(not(target_arch = "wasm32"))]
 		return Guard::new(
-			self.0
+			self.inner
 				.pool
 				.spawn_pinned_unchecked(task)
 				.map_err(JoinError::into_panic)
@@ -121,7 +156,11 @@ impl Clone for ThreadPool {
 	///
 	/// We could for example submit jobs from multiple threads concurrently.
 	fn clone(&self) -> Self {
-		Self(self.0.clone())
+		Self {
+			inner: self.inner.clone(),
+			retry: self.retry.clone(),
+			checkpoint: self.checkpoint.clone(),
+		}
 	}
 }
 
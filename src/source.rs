@@ -13,6 +13,8 @@ use crate::{
 #[cfg(feature = "aws")]
 #[doc(inline)]
 pub use amadeus_aws::Cloudfront;
+#[doc(inline)]
+pub use amadeus_core::file::{Splits, WriteMode};
 #[cfg(feature = "aws")]
 pub mod aws {
 	pub use crate::data::CloudfrontRow;
@@ -24,13 +26,13 @@ pub mod aws {
 pub use amadeus_commoncrawl::CommonCrawl;
 #[cfg(feature = "parquet")]
 #[doc(inline)]
-pub use amadeus_parquet::{Parquet, ParquetDirectory};
+pub use amadeus_parquet::{Parquet, ParquetDestination, ParquetDirectory, ParquetFilter};
 #[cfg(feature = "postgres")]
 #[doc(inline)]
-pub use amadeus_postgres::{Postgres, PostgresSelect, PostgresTable};
+pub use amadeus_postgres::{Postgres, PostgresDestination, PostgresSelect, PostgresTable};
 #[cfg(feature = "amadeus-serde")]
 #[doc(inline)]
-pub use amadeus_serde::{Csv, Json};
+pub use amadeus_serde::{Csv, CsvBuilder, CsvDestination, Json, JsonDestination, Terminator, Trim};
 
 pub trait Source: Clone + Debug {
 	type Item: crate::data::Data;
@@ -160,6 +162,82 @@ impl Source for CommonCrawl {
 	}
 }
 
+#[cfg(feature = "amadeus-serde")]
+impl<D, Row> Destination for CsvDestination<D, Row>
+where
+	D: amadeus_core::file::OutputDirectory,
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
+#[cfg(feature = "amadeus-serde")]
+impl<D, Row> Destination for JsonDestination<D, Row>
+where
+	D: amadeus_core::file::OutputDirectory,
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
+#[cfg(feature = "parquet")]
+impl<D, Row> Destination for ParquetDestination<D, Row>
+where
+	D: amadeus_core::file::OutputDirectory,
+	Row: super::data::Data,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
+#[cfg(feature = "postgres")]
+impl<Row> Destination for PostgresDestination<Row>
+where
+	Row: super::data::Data + amadeus_postgres::PostgresRow,
+{
+	type Item = <Self as amadeus_core::Destination>::Item;
+	type Error = <Self as amadeus_core::Destination>::Error;
+
+	type ParSink = <Self as amadeus_core::Destination>::ParSink;
+	type DistSink = <Self as amadeus_core::Destination>::DistSink;
+
+	fn par_sink(self) -> Self::ParSink {
+		<Self as amadeus_core::Destination>::par_sink(self)
+	}
+	fn dist_sink(self) -> Self::DistSink {
+		<Self as amadeus_core::Destination>::dist_sink(self)
+	}
+}
+
 #[pin_project]
 #[derive(new)]
 pub struct IntoStream<I, U>(#[pin] I, PhantomData<fn() -> U>);
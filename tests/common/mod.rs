@@ -0,0 +1,41 @@
+//! Rows and helpers shared by the tests that write to destinations and read the
+//! output back.
+
+#![allow(dead_code)]
+
+use std::{
+	fs, path::{Path, PathBuf}
+};
+
+use amadeus::prelude::*;
+
+#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+pub struct Written {
+	pub id: i64,
+	pub name: String,
+	pub score: Option<f64>,
+}
+
+/// `n` rows with ascending ids, every third of which has no score.
+pub fn written(n: i64) -> Vec<Written> {
+	(0..n)
+		.map(|id| Written {
+			id,
+			name: format!("row {}", id),
+			score: if id % 3 == 0 {
+				None
+			} else {
+				Some(id as f64 / 8.0)
+			},
+		})
+		.collect()
+}
+
+/// The committed part files with the given extension in `dir`.
+pub fn part_files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+	fs::read_dir(dir)
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().map_or(false, |e| e == extension))
+		.collect()
+}
@@ -1,8 +1,13 @@
 #![allow(clippy::suspicious_map)]
 
-use std::{path::PathBuf, time::SystemTime};
+mod common;
+
+use std::{
+	env, fs, path::PathBuf, process, sync::atomic::{AtomicUsize, Ordering}, time::SystemTime
+};
 
 use amadeus::prelude::*;
+use common::{part_files, written, Written};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -58,5 +63,123 @@ async fn csv() {
 		100_000
 	);
 
+	let written = written(10_000);
+	let dir = env::temp_dir().join(format!("amadeus-csv-{}", process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	static CONSUMED: AtomicUsize = AtomicUsize::new(0);
+	let write = |mode, has_headers| {
+		let (written, dir) = (written.clone(), dir.clone());
+		async move {
+			let destination = CsvDestination::new(dir)
+				.await?
+				.has_headers(has_headers)
+				.mode(mode);
+			written
+				.into_par_stream()
+				.inspect(|_: &Written| {
+					let _ = CONSUMED.fetch_add(1, Ordering::Relaxed);
+				})
+				.pipe(pool, destination.par_sink())
+				.await
+		}
+	};
+	let read = || async {
+		let mut rows: Vec<Written> = Csv::<_, Written>::new(part_files(&dir, "csv"))
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+		rows.sort_by_key(|row| row.id);
+		rows
+	};
+
+	write(WriteMode::FailIfExists, false).await.unwrap();
+	assert!(dir.join("_SUCCESS").exists());
+	assert_eq!(read().await, written);
+	let consumed = CONSUMED.load(Ordering::Relaxed);
+	assert!(write(WriteMode::FailIfExists, false).await.is_err());
+	assert_eq!(CONSUMED.load(Ordering::Relaxed), consumed);
+	assert_eq!(read().await, written);
+	write(WriteMode::Append, false).await.unwrap();
+	assert_eq!(read().await.len(), written.len() * 2);
+	write(WriteMode::Overwrite, false).await.unwrap();
+	assert_eq!(read().await, written);
+
+	write(WriteMode::Overwrite, true).await.unwrap();
+	for entry in fs::read_dir(&dir).unwrap() {
+		let path = entry.unwrap().path();
+		if path
+			.extension()
+			.map_or(false, |extension| extension == "csv")
+		{
+			let contents = fs::read_to_string(path).unwrap();
+			assert_eq!(contents.lines().next(), Some("id,name,score"));
+		}
+	}
+	let mut rows: Vec<Written> = CsvBuilder::new()
+		.has_headers(true)
+		.build::<_, Written>(Splits::new(part_files(&dir, "csv"), 1000).header(true))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	rows.sort_by_key(|row| row.id);
+	assert_eq!(rows, written);
+	fs::remove_dir_all(&dir).unwrap();
+
+	let file = env::temp_dir().join(format!("amadeus-csv-{}.tsv", process::id()));
+	fs::write(
+		&file,
+		"# exported\nscore\tid\tname\n\t0\trow 0\n0.125\t1\t row 1 \n",
+	)
+	.unwrap();
+	let rows: Vec<Written> = CsvBuilder::new()
+		.delimiter(b'\t')
+		.has_headers(true)
+		.trim(Trim::Fields)
+		.comment(Some(b'#'))
+		.build::<_, Written>(vec![file.clone()])
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	assert_eq!(rows, written[..2]);
+
+	// Records larger than the chunks files are read in
+	let written = (0..5_i64)
+		.map(|id| Written {
+			id,
+			name: format!("{},\n\"{}", id, "x".repeat(700_000)),
+			score: Some(id as f64),
+		})
+		.collect::<Vec<_>>();
+	let contents = written
+		.iter()
+		.map(|row| {
+			format!(
+				"{},\"{}\",{}\n",
+				row.id,
+				row.name.replace('"', "\"\""),
+				row.score.unwrap()
+			)
+		})
+		.collect::<String>();
+	fs::write(&file, contents).unwrap();
+	let rows: Vec<Written> = Csv::<_, Written>::new(vec![file.clone()])
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	assert_eq!(rows, written);
+	fs::remove_file(&file).unwrap();
+
 	println!("in {:?}", start.elapsed().unwrap());
 }
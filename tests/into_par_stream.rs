@@ -1,6 +1,9 @@
 use either::Either;
+use std::{collections::BTreeMap, panic, sync::Arc, time::Duration};
 
-use amadeus::prelude::*;
+use amadeus::{
+	par_sink::Timestamp, par_stream::{forward_cancels, ForwardCancel}, prelude::*
+};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -34,3 +37,559 @@ async fn into_par_stream() {
 	let sum: usize = slice.iter().cloned().par().sum(&pool).await;
 	assert_eq!(sum, slice.iter().sum::<usize>());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn sort_by() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	// Small enough a buffer that the workers spill, with many duplicates
+	let rows = (0..100_000_u64)
+		.map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % 10_000)
+		.collect::<Vec<_>>();
+	let mut expected = rows.clone();
+	expected.sort();
+	let sorted = rows
+		.clone()
+		.into_par_stream()
+		.sort_by(&pool, 1000, |a: &u64, b: &u64| a.cmp(b))
+		.await
+		.unwrap();
+	assert_eq!(sorted.len(), rows.len());
+	assert_eq!(
+		sorted.into_iter().collect::<Result<Vec<_>, _>>().unwrap(),
+		expected
+	);
+	let sorted = rows
+		.into_par_stream()
+		.sort_by(&pool, 1000, |a: &u64, b: &u64| b.cmp(a))
+		.await
+		.unwrap();
+	assert!(sorted.ranges() > 1);
+	let res: Vec<u64> = sorted
+		.into_par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(&pool)
+		.await;
+	expected.reverse();
+	assert_eq!(res, expected);
+	let rows = (0..10_000_u64).map(|i| i % 10).collect::<Vec<_>>();
+	let sorted = rows
+		.clone()
+		.into_par_stream()
+		.sort_by(&pool, 100, |a: &u64, b: &u64| a.cmp(b))
+		.await
+		.unwrap();
+	assert!(sorted.ranges() <= 10);
+	let mut expected = rows;
+	expected.sort();
+	assert_eq!(
+		sorted.into_iter().collect::<Result<Vec<_>, _>>().unwrap(),
+		expected
+	);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn hash_join() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let left = (0..1000_u64).map(|i| (i % 50, i)).collect::<Vec<_>>();
+	let right = (0..100_u64)
+		.map(|i| (25 + i % 50, format!("{}", i)))
+		.collect::<Vec<_>>();
+	for &kind in &[
+		JoinKind::Inner,
+		JoinKind::Left,
+		JoinKind::Right,
+		JoinKind::Full,
+		JoinKind::Semi,
+		JoinKind::Anti,
+	] {
+		let mut groups = BTreeMap::<u64, (Vec<u64>, Vec<String>)>::new();
+		for (key, value) in &left {
+			groups.entry(*key).or_default().0.push(*value);
+		}
+		for (key, value) in &right {
+			groups.entry(*key).or_default().1.push(value.clone());
+		}
+		let expected = groups
+			.into_iter()
+			.filter_map(|(key, (left, right))| {
+				let yielded = match kind {
+					JoinKind::Inner | JoinKind::Semi => !left.is_empty() && !right.is_empty(),
+					JoinKind::Left => !left.is_empty(),
+					JoinKind::Right => !right.is_empty(),
+					JoinKind::Full => true,
+					JoinKind::Anti => !left.is_empty() && right.is_empty(),
+				};
+				let right = match kind {
+					JoinKind::Semi | JoinKind::Anti => Vec::new(),
+					_ => right,
+				};
+				Some((key, left, right)).filter(|_| yielded)
+			})
+			.collect::<Vec<_>>();
+		let mut res: Vec<(u64, Vec<u64>, Vec<String>)> = left
+			.clone()
+			.into_par_stream()
+			.hash_join(&pool, right.clone(), kind, 7)
+			.await
+			.unwrap()
+			.map(|row: Result<_, _>| {
+				let (key, left, right) = row.unwrap();
+				let mut left = left.collect::<Vec<_>>();
+				let mut right = right.collect::<Vec<_>>();
+				left.sort();
+				right.sort_by_key(|value| value.parse::<u64>().unwrap());
+				(key, left, right)
+			})
+			.collect(&pool)
+			.await;
+		res.sort();
+		assert_eq!(res, expected, "{:?}", kind);
+	}
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn repartition_by() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let rows = (0..10_000_u64).collect::<Vec<_>>();
+	let repartitioned = rows
+		.clone()
+		.into_par_stream()
+		.repartition_by(&pool, |i: &u64| i % 100, 6)
+		.await
+		.unwrap();
+	assert_eq!(repartitioned.size_hint(), (6, Some(6)));
+	let mut res: Vec<u64> = repartitioned
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, rows);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn windows() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let minute = |minute: i64| DateTime::from_nanos(minute * 60_000_000_000);
+	let res = (0..60_i64)
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity.tumbling_window(
+				move |i: &i64| minute(*i),
+				Duration::from_secs(600),
+				Identity.sum::<i64>(),
+			),
+		)
+		.await;
+	assert_eq!(
+		res.into_iter().collect::<Vec<_>>(),
+		(0..6)
+			.map(|i| (minute(i * 10), (i * 10..i * 10 + 10).sum()))
+			.collect::<Vec<_>>()
+	);
+	let res = (0..60_i64)
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity.sliding_window(
+				move |i: &i64| minute(*i),
+				Duration::from_secs(600),
+				Duration::from_secs(300),
+				Identity.count(),
+			),
+		)
+		.await;
+	assert_eq!(
+		res.into_iter().collect::<Vec<_>>(),
+		(-1..12)
+			.map(|i| (minute(i * 5), if i == -1 || i == 11 { 5 } else { 10 }))
+			.collect::<Vec<_>>()
+	);
+	let res = [0, 1, 4, 20, 22, 40, 44, 49, 53]
+		.iter()
+		.map(|&i| minute(i))
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity.session_window(
+				|time: &DateTime| *time,
+				Duration::from_secs(300),
+				Identity.count(),
+			),
+		)
+		.await;
+	assert_eq!(
+		res.into_iter().collect::<Vec<_>>(),
+		vec![
+			(minute(0), 3),
+			(minute(20), 2),
+			(minute(40), 2),
+			(minute(49), 2)
+		]
+	);
+	for (size, slide) in [
+		(Duration::from_secs(0), Duration::from_secs(1)),
+		(Duration::from_secs(1), Duration::from_secs(0)),
+		(Duration::from_secs(u64::MAX), Duration::from_secs(1)),
+	]
+	.iter()
+	.copied()
+	{
+		let window = panic::catch_unwind(|| {
+			ParallelPipe::<i64>::sliding_window(
+				Identity,
+				move |i: &i64| minute(*i),
+				size,
+				slide,
+				Identity.count(),
+			)
+		});
+		assert!(window.is_err());
+	}
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn quantiles() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let res = (0..10_000)
+		.map(f64::from)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.quantiles(&pool, &[0.0, 0.5, 0.99, 1.0])
+		.await;
+	assert_eq!(res[0], Some(0.0));
+	assert!((res[1].unwrap() - 5_000.0).abs() < 50.0);
+	assert!((res[2].unwrap() - 9_900.0).abs() < 10.0);
+	assert_eq!(res[3], Some(9_999.0));
+	let res = Vec::<f64>::new()
+		.into_par_stream()
+		.quantiles(&pool, &[0.5])
+		.await;
+	assert_eq!(res, vec![None]);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn count_distinct_approx() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let res = (0..10_000_usize)
+		.map(|i| i % 1000)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.count_distinct_approx(&pool, 0.01)
+		.await;
+	assert!((res - 1000.0).abs() < 50.0, "{}", res);
+	let res = (0..10_000_usize)
+		.map(|i| (i % 2, i / 2 % 500))
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity.group_by(Identity.count_distinct_approx(0.01)),
+		)
+		.await;
+	assert_eq!(res.len(), 2);
+	assert!((res[&0] - 500.0).abs() < 25.0, "{}", res[&0]);
+	assert!((res[&1] - 500.0).abs() < 25.0, "{}", res[&1]);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn distinct() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let rows = (0..10_000_u64).collect::<Vec<_>>();
+	let mut res: Vec<u64> = rows
+		.iter()
+		.map(|i| i % 1000)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.distinct(&pool, 6)
+		.await
+		.unwrap()
+		.map(|i: Result<u64, _>| i.unwrap() * 2)
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, (0..1000).map(|i| i * 2).collect::<Vec<_>>());
+	let mut res: Vec<u64> = rows
+		.clone()
+		.into_par_stream()
+		.dedup_by_key(&pool, |i: &u64| i % 7, 3)
+		.await
+		.unwrap()
+		.map(|i: Result<u64, _>| i.unwrap() % 7)
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, (0..7).collect::<Vec<_>>());
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn take() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let rows = (0..10_000_u64).collect::<Vec<_>>();
+	let res = rows.clone().into_par_stream().take(&pool, 20).await;
+	assert_eq!(res, (0..20).collect::<Vec<_>>());
+	let res = rows.clone().into_par_stream().take(&pool, 20_000).await;
+	assert_eq!(res, rows);
+	let res = rows.clone().into_par_stream().first(&pool).await;
+	assert_eq!(res, Some(0));
+	let res = rows
+		.clone()
+		.into_par_stream()
+		.find(&pool, |i: &u64| i % 1000 == 999)
+		.await;
+	assert_eq!(res, Some(999));
+	let res = rows
+		.clone()
+		.into_par_stream()
+		.find_map(&pool, |i: u64| if i > 5000 { Some(i * 2) } else { None })
+		.await;
+	assert_eq!(res, Some(10002));
+	let res = rows
+		.clone()
+		.into_par_stream()
+		.find(&pool, |i: &u64| *i > 10_000)
+		.await;
+	assert_eq!(res, None);
+	let odd: Vec<u64> = rows
+		.clone()
+		.into_par_stream()
+		.filter(|i: &u64| i % 2 == 1)
+		.collect(&pool)
+		.await;
+	let res: u64 = rows
+		.clone()
+		.into_par_stream()
+		.filter(|i: &u64| i % 2 == 1)
+		.limit(&pool, 10)
+		.await
+		.sum(&pool)
+		.await;
+	assert_eq!(res, odd[..10].iter().sum::<u64>());
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn cancel() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let cancel = Cancel::new();
+	let res = cancel
+		.run(
+			(0..10_000)
+				.collect::<Vec<_>>()
+				.into_par_stream()
+				.with_cancel(&cancel)
+				.count(&pool),
+		)
+		.await;
+	assert_eq!(res, Ok(10_000));
+	let res = cancel
+		.run(
+			(0..10_000)
+				.collect::<Vec<_>>()
+				.into_par_stream()
+				.with_cancel(&cancel)
+				.inspect({
+					let cancel = cancel.clone();
+					move |&i| {
+						if i == 100 {
+							cancel.cancel();
+						}
+					}
+				})
+				.collect::<_, Vec<_>>(&pool),
+		)
+		.await;
+	assert_eq!(res, Err(Cancelled));
+	let res = (0..10_000)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.with_cancel(&cancel)
+		.count(&pool)
+		.await;
+	assert_eq!(res, 0);
+	let cancel = Cancel::with_timeout(Duration::from_secs(0));
+	let res = cancel
+		.run(
+			(0..10_000)
+				.collect::<Vec<_>>()
+				.into_par_stream()
+				.with_cancel(&cancel)
+				.count(&pool),
+		)
+		.await;
+	assert_eq!(res, Err(Cancelled));
+
+	// Pools can register while a cancellation is forwarded to the others
+	struct Register;
+	impl ForwardCancel for Register {
+		fn forward_cancel(self: Arc<Self>, _id: u64) {
+			forward_cancels(Arc::downgrade(
+				&(Arc::new(Register) as Arc<dyn ForwardCancel>),
+			));
+		}
+	}
+	let register = Arc::new(Register) as Arc<dyn ForwardCancel>;
+	forward_cancels(Arc::downgrade(&register));
+	Cancel::new().cancel();
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn progress() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let progress = Progress::new();
+	let res = (0..10_000)
+		.collect::<Vec<_>>()
+		.into_par_stream()
+		.with_progress(&progress)
+		.count(&pool)
+		.await;
+	assert_eq!(res, 10_000);
+	let metrics = progress.metrics();
+	assert!(metrics.is_done());
+	assert_eq!(metrics.items, 10_000);
+	assert_eq!(metrics.tasks_failed, 0);
+	assert_eq!(metrics.tasks_completed, metrics.tasks_dispatched);
+	assert_eq!(metrics.fraction_done(), Some(1.0));
+	assert_eq!(metrics.eta(), Some(Duration::from_secs(0)));
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn errors() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let rows = (0..1000_u32)
+		.map(|i| {
+			if i % 100 != 0 {
+				Ok(i)
+			} else {
+				Err(format!("bad row {}", i))
+			}
+		})
+		.collect::<Vec<_>>();
+	let res: Vec<u32> = rows
+		.clone()
+		.into_par_stream()
+		.skip_errors()
+		.collect(&pool)
+		.await;
+	assert_eq!(res.len(), 990);
+	let (count, errors) = rows
+		.clone()
+		.into_par_stream()
+		.collect_errors(&pool, 3, Identity.count())
+		.await;
+	assert_eq!(count, 990);
+	assert_eq!(errors.count, 10);
+	assert_eq!(errors.errors.len(), 3);
+	assert!(errors
+		.to_string()
+		.starts_with("10 errors, the first: bad row "));
+	let (_, errors) = rows[..100]
+		.to_vec()
+		.into_par_stream()
+		.collect_errors(&pool, 3, Identity.count())
+		.await;
+	assert_eq!(errors.to_string(), "1 error, the first: bad row 0");
+	let res = rows
+		.clone()
+		.into_par_stream()
+		.fail_after(&pool, 10, Identity.count())
+		.await;
+	assert_eq!(res, Ok(990));
+	let res = rows
+		.clone()
+		.into_par_stream()
+		.fail_after(&pool, 5, Identity.count())
+		.await
+		.unwrap_err();
+	assert!(res.count > 5 && res.count <= 10);
+	assert_eq!(res.errors.len(), 6);
+	let (count, mut errors): (usize, Vec<String>) = rows
+		.into_par_stream()
+		.pipe(
+			&pool,
+			Identity.split_errors(Identity.count(), Identity.collect()),
+		)
+		.await;
+	errors.sort();
+	assert_eq!(count, 990);
+	assert_eq!(errors.len(), 10);
+	assert_eq!(errors[0], "bad row 0");
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn group_by_spilling() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	let rows = (0..10_000_u64).collect::<Vec<_>>();
+	let grouped = rows
+		.clone()
+		.into_par_stream()
+		.map(|i: u64| (i % 1000, i))
+		.group_by(&pool, Identity.collect::<Vec<u64>>())
+		.await;
+	// The values of a group are in the order the workers' partial aggregates finished
+	let mut expected = grouped
+		.into_iter()
+		.map(|(key, mut values)| {
+			values.sort();
+			(key, values)
+		})
+		.collect::<Vec<_>>();
+	expected.sort();
+	let mut res: Vec<(u64, Vec<u64>)> = rows
+		.clone()
+		.into_par_stream()
+		.map(|i: u64| (i % 1000, i))
+		.group_by_spilling(&pool, Identity.collect::<Vec<u64>>(), 100, 4)
+		.await
+		.unwrap()
+		.map(|row: Result<(u64, Vec<u64>), _>| {
+			let (key, mut values) = row.unwrap();
+			values.sort();
+			(key, values)
+		})
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, expected);
+	let mut res: Vec<(u64, u64)> = rows
+		.clone()
+		.into_par_stream()
+		.map(|i: u64| (i % 10, i))
+		.group_by_spilling(&pool, Identity.sum::<u64>(), 3, 2)
+		.await
+		.unwrap()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(
+		res,
+		(0..10)
+			.map(|k| (k, rows.iter().filter(|&&i| i % 10 == k).sum()))
+			.collect::<Vec<_>>()
+	);
+}
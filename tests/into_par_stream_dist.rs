@@ -1,9 +1,13 @@
 #[cfg(feature = "constellation")]
 use constellation::*;
 use either::Either;
-use std::time::{Duration, SystemTime};
+use futures::{future, stream, FutureExt};
+use std::{
+	env, fs, panic::AssertUnwindSafe, process, sync::atomic::{AtomicBool, AtomicUsize, Ordering}, time::{Duration, SystemTime}
+};
+use tokio::time::delay_for;
 
-use amadeus::dist::prelude::*;
+use amadeus::{dist::prelude::*, par_sink::Timestamp, par_stream::ImplIter};
 
 fn main() {
 	if cfg!(miri) {
@@ -22,6 +26,7 @@ fn main() {
 				let thread_pool = ThreadPool::new(None, None).unwrap();
 				run(&thread_pool).await
 			};
+			checkpoint().await;
 			#[cfg(feature = "constellation")]
 			let process_pool_time = {
 				let process_pool =
@@ -65,5 +70,289 @@ async fn run<P: amadeus_core::pool::ProcessPool>(pool: &P) -> Duration {
 	let sum: usize = slice.iter().cloned().dist().sum(&pool).await;
 	assert_eq!(sum, slice.iter().sum::<usize>());
 
+	sort_by(pool).await;
+	hash_join(pool).await;
+	repartition_by(pool).await;
+	distinct(pool).await;
+	group_by_spilling(pool).await;
+	take(pool).await;
+	windows(pool).await;
+	quantiles(pool).await;
+	count_distinct_approx(pool).await;
+	cancel(pool).await;
+	progress(pool).await;
+	errors(pool).await;
+
 	start.elapsed().unwrap()
 }
+
+async fn sort_by<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let rows = (0..10_000_usize)
+		.map(|i| i.wrapping_mul(2_654_435_761) % 1000)
+		.collect::<Vec<_>>();
+	let mut expected = rows.clone();
+	expected.sort();
+	let sorted = rows
+		.into_dist_stream()
+		.sort_by(&pool, 1000, Fn!(|a: &usize, b: &usize| a.cmp(b)))
+		.await
+		.unwrap();
+	let res: Vec<usize> = sorted
+		.into_dist_stream()
+		.map(Fn!(|row: Result<usize, _>| row.unwrap()))
+		.collect(&pool)
+		.await;
+	assert_eq!(res, expected);
+}
+
+async fn hash_join<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let mut res: Vec<(usize, usize, usize)> = (0..1000_usize)
+		.map(|i| (i % 100, i))
+		.dist()
+		.hash_join(
+			&pool,
+			(50..150_usize).map(|i| (i, i * 2)).collect::<Vec<_>>(),
+			JoinKind::Inner,
+			4,
+		)
+		.await
+		.unwrap()
+		.map(FnMut!(|row: Result<
+			(usize, ImplIter<usize>, ImplIter<usize>),
+			_,
+		>| {
+			let (key, left, right) = row.unwrap();
+			(key, left.count(), right.sum::<usize>())
+		}))
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, (50..100).map(|i| (i, 10, i * 2)).collect::<Vec<_>>());
+}
+
+async fn repartition_by<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let mut res: Vec<usize> = (0..1000_usize)
+		.dist()
+		.repartition_by(&pool, FnMut!(|i: &usize| i % 10), 4)
+		.await
+		.unwrap()
+		.map(FnMut!(|row: Result<usize, _>| row.unwrap()))
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, (0..1000).collect::<Vec<_>>());
+}
+
+async fn windows<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let res = (0..600_i64)
+		.dist()
+		.pipe(
+			&pool,
+			Identity.session_window(
+				Fn!(|i: &i64| DateTime::from_nanos((i / 100 * 10 + i % 100 / 50) * 60_000_000_000)),
+				Duration::from_secs(300),
+				Identity.count(),
+			),
+		)
+		.await;
+	assert_eq!(
+		res.into_iter().collect::<Vec<_>>(),
+		(0..6)
+			.map(|i| (DateTime::from_nanos(i * 600_000_000_000), 100))
+			.collect::<Vec<_>>()
+	);
+}
+
+async fn quantiles<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let res = (0..10_000_u32)
+		.dist()
+		.map(FnMut!(|i: u32| f64::from(i)))
+		.quantiles(&pool, &[0.5, 0.99])
+		.await;
+	assert!((res[0].unwrap() - 5_000.0).abs() < 50.0);
+	assert!((res[1].unwrap() - 9_900.0).abs() < 10.0);
+}
+
+async fn count_distinct_approx<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let res = (0..10_000_usize)
+		.map(|i| (i % 2, i % 1000))
+		.collect::<Vec<_>>()
+		.into_dist_stream()
+		.pipe(
+			&pool,
+			Identity.group_by(Identity.count_distinct_approx(0.01)),
+		)
+		.await;
+	assert_eq!(res.len(), 2);
+	assert!((res[&0] - 500.0).abs() < 25.0, "{}", res[&0]);
+	assert!((res[&1] - 500.0).abs() < 25.0, "{}", res[&1]);
+}
+
+async fn distinct<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let mut res: Vec<usize> = (0..1000_usize)
+		.dist()
+		.map(FnMut!(|i: usize| i % 100))
+		.distinct(&pool, 4)
+		.await
+		.unwrap()
+		.map(FnMut!(|row: Result<usize, _>| row.unwrap()))
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(res, (0..100).collect::<Vec<_>>());
+}
+
+async fn take<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let res = (0..1000_usize)
+		.dist()
+		.find(&pool, FnMut!(|i: &usize| i % 100 == 42))
+		.await;
+	assert_eq!(res, Some(42));
+	let res = (0..1000_usize).dist().take(&pool, 5).await;
+	assert_eq!(res, vec![0, 1, 2, 3, 4]);
+}
+
+async fn cancel<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let cancel = Cancel::new();
+	let res = cancel
+		.run((0..10_000_u32).dist().with_cancel(&cancel).count(&pool))
+		.await;
+	assert_eq!(res, Ok(10_000));
+	let cancel = Cancel::with_timeout(Duration::from_secs(0));
+	let res = cancel
+		.run((0..10_000_u32).dist().with_cancel(&cancel).count(&pool))
+		.await;
+	assert_eq!(res, Err(Cancelled));
+	// Tasks waiting on rows are woken when cancelled, including on other processes
+	let cancel = Cancel::new();
+	let (res, ()) = future::join(
+		(0..4_u32)
+			.dist()
+			.flat_map(FnMut!(|_: u32| stream::pending::<u32>()))
+			.with_cancel(&cancel)
+			.count(&pool),
+		async {
+			delay_for(Duration::from_millis(100)).await;
+			cancel.cancel();
+		},
+	)
+	.await;
+	assert_eq!(res, 0);
+	let cancel = Cancel::with_timeout(Duration::from_millis(100));
+	let res = (0..4_u32)
+		.dist()
+		.flat_map(FnMut!(|_: u32| stream::pending::<u32>()))
+		.with_cancel(&cancel)
+		.count(&pool)
+		.await;
+	assert_eq!(res, 0);
+	let cancel = Cancel::new();
+	let (res, ()) = future::join(
+		cancel.run(
+			(0..4_u32)
+				.dist()
+				.flat_map(FnMut!(|_: u32| stream::pending::<u32>()))
+				.count(&pool),
+		),
+		async {
+			delay_for(Duration::from_millis(100)).await;
+			cancel.cancel();
+		},
+	)
+	.await;
+	assert_eq!(res, Err(Cancelled));
+}
+
+async fn progress<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let progress = Progress::new();
+	let res = (0..10_000_u32)
+		.dist()
+		.with_progress(&progress)
+		.count(&pool)
+		.await;
+	assert_eq!(res, 10_000);
+	let metrics = progress.metrics();
+	assert!(metrics.tasks_dispatched > 0);
+	assert_eq!(metrics.tasks_completed, metrics.tasks_dispatched);
+	assert_eq!(metrics.items, 10_000);
+}
+
+async fn errors<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let res = (0..1000_u32)
+		.dist()
+		.map(FnMut!(|i: u32| if i % 100 != 0 {
+			Ok(i)
+		} else {
+			Err(format!("bad row {}", i))
+		}))
+		.fail_after(&pool, 5, Identity.count())
+		.await
+		.unwrap_err();
+	assert!(res.count > 5 && res.count <= 10);
+	assert_eq!(res.errors.len(), 6);
+}
+
+async fn group_by_spilling<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let mut res: Vec<(usize, Vec<usize>)> = (0..1000_usize)
+		.dist()
+		.map(FnMut!(|i: usize| (i % 100, i)))
+		.group_by_spilling(&pool, Identity.collect::<Vec<usize>>(), 10, 4)
+		.await
+		.unwrap()
+		.map(FnMut!(|row: Result<(usize, Vec<usize>), _>| {
+			let (key, mut values) = row.unwrap();
+			values.sort();
+			(key, values)
+		}))
+		.collect(&pool)
+		.await;
+	res.sort();
+	assert_eq!(
+		res,
+		(0..100)
+			.map(|k| (k, (0..10).map(|i| k + i * 100).collect()))
+			.collect::<Vec<_>>()
+	);
+}
+
+async fn checkpoint() {
+	static FAIL: AtomicBool = AtomicBool::new(true);
+	static RUN: AtomicUsize = AtomicUsize::new(0);
+
+	let dir = env::temp_dir().join(format!("amadeus-checkpoint-{}", process::id()));
+	let checkpoint = Checkpoint::new(&dir).unwrap();
+	let pool = ThreadPool::new(None, None)
+		.unwrap()
+		.with_checkpoint(checkpoint.clone());
+	let stream = || {
+		(0i64..1_000)
+			.into_dist_stream()
+			.flat_map(FnMut!(|i| stream::once(async move {
+				if i == 950 && FAIL.load(Ordering::Relaxed) {
+					tokio::time::delay_for(Duration::from_secs(1)).await;
+					panic!("this is intended to panic");
+				}
+				let _ = RUN.fetch_add(1, Ordering::Relaxed);
+				i
+			})))
+	};
+
+	let res = AssertUnwindSafe(stream().collect::<_, Vec<_>>(&pool))
+		.catch_unwind()
+		.await;
+	assert!(res.is_err());
+
+	FAIL.store(false, Ordering::Relaxed);
+	RUN.store(0, Ordering::Relaxed);
+	let res: Vec<_> = stream().collect(&pool).await;
+	assert_eq!(res, (0..1_000).collect::<Vec<_>>());
+	assert!(RUN.load(Ordering::Relaxed) < 1_000);
+
+	RUN.store(0, Ordering::Relaxed);
+	let res: Vec<_> = stream().collect(&pool).await;
+	assert_eq!(res, (0..1_000).collect::<Vec<_>>());
+	assert_eq!(RUN.load(Ordering::Relaxed), 0);
+
+	checkpoint.clear().unwrap();
+	fs::remove_dir(dir).unwrap();
+}
@@ -1,8 +1,11 @@
 #![allow(clippy::suspicious_map)]
 
-use std::{path::PathBuf, time::SystemTime};
+mod common;
 
-use amadeus::prelude::*;
+use std::{env, fmt, fs, path::PathBuf, process, time::SystemTime};
+
+use amadeus::{par_sink::RecordError, prelude::*};
+use common::{part_files, written, Written};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -84,5 +87,137 @@ async fn json() {
 	);
 	println!("b: {:?}", b.elapsed().unwrap());
 
+	let written = written(10_000);
+	let dir = env::temp_dir().join(format!("amadeus-json-{}", process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	written
+		.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			JsonDestination::new(dir.clone()).await.unwrap().par_sink(),
+		)
+		.await
+		.unwrap();
+	assert!(dir.join("_SUCCESS").exists());
+	let mut read: Vec<Written> = Json::<_, Written>::new(part_files(&dir, "json"))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	read.sort_by_key(|row| row.id);
+	assert_eq!(read, written);
+
+	let progress = Progress::new();
+	let count = Json::<_, Written>::new(part_files(&dir, "json"))
+		.await
+		.unwrap()
+		.par_stream()
+		.with_progress(&progress)
+		.count(pool)
+		.await;
+	assert_eq!(count, written.len());
+	let metrics = progress.metrics();
+	assert!(metrics.is_done());
+	assert_eq!(metrics.items, written.len() as u64);
+	assert_eq!(
+		metrics.bytes,
+		part_files(&dir, "json")
+			.iter()
+			.map(|path| fs::metadata(path).unwrap().len())
+			.sum::<u64>()
+	);
+
+	#[derive(Clone, Debug)]
+	struct BadRecord(String);
+	impl fmt::Display for BadRecord {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			f.write_str("bad record")
+		}
+	}
+	impl RecordError for BadRecord {
+		fn record(&self) -> Option<&str> {
+			Some(&self.0)
+		}
+	}
+	let dead_letters = env::temp_dir().join(format!("amadeus-json-dead-{}", process::id()));
+	let _ = fs::remove_dir_all(&dead_letters);
+	let dead_letter_destination = JsonDestination::new(dead_letters.clone()).await.unwrap();
+	let (count, res) = vec![Ok(1_u64), Err(BadRecord(String::from("{id: 2"))), Ok(3)]
+		.into_par_stream()
+		.pipe(
+			pool,
+			Identity.split_errors(
+				Identity.count(),
+				Identity
+					.map(|err: BadRecord| DeadLetter::new(&err))
+					.pipe(dead_letter_destination.par_sink()),
+			),
+		)
+		.await;
+	res.unwrap();
+	assert_eq!(count, 2);
+	let read: Vec<DeadLetter> = Json::<_, DeadLetter>::new(part_files(&dead_letters, "json"))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	assert_eq!(
+		read,
+		vec![DeadLetter {
+			record: Some(String::from("{id: 2")),
+			error: String::from("bad record"),
+		}]
+	);
+	fs::remove_dir_all(&dead_letters).unwrap();
+
+	// Byte ranges that split lines resync to the next line
+	let mut read: Vec<Written> =
+		Json::<_, Written>::new(Splits::new(part_files(&dir, "json"), 1000))
+			.await
+			.unwrap()
+			.par_stream()
+			.map(|row: Result<_, _>| row.unwrap())
+			.collect(pool)
+			.await;
+	read.sort_by_key(|row| row.id);
+	assert_eq!(read, written);
+	fs::remove_dir_all(&dir).unwrap();
+
+	// Values larger than the chunks files are read in
+	let written = (0..5_i64)
+		.map(|id| Written {
+			id,
+			name: format!("{}\n\"{}", id, "x".repeat(700_000)),
+			score: Some(id as f64),
+		})
+		.collect::<Vec<_>>();
+	let contents = written
+		.iter()
+		.map(|row| {
+			format!(
+				"{{\"id\":{},\"name\":\"{}\",\"score\":{}}}\n",
+				row.id,
+				row.name.replace('\n', "\\n").replace('"', "\\\""),
+				row.score.unwrap()
+			)
+		})
+		.collect::<String>();
+	let file = env::temp_dir().join(format!("amadeus-json-{}.json", process::id()));
+	fs::write(&file, contents).unwrap();
+	let read: Vec<Written> = Json::<_, Written>::new(vec![file.clone()])
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	assert_eq!(read, written);
+	fs::remove_file(&file).unwrap();
+
 	println!("in {:?}", start.elapsed().unwrap());
 }
@@ -1,8 +1,8 @@
 #[cfg(feature = "constellation")]
 use constellation::*;
-use futures::FutureExt;
+use futures::{stream, FutureExt};
 use std::{
-	panic, panic::AssertUnwindSafe, time::{Duration, SystemTime}
+	panic, panic::AssertUnwindSafe, sync::atomic::{AtomicBool, Ordering}, time::{Duration, SystemTime}
 };
 
 use amadeus::dist::prelude::*;
@@ -33,6 +33,18 @@ fn main() {
 			#[cfg(not(feature = "constellation"))]
 			let process_pool_time = "-";
 
+			let thread_pool = ThreadPool::new(None, None)
+				.unwrap()
+				.with_retry(RetryPolicy::new().retries(2).speculate(2.0));
+			retry(&thread_pool).await;
+			#[cfg(feature = "constellation")]
+			{
+				let process_pool = ProcessPool::new(None, None, None, Resources::default())
+					.unwrap()
+					.with_retry(RetryPolicy::new().retries(2));
+				lose_process(&process_pool).await;
+			}
+
 			println!("in {:?} {:?}", thread_pool_time, process_pool_time);
 		})
 }
@@ -55,3 +67,65 @@ async fn run<P: amadeus_core::pool::ProcessPool + std::panic::RefUnwindSafe>(poo
 
 	start.elapsed().unwrap()
 }
+
+async fn retry<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	static PANICKED: AtomicBool = AtomicBool::new(false);
+	static DELAYED: AtomicBool = AtomicBool::new(false);
+
+	let sum = (0i64..1_000)
+		.into_dist_stream()
+		.map(FnMut!(|i| {
+			if i == 500 && !PANICKED.swap(true, Ordering::Relaxed) {
+				panic!("this is intended to panic once")
+			}
+			i
+		}))
+		.sum::<_, i64>(pool)
+		.await;
+	assert_eq!(sum, (0..1_000).sum::<i64>());
+
+	let start = SystemTime::now();
+	let sum = (0i64..1_000)
+		.into_dist_stream()
+		.flat_map(FnMut!(|i| stream::once(async move {
+			if i == 999 && !DELAYED.swap(true, Ordering::Relaxed) {
+				tokio::time::delay_for(Duration::from_secs(10)).await;
+			}
+			i
+		})))
+		.sum::<_, i64>(pool)
+		.await;
+	assert_eq!(sum, (0..1_000).sum::<i64>());
+	assert!(start.elapsed().unwrap() < Duration::from_secs(10));
+}
+
+/// One of the pool's processes exits partway through: its tasks are retried on the
+/// others, which the pool carries on with.
+#[cfg(feature = "constellation")]
+async fn lose_process(pool: &ProcessPool) {
+	// A file marks that a process has exited, as statics aren't shared between them
+	let marker = std::env::temp_dir().join(format!("amadeus-lose-process-{}", std::process::id()));
+
+	let sum = (0i64..1_000)
+		.into_dist_stream()
+		.map(FnMut!(move |i| {
+			if i == 500
+				&& std::fs::OpenOptions::new()
+					.write(true)
+					.create_new(true)
+					.open(&marker)
+					.is_ok()
+			{
+				std::process::exit(1)
+			}
+			i
+		}))
+		.sum::<_, i64>(pool)
+		.await;
+	assert_eq!(sum, (0..1_000).sum::<i64>());
+
+	let sum = (0i64..1_000).into_dist_stream().sum::<_, i64>(pool).await;
+	assert_eq!(sum, (0..1_000).sum::<i64>());
+
+	std::fs::remove_file(marker).unwrap();
+}
@@ -5,9 +5,13 @@
 	clippy::suspicious_map
 )]
 
-use std::{collections::HashMap, path::PathBuf, time::SystemTime};
+mod common;
+
+use futures::stream;
+use std::{collections::HashMap, env, fs, path::PathBuf, process, time::SystemTime};
 
 use amadeus::prelude::*;
+use common::{part_files, written, Written};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -1026,5 +1030,165 @@ async fn parquet() {
 		14_444
 	);
 
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Nested {
+		row: Written,
+		tags: List<i32>,
+		inner: NestedInner,
+	}
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct NestedInner {
+		flag: bool,
+		note: Option<String>,
+	}
+
+	let written = written(100_000)
+		.into_iter()
+		.map(|row| Nested {
+			tags: (0..(row.id % 4) as i32).collect::<Vec<_>>().into(),
+			inner: NestedInner {
+				flag: row.id % 2 == 0,
+				note: if row.id % 5 == 0 {
+					Some(row.id.to_string())
+				} else {
+					None
+				},
+			},
+			row,
+		})
+		.collect::<Vec<_>>();
+	let dir = env::temp_dir().join(format!("amadeus-parquet-{}", process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	written
+		.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			ParquetDestination::new(dir.clone())
+				.await
+				.unwrap()
+				.par_sink(),
+		)
+		.await
+		.unwrap();
+	assert!(dir.join("_SUCCESS").exists());
+	let mut read: Vec<Nested> = Parquet::<_, Nested>::new(ParquetDirectory::new(dir.clone()))
+		.await
+		.unwrap()
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+	read.sort_by_key(|nested| nested.row.id);
+	assert_eq!(read, written);
+
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct Projected {
+		inner: ProjectedInner,
+		row: ProjectedRow,
+	}
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct ProjectedInner {
+		note: Option<String>,
+	}
+	#[derive(Data, Clone, PartialEq, Debug)]
+	struct ProjectedRow {
+		id: i64,
+	}
+	let mut projected: Vec<Projected> =
+		Parquet::<_, Value>::new(ParquetDirectory::new(dir.clone()))
+			.await
+			.unwrap()
+			.with_projection(vec!["inner.note", "row.id"])
+			.par_stream()
+			.map(|row: Result<Value, _>| row.unwrap().downcast().unwrap())
+			.collect(pool)
+			.await;
+	projected.sort_by_key(|projected| projected.row.id);
+	assert_eq!(
+		projected,
+		written
+			.iter()
+			.map(|nested| Projected {
+				inner: ProjectedInner {
+					note: nested.inner.note.clone()
+				},
+				row: ProjectedRow { id: nested.row.id },
+			})
+			.collect::<Vec<_>>()
+	);
+	fs::remove_dir_all(&dir).unwrap();
+
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn parquet_filter() {
+	let pool = &ThreadPool::new(None, None).unwrap();
+
+	// Written by a single task, so one file of ascending ids in row groups of 65,536 rows
+	let written = written(200_000);
+	let dir = env::temp_dir().join(format!("amadeus-parquet-filter-{}", process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	vec![written.clone()]
+		.into_par_stream()
+		.flat_map(stream::iter)
+		.pipe(
+			pool,
+			ParquetDestination::new(dir.clone())
+				.await
+				.unwrap()
+				.par_sink(),
+		)
+		.await
+		.unwrap();
+	let files = part_files(&dir, "parquet");
+	assert_eq!(files.len(), 1);
+
+	let read = |filter| {
+		let file = files[0].clone();
+		async move {
+			let mut read: Vec<Written> = Parquet::<_, Written>::new(file)
+				.await
+				.unwrap()
+				.with_filter(filter)
+				.par_stream()
+				.map(|row: Result<_, _>| row.unwrap())
+				.collect(pool)
+				.await;
+			read.sort_by_key(|row| row.id);
+			read
+		}
+	};
+
+	// Only the second row group's statistics match, and all of its rows come back
+	assert_eq!(
+		read(ParquetFilter::range("id", 70_000_i64..80_000)).await,
+		&written[65_536..131_072]
+	);
+	assert_eq!(
+		read(ParquetFilter::eq("id", 0_i64).or(ParquetFilter::eq("id", 199_999_i64))).await,
+		[&written[..65_536], &written[196_608..]].concat()
+	);
+	assert_eq!(read(ParquetFilter::eq("id", -1_i64)).await, []);
+	assert_eq!(read(ParquetFilter::range("id", 0_i64..)).await, written);
+
+	// The column chunks read by the tasks are counted
+	let progress = Progress::new();
+	let count = Parquet::<_, Written>::new(files[0].clone())
+		.await
+		.unwrap()
+		.par_stream()
+		.with_progress(&progress)
+		.count(pool)
+		.await;
+	assert_eq!(count, written.len());
+	let (bytes, len) = (
+		progress.metrics().bytes,
+		fs::metadata(&files[0]).unwrap().len(),
+	);
+	assert!(bytes > len / 2 && bytes <= len, "{} {}", bytes, len);
+
+	fs::remove_dir_all(&dir).unwrap();
+}
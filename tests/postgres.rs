@@ -1,8 +1,11 @@
 #![allow(clippy::suspicious_map)]
 
+mod common;
+
 use std::time::SystemTime;
 
 use amadeus::prelude::*;
+use common::{written, Written};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -60,5 +63,75 @@ async fn postgres() {
 	// 	4
 	// );
 
+	let written = written(10_000);
+	let write = |truncate| {
+		written.clone().into_par_stream().pipe(
+			pool,
+			PostgresDestination::new(
+				"postgres://postgres:a@localhost/alec".parse().unwrap(),
+				"amadeus_written".parse().unwrap(),
+			)
+			.create_table(true)
+			.truncate(truncate)
+			.par_sink(),
+		)
+	};
+	let read = || async {
+		let mut rows: Vec<Written> = Postgres::<Written>::new(vec![(
+			"postgres://postgres:a@localhost/alec".parse().unwrap(),
+			vec![PostgresSelect::Table("amadeus_written".parse().unwrap())],
+		)])
+		.par_stream()
+		.map(|row: Result<_, _>| row.unwrap())
+		.collect(pool)
+		.await;
+		rows.sort_by_key(|row| row.id);
+		rows
+	};
+
+	write(true).await.unwrap();
+	assert_eq!(read().await, written);
+	write(false).await.unwrap();
+	assert_eq!(read().await.len(), written.len() * 2);
+	write(true).await.unwrap();
+	assert_eq!(read().await, written);
+
+	#[derive(Data, Clone, PartialEq, PartialOrd, Debug)]
+	struct Dated {
+		id: i64,
+		date: Option<DateWithoutTimezone>,
+	}
+	let dated = (0..100_i64)
+		.map(|id| Dated {
+			id,
+			date: DateWithoutTimezone::new(2020, 1, 1 + (id % 28) as u8),
+		})
+		.collect::<Vec<_>>();
+	dated
+		.clone()
+		.into_par_stream()
+		.pipe(
+			pool,
+			PostgresDestination::new(
+				"postgres://postgres:a@localhost/alec".parse().unwrap(),
+				"amadeus_dated".parse().unwrap(),
+			)
+			.create_table(true)
+			.truncate(true)
+			.par_sink(),
+		)
+		.await
+		.unwrap();
+	let mut rows: Vec<Dated> = Postgres::<Dated>::new(vec![(
+		"postgres://postgres:a@localhost/alec".parse().unwrap(),
+		vec![PostgresSelect::Table("amadeus_dated".parse().unwrap())],
+	)])
+	.par_stream()
+	.map(|row: Result<_, _>| row.unwrap())
+	.collect(pool)
+	.await;
+	rows.sort_by_key(|row| row.id);
+	assert_eq!(rows, dated);
+
 	println!("in {:?}", start.elapsed().unwrap());
 }